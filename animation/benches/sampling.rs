@@ -0,0 +1,150 @@
+// sampling benchmark, run with `cargo bench`
+// samples a clip with many joints and long tracks, once with a linear scan over every frame,
+// which is what every sample used to do, and once through Clip::sample
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use animation::clip::Clip;
+use animation::curves::Interpolation;
+use animation::frame::Frame;
+use animation::pose::Pose;
+use animation::track::{Cast, Interpolate, Track};
+
+use math::quaternion::Quat;
+use math::vec3::{vec3, Vec3};
+
+const JOINTS: usize = 100;
+const SECONDS: f32 = 60.0;
+const FRAMES_PER_SECOND: f32 = 30.0;
+const UPDATES_PER_SECOND: f32 = 60.0;
+
+fn build_clip() -> Clip {
+    let mut clip = Clip::new();
+    let frames = (SECONDS * FRAMES_PER_SECOND) as usize;
+
+    for joint in 0..JOINTS {
+        let track = clip.get_transform_track(joint as u32);
+        track.position.interpolation = Interpolation::Linear;
+        track.rotation.interpolation = Interpolation::Linear;
+
+        for i in 0..=frames {
+            let time = i as f32 / FRAMES_PER_SECOND;
+
+            let mut position = Frame::<3>::new();
+            position.time = time;
+            position.m_value = [time.sin(), time.cos(), joint as f32];
+            track.position.frames.push(position);
+
+            let mut rotation = Frame::<4>::new();
+            rotation.time = time;
+            rotation.m_value = Quat::create(time * 10.0, vec3(0.0, 1.0, 0.0)).to_array();
+            track.rotation.frames.push(rotation);
+        }
+    }
+    clip.re_calculate_duration();
+
+    clip
+}
+
+/// the old frame lookup, a backwards scan over every frame
+fn linear_scan<const N: usize>(track: &Track<N>, time: f32) -> usize {
+    for i in (0..track.frames.len() - 1).rev() {
+        if time >= track.frames[i].time {
+            return i;
+        }
+    }
+
+    0
+}
+
+/// linear interpolation between the frames found by the old lookup
+fn sample_linear_scan<T: Cast<T> + Interpolate<T>, const N: usize>(
+    track: &Track<N>,
+    time: f32,
+) -> T {
+    let frame = linear_scan(track, time);
+    let this = &track.frames[frame];
+    let next = &track.frames[frame + 1];
+    let t = (time - this.time) / (next.time - this.time);
+
+    T::interpolate(&T::cast(&this.m_value), &T::cast(&next.m_value), t)
+}
+
+/// the same work as Clip::sample with the old lookup
+fn sample_clip_linear_scan(clip: &Clip, out_pose: &mut Pose, time: f32) {
+    let time = clip.adjust_time_to_fit_range(time);
+    for track in &clip.tracks {
+        let joint = &mut out_pose.joints[track.id as usize];
+        joint.translation = sample_linear_scan::<Vec3, 3>(&track.position, time);
+        joint.orientation = sample_linear_scan::<Quat, 4>(&track.rotation, time);
+    }
+}
+
+fn report(name: &str, samples: usize, elapsed: Duration) {
+    println!(
+        "{:<32} {:>10.2} ms {:>8.1} ns per track sample",
+        name,
+        elapsed.as_secs_f64() * 1e3,
+        elapsed.as_secs_f64() * 1e9 / samples as f64
+    );
+}
+
+fn main() {
+    let mut clip = build_clip();
+    let mut pose = Pose::new();
+    pose.resize(JOINTS);
+
+    let updates = (SECONDS * UPDATES_PER_SECOND) as usize;
+    let times = (0..updates)
+        .map(|i| i as f32 / UPDATES_PER_SECOND)
+        .collect::<Vec<f32>>();
+    // a fixed shuffle so every run jumps around the same way
+    let mut scattered = times.clone();
+    for i in 0..scattered.len() {
+        scattered.swap(i, (i * 7919) % times.len());
+    }
+
+    // position and rotation are sampled for every joint on every update
+    let samples = updates * JOINTS * 2;
+    println!(
+        "{} joints, {} frames per track, {} updates",
+        JOINTS,
+        (SECONDS * FRAMES_PER_SECOND) as usize + 1,
+        updates
+    );
+
+    let mut errors = Vec::new();
+    let start = Instant::now();
+    for time in &times {
+        sample_clip_linear_scan(&clip, &mut pose, *time);
+        black_box(&pose);
+    }
+    report("linear scan, playing forward", samples, start.elapsed());
+
+    let start = Instant::now();
+    for time in &times {
+        black_box(clip.sample(&mut pose, *time, &mut errors));
+    }
+    report("clip sample, playing forward", samples, start.elapsed());
+
+    let start = Instant::now();
+    for time in &scattered {
+        sample_clip_linear_scan(&clip, &mut pose, *time);
+        black_box(&pose);
+    }
+    report("linear scan, random times", samples, start.elapsed());
+
+    let start = Instant::now();
+    for time in &scattered {
+        black_box(clip.sample(&mut pose, *time, &mut errors));
+    }
+    report("clip sample, random times", samples, start.elapsed());
+
+    clip.build_lookups(FRAMES_PER_SECOND);
+    let start = Instant::now();
+    for time in &scattered {
+        black_box(clip.sample(&mut pose, *time, &mut errors));
+    }
+    report("clip sample, random with table", samples, start.elapsed());
+}
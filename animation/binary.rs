@@ -0,0 +1,444 @@
+// native binary format for clips, skeletons and transform tracks
+// lets clips pulled out of gltf or collada files be cached and shared without the source model
+//
+// every file starts with a header: the magic bytes, a little endian u16 version and a u8 kind
+// the rest is little endian, floats are stored bit for bit so values round trip exactly
+// strings and lists are prefixed with their length as a u32
+
+use std::fs;
+use std::path::Path;
+
+use super::clip::Clip;
+use super::curves::{Interpolation, LoopMode};
+use super::frame::Frame;
+use super::palette::joint_order;
+use super::pose::Pose;
+use super::skeleton::Skeleton;
+use super::track::Track;
+use super::track_transform::TransformTrack;
+use super::track_weights::WeightsTrack;
+
+use math::mat4::Mat4;
+use math::quaternion::Quat;
+use math::transform::Transform;
+use math::vec3::Vec3;
+
+pub const MAGIC: [u8; 4] = *b"ANIM";
+/// bumped whenever the layout changes, older versions keep loading
+/// 2 added the morph target weights of clips
+pub const VERSION: u16 = 2;
+
+/// something that can be written to and read from the binary format
+pub trait Binary: Sized {
+    /// tells the kinds of file apart so a skeleton is not loaded as a clip
+    const KIND: u8;
+
+    fn write(&self, writer: &mut Writer);
+    fn read(reader: &mut Reader) -> Result<Self, String>;
+}
+
+pub fn to_bytes<T: Binary>(value: &T) -> Vec<u8> {
+    let mut writer = Writer::new();
+    writer.bytes(&MAGIC);
+    writer.u16(VERSION);
+    writer.u8(T::KIND);
+    value.write(&mut writer);
+
+    writer.data
+}
+
+pub fn from_bytes<T: Binary>(bytes: &[u8]) -> Result<T, String> {
+    let mut reader = Reader::new(bytes);
+
+    if reader.bytes(MAGIC.len())? != MAGIC {
+        return Err(String::from("not an animation file"));
+    }
+    let version = reader.u16()?;
+    if version == 0 || version > VERSION {
+        return Err(format!("unsupported version {}", version));
+    }
+    reader.version = version;
+    let kind = reader.u8()?;
+    if kind != T::KIND {
+        return Err(format!("expected kind {} but found {}", T::KIND, kind));
+    }
+
+    let value = T::read(&mut reader)?;
+    if reader.remaining() > 0 {
+        return Err(format!(
+            "{} unexpected bytes at the end",
+            reader.remaining()
+        ));
+    }
+
+    Ok(value)
+}
+
+pub fn save<T: Binary>(value: &T, path: &Path) -> Result<(), String> {
+    fs::write(path, to_bytes(value)).map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+pub fn load<T: Binary>(path: &Path) -> Result<T, String> {
+    let bytes = fs::read(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    from_bytes(&bytes).map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+/// little endian output handed to Binary::write
+pub struct Writer {
+    data: Vec<u8>,
+}
+
+impl Writer {
+    fn new() -> Self {
+        Self { data: Vec::new() }
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    pub fn u16(&mut self, value: u16) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub fn i32(&mut self, value: i32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub fn f32(&mut self, value: f32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub fn f32s(&mut self, values: &[f32]) {
+        for value in values {
+            self.f32(*value);
+        }
+    }
+
+    pub fn count(&mut self, len: usize) {
+        self.u32(len as u32);
+    }
+
+    pub fn str(&mut self, value: &str) {
+        self.count(value.len());
+        self.bytes(value.as_bytes());
+    }
+}
+
+/// little endian input handed to Binary::read, every read fails instead of panicking on short data
+pub struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+    /// version of the data being read, for layouts that changed between versions
+    pub version: u16,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            position: 0,
+            version: VERSION,
+        }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if len > self.remaining() {
+            return Err(format!("unexpected end of data at byte {}", self.position));
+        }
+
+        let bytes = &self.data[self.position..self.position + len];
+        self.position += len;
+
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.bytes(N)?);
+
+        Ok(array)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    pub fn i32(&mut self) -> Result<i32, String> {
+        Ok(i32::from_le_bytes(self.array()?))
+    }
+
+    pub fn f32(&mut self) -> Result<f32, String> {
+        Ok(f32::from_le_bytes(self.array()?))
+    }
+
+    pub fn f32s<const N: usize>(&mut self) -> Result<[f32; N], String> {
+        let mut values = [0.0; N];
+        for value in &mut values {
+            *value = self.f32()?;
+        }
+
+        Ok(values)
+    }
+
+    /// length of a list, checked against what is left so corrupt files do not allocate wildly
+    /// `size` is the smallest number of bytes a single element takes
+    pub fn count(&mut self, size: usize) -> Result<usize, String> {
+        let len = self.u32()? as usize;
+        if len.saturating_mul(size.max(1)) > self.remaining() {
+            return Err(format!(
+                "length {} at byte {} is too long",
+                len, self.position
+            ));
+        }
+
+        Ok(len)
+    }
+
+    pub fn str(&mut self) -> Result<String, String> {
+        let len = self.count(1)?;
+        String::from_utf8(self.bytes(len)?.to_vec())
+            .map_err(|_| format!("invalid string before byte {}", self.position))
+    }
+}
+
+fn write_interpolation(writer: &mut Writer, interpolation: Interpolation) {
+    writer.u8(match interpolation {
+        Interpolation::Constant => 0,
+        Interpolation::Linear => 1,
+        Interpolation::Cubic => 2,
+    });
+}
+
+fn read_interpolation(reader: &mut Reader) -> Result<Interpolation, String> {
+    match reader.u8()? {
+        0 => Ok(Interpolation::Constant),
+        1 => Ok(Interpolation::Linear),
+        2 => Ok(Interpolation::Cubic),
+        other => Err(format!("unknown interpolation {}", other)),
+    }
+}
+
+fn write_loop_mode(writer: &mut Writer, loop_mode: LoopMode) {
+    writer.u8(match loop_mode {
+        LoopMode::Clamp => 0,
+        LoopMode::Loop => 1,
+        LoopMode::PingPong => 2,
+    });
+}
+
+fn read_loop_mode(reader: &mut Reader) -> Result<LoopMode, String> {
+    match reader.u8()? {
+        0 => Ok(LoopMode::Clamp),
+        1 => Ok(LoopMode::Loop),
+        2 => Ok(LoopMode::PingPong),
+        other => Err(format!("unknown loop mode {}", other)),
+    }
+}
+
+fn write_track<const N: usize>(writer: &mut Writer, track: &Track<N>) {
+    write_interpolation(writer, track.interpolation);
+    writer.count(track.frames.len());
+    for frame in &track.frames {
+        writer.f32(frame.time);
+        writer.f32s(&frame.m_value);
+        writer.f32s(&frame.m_in);
+        writer.f32s(&frame.m_out);
+    }
+}
+
+fn read_track<const N: usize>(reader: &mut Reader) -> Result<Track<N>, String> {
+    let mut track = Track::<N>::new();
+    track.interpolation = read_interpolation(reader)?;
+
+    let len = reader.count((1 + 3 * N) * 4)?;
+    for _ in 0..len {
+        let mut frame = Frame::<N>::new();
+        frame.time = reader.f32()?;
+        frame.m_value = reader.f32s()?;
+        frame.m_in = reader.f32s()?;
+        frame.m_out = reader.f32s()?;
+        track.frames.push(frame);
+    }
+
+    Ok(track)
+}
+
+impl Binary for TransformTrack {
+    const KIND: u8 = 1;
+
+    fn write(&self, writer: &mut Writer) {
+        writer.u32(self.id);
+        write_track(writer, &self.position);
+        write_track(writer, &self.rotation);
+        write_track(writer, &self.scaling);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, String> {
+        let mut track = TransformTrack::new();
+        track.id = reader.u32()?;
+        track.position = read_track(reader)?;
+        track.rotation = read_track(reader)?;
+        track.scaling = read_track(reader)?;
+
+        Ok(track)
+    }
+}
+
+/// lookup tables are not saved, call build_lookups on loaded clips that need them
+impl Binary for Clip {
+    const KIND: u8 = 2;
+
+    fn write(&self, writer: &mut Writer) {
+        writer.str(&self.name);
+        write_loop_mode(writer, self.get_loop_mode());
+        writer.f32(self.get_speed());
+
+        let events = self.get_events();
+        writer.count(events.len());
+        for event in events {
+            writer.f32(event.time);
+            writer.str(&event.name);
+        }
+
+        writer.count(self.tracks.len());
+        for track in &self.tracks {
+            track.write(writer);
+        }
+
+        writer.count(self.weights.len());
+        for track in &self.weights {
+            writer.u32(track.id);
+            writer.count(track.weights.len());
+            for weight in &track.weights {
+                write_track(writer, weight);
+            }
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, String> {
+        let mut clip = Clip::new();
+        clip.name = reader.str()?;
+        clip.set_loop_mode(read_loop_mode(reader)?);
+        clip.set_speed(reader.f32()?);
+
+        let len = reader.count(8)?;
+        for _ in 0..len {
+            let time = reader.f32()?;
+            clip.add_event(&reader.str()?, time);
+        }
+
+        // id and three empty tracks
+        let len = reader.count(4 + 3 * 5)?;
+        for _ in 0..len {
+            clip.tracks.push(TransformTrack::read(reader)?);
+        }
+
+        if reader.version >= 2 {
+            // id and an empty list
+            let len = reader.count(4 + 4)?;
+            for _ in 0..len {
+                let mut track = WeightsTrack::new();
+                track.id = reader.u32()?;
+                let len = reader.count(5)?;
+                for _ in 0..len {
+                    track.weights.push(read_track(reader)?);
+                }
+                clip.weights.push(track);
+            }
+        }
+        clip.re_calculate_duration();
+
+        Ok(clip)
+    }
+}
+
+impl Binary for Skeleton {
+    const KIND: u8 = 3;
+
+    fn write(&self, writer: &mut Writer) {
+        let pose = &self.rest_pose;
+        writer.count(pose.joints.len());
+
+        for (i, joint) in pose.joints.iter().enumerate() {
+            writer.str(&self.joint_names[i]);
+            writer.i32(pose.parents[i]);
+            writer.f32s(&joint.translation.to_array());
+            writer.f32s(&joint.orientation.to_array());
+            writer.f32s(&joint.scaling.to_array());
+
+            match &self.inverse_bind_pose[i] {
+                Some(matrix) => {
+                    writer.u8(1);
+                    for row in &matrix.data {
+                        writer.f32s(row);
+                    }
+                }
+                None => writer.u8(0),
+            }
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, String> {
+        // name length, parent, transform and inverse bind flag
+        let len = reader.count(4 + 4 + 10 * 4 + 1)?;
+
+        let mut skeleton = Skeleton::new();
+        let mut pose = Pose::new();
+        for i in 0..len {
+            skeleton.joint_names.push(reader.str()?);
+
+            let parent = reader.i32()?;
+            if parent < -1 || parent >= len as i32 {
+                return Err(format!("joint {} has an unknown parent {}", i, parent));
+            }
+            pose.parents.push(parent);
+
+            pose.joints.push(Transform {
+                translation: Vec3::from(&reader.f32s()?),
+                orientation: Quat::from(&reader.f32s()?),
+                scaling: Vec3::from(&reader.f32s()?),
+            });
+
+            let inverse_bind_pose = match reader.u8()? {
+                0 => None,
+                1 => Some(Mat4 {
+                    data: [
+                        reader.f32s()?,
+                        reader.f32s()?,
+                        reader.f32s()?,
+                        reader.f32s()?,
+                    ],
+                }),
+                other => return Err(format!("unknown inverse bind pose flag {}", other)),
+            };
+            skeleton.inverse_bind_pose.push(inverse_bind_pose);
+        }
+
+        // the controller and the palette expect a tree, catch self parents and cycles here
+        joint_order(&pose.parents)?;
+        skeleton.rest_pose = pose;
+
+        Ok(skeleton)
+    }
+}
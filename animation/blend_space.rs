@@ -0,0 +1,234 @@
+// blend spaces, clips placed at points on a line or a plane and blended by a parameter
+// every clip plays at the same normalized time so cycles like foot steps stay in step
+
+use super::clip::{Clip, SampleError};
+use super::pose::Pose;
+
+use math::vec2::{dot, Vec2};
+
+/// clips played in sync and blended by weight, shared by the 1d and 2d blend spaces
+#[derive(Clone)]
+struct SyncedClips {
+    clips: Vec<Clip>,
+    weights: Vec<f32>,
+    /// shared playback position in [0, 1)
+    normalized_time: f32,
+}
+
+impl SyncedClips {
+    fn new() -> Self {
+        Self {
+            clips: Vec::new(),
+            weights: Vec::new(),
+            normalized_time: 0.0,
+        }
+    }
+
+    fn push(&mut self, clip: Clip) {
+        self.clips.push(clip);
+        self.weights.push(0.0);
+    }
+
+    /// advance by the weighted duration so the blend plays at the speed of its dominant clips
+    fn update(&mut self, delta_time: f32) {
+        let duration = self
+            .clips
+            .iter()
+            .zip(&self.weights)
+            .map(|(clip, weight)| clip.get_duration() * weight)
+            .sum::<f32>();
+
+        if duration > 0.0 {
+            self.normalized_time = (self.normalized_time + delta_time / duration).rem_euclid(1.0);
+        }
+    }
+
+    fn sample(&mut self, out_pose: &mut Pose, errors: &mut Vec<SampleError>) {
+        let reference = out_pose.clone();
+        let mut total = 0.0;
+
+        for (clip, weight) in self.clips.iter_mut().zip(&self.weights) {
+            if *weight <= 0.0 {
+                continue;
+            }
+
+            let mut pose = reference.clone();
+            let time = clip.get_start_time() + self.normalized_time * clip.get_duration();
+            clip.sample(&mut pose, time, errors);
+
+            // blending each pose in by its share of the weight so far gives the weighted average
+            total += weight;
+            let current = out_pose.clone();
+            Pose::blend(out_pose, &current, &pose, weight / total);
+        }
+    }
+}
+
+/// clips placed along a single parameter, like idle at 0, walk at 1.5 and run at 4
+#[derive(Clone)]
+pub struct BlendSpace1D {
+    synced: SyncedClips,
+    points: Vec<f32>,
+    parameter: f32,
+}
+
+impl Default for BlendSpace1D {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BlendSpace1D {
+    pub fn new() -> Self {
+        Self {
+            synced: SyncedClips::new(),
+            points: Vec::new(),
+            parameter: 0.0,
+        }
+    }
+
+    pub fn add_clip(&mut self, clip: Clip, point: f32) {
+        self.synced.push(clip);
+        self.points.push(point);
+        self.calculate_weights();
+    }
+
+    pub fn set_parameter(&mut self, parameter: f32) {
+        self.parameter = parameter;
+        self.calculate_weights();
+    }
+
+    pub fn get_weights(&self) -> &[f32] {
+        &self.synced.weights
+    }
+
+    pub fn get_normalized_time(&self) -> f32 {
+        self.synced.normalized_time
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        self.synced.update(delta_time);
+    }
+
+    /// sample the blend into `out_pose`, joints without tracks keep their current value
+    /// like Clip::sample, tracks that can not be sampled are added to `errors`
+    pub fn sample(&mut self, out_pose: &mut Pose, errors: &mut Vec<SampleError>) {
+        self.synced.sample(out_pose, errors);
+    }
+
+    /// linear interpolation between the two closest points, clamped to the ends of the line
+    fn calculate_weights(&mut self) {
+        let weights = &mut self.synced.weights;
+        weights.fill(0.0);
+
+        // closest point below and above the parameter
+        let mut below: Option<usize> = None;
+        let mut above: Option<usize> = None;
+        for (i, point) in self.points.iter().enumerate() {
+            if *point <= self.parameter && below.is_none_or(|b| *point > self.points[b]) {
+                below = Some(i);
+            }
+            if *point >= self.parameter && above.is_none_or(|a| *point < self.points[a]) {
+                above = Some(i);
+            }
+        }
+
+        match (below, above) {
+            (Some(b), Some(a)) if a != b => {
+                let t = (self.parameter - self.points[b]) / (self.points[a] - self.points[b]);
+                weights[b] = 1.0 - t;
+                weights[a] = t;
+            }
+            (Some(i), _) | (None, Some(i)) => weights[i] = 1.0,
+            (None, None) => {}
+        }
+    }
+}
+
+/// clips placed on a plane, like velocity along x and z
+/// weights use gradient band interpolation so the points don't need to form a grid
+#[derive(Clone)]
+pub struct BlendSpace2D {
+    synced: SyncedClips,
+    points: Vec<Vec2>,
+    parameter: Vec2,
+}
+
+impl Default for BlendSpace2D {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BlendSpace2D {
+    pub fn new() -> Self {
+        Self {
+            synced: SyncedClips::new(),
+            points: Vec::new(),
+            parameter: Vec2::ZERO,
+        }
+    }
+
+    pub fn add_clip(&mut self, clip: Clip, point: Vec2) {
+        self.synced.push(clip);
+        self.points.push(point);
+        self.calculate_weights();
+    }
+
+    pub fn set_parameter(&mut self, parameter: Vec2) {
+        self.parameter = parameter;
+        self.calculate_weights();
+    }
+
+    pub fn get_weights(&self) -> &[f32] {
+        &self.synced.weights
+    }
+
+    pub fn get_normalized_time(&self) -> f32 {
+        self.synced.normalized_time
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        self.synced.update(delta_time);
+    }
+
+    /// sample the blend into `out_pose`, joints without tracks keep their current value
+    /// like Clip::sample, tracks that can not be sampled are added to `errors`
+    pub fn sample(&mut self, out_pose: &mut Pose, errors: &mut Vec<SampleError>) {
+        self.synced.sample(out_pose, errors);
+    }
+
+    /// every point gets the smallest of its influences against the other points,
+    /// the influence falls off linearly from 1 at the point to 0 at the other point
+    fn calculate_weights(&mut self) {
+        let weights = &mut self.synced.weights;
+        let mut total = 0.0;
+
+        for (i, point) in self.points.iter().enumerate() {
+            let to_parameter = self.parameter - *point;
+            let mut weight: f32 = 1.0;
+
+            for (j, other) in self.points.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+
+                let to_other = *other - *point;
+                let len_squared = dot(&to_other, &to_other);
+                if len_squared <= 0.0 {
+                    continue;
+                }
+                weight = weight.min(1.0 - dot(&to_parameter, &to_other) / len_squared);
+            }
+
+            weights[i] = weight.max(0.0);
+            total += weights[i];
+        }
+
+        if total > 0.0 {
+            for weight in weights.iter_mut() {
+                *weight /= total;
+            }
+        }
+    }
+}
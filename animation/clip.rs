@@ -2,72 +2,125 @@
 // _______________________________________________________________________________________________________
 // yet again lots of help from "gabor szauer - hands on c++ game animation programming packt"
 
+use std::fmt;
+
+use super::curves::LoopMode;
+use super::morph::MorphWeights;
 use super::pose::Pose;
+use super::track::TrackError;
 use super::track_transform::TransformTrack;
+use super::track_weights::WeightsTrack;
+
+use math::quaternion::Quat;
+
+/// named marker on a clip timeline, like a footstep or the moment a weapon is drawn
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClipEvent {
+    pub time: f32,
+    pub name: String,
+}
+
+/// a track of a clip that could not be sampled, the joint or node it animates keeps its pose
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SampleError {
+    /// transform track of a joint
+    Joint(u32, TrackError),
+    /// morph target weights track of a node
+    Weights(u32, TrackError),
+}
+
+impl fmt::Display for SampleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SampleError::Joint(joint, err) => write!(f, "joint {}: {}", joint, err),
+            SampleError::Weights(node, err) => write!(f, "weights of node {}: {}", node, err),
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct Clip {
     pub tracks: Vec<TransformTrack>,
+    /// morph target weights, keyed by node instead of joint
+    pub weights: Vec<WeightsTrack>,
     pub name: String,
     start_time: f32,
     end_time: f32,
-    looping: bool,
+    loop_mode: LoopMode,
+    /// playback rate used by the controller, 1 plays at normal speed
+    /// negative rates play backwards
+    speed: f32,
+    /// sorted by time
+    events: Vec<ClipEvent>,
 }
 
 impl Clip {
     pub fn new() -> Self {
         Self {
             tracks: Vec::new(),
+            weights: Vec::new(),
             name: String::from("None"),
             start_time: 0.0,
             end_time: 0.0,
-            looping: true,
+            loop_mode: LoopMode::Loop,
+            speed: 1.0,
+            events: Vec::new(),
         }
     }
 
-    pub fn sample(&mut self, out_pose: &mut Pose, time: f32) -> f32 {
-        if self.get_duration() == 0.0 {
-            return 0.0;
-        }
+    /// sample the clip at `time` and return the playback time to continue from
+    /// this is the time on the timeline except for ping pong clips, where it also
+    /// remembers the direction (between end and end + duration playback runs backwards)
+    /// clips without a duration still hold the pose of their single frame tracks
+    /// joints whose track can not be sampled keep their pose and the track is added to `errors`
+    pub fn sample(&mut self, out_pose: &mut Pose, time: f32, errors: &mut Vec<SampleError>) -> f32 {
+        let playback_time = self.wrap_playback_time(time);
+        let time = self.adjust_time_to_fit_range(playback_time);
 
-        let time = self.adjust_time_to_fit_range(time);
+        for track in &mut self.tracks {
+            let joint = &mut out_pose.joints[track.id as usize];
+            match track.sample(joint, time, self.loop_mode) {
+                Ok(animated) => *joint = animated,
+                Err(err) => errors.push(SampleError::Joint(track.id, err)),
+            }
+        }
 
-        let len = self.tracks.len();
-        for i in 0..len {
-            let j = self.tracks[i].id;
-            let local = &out_pose.joints[j as usize];
-            let animated = self.tracks[i].sample(local, time, self.looping);
+        playback_time
+    }
 
-            out_pose.joints[j as usize] = animated;
+    /// sample the morph target weights at `time`, nodes without a track keep their weights
+    /// like sample, tracks that can not be sampled are added to `errors`
+    pub fn sample_weights(
+        &mut self,
+        out_weights: &mut MorphWeights,
+        time: f32,
+        errors: &mut Vec<SampleError>,
+    ) {
+        let time = self.adjust_time_to_fit_range(self.wrap_playback_time(time));
+        for track in &mut self.weights {
+            let out = out_weights.get_mut(track.id, track.weights.len());
+            if let Err(err) = track.sample(out, time, self.loop_mode) {
+                errors.push(SampleError::Weights(track.id, err));
+            }
         }
-
-        time
     }
 
+    /// map any time onto the timeline of the clip, according to its loop mode
     pub fn adjust_time_to_fit_range(&self, time: f32) -> f32 {
-        let mut time = time;
-        if self.looping {
-            let duration = self.get_duration();
-            if duration <= 0.0 {
-                return 0.0;
-            }
+        if self.loop_mode != LoopMode::Clamp && self.get_duration() <= 0.0 {
+            return 0.0;
+        }
 
-            time = (time - self.start_time) % duration;
+        self.loop_mode.fit(time, self.start_time, self.end_time)
+    }
 
-            if time < 0.0 {
-                time += duration;
-            }
-            time += self.start_time;
-        } else {
-            if time < self.start_time {
-                time = self.start_time;
-            }
-            if time > self.end_time {
-                time = self.end_time;
-            }
+    /// like adjust_time_to_fit_range but ping pong times keep their direction, see LoopMode::wrap
+    pub fn wrap_playback_time(&self, time: f32) -> f32 {
+        if self.loop_mode != LoopMode::Clamp && self.get_duration() <= 0.0 {
+            return 0.0;
         }
 
-        time
+        self.loop_mode.wrap(time, self.start_time, self.end_time)
     }
 
     pub fn re_calculate_duration(&mut self) {
@@ -95,9 +148,207 @@ impl Clip {
                 }
             }
         }
+
+        for track in &self.weights {
+            if track.is_valid() {
+                let start_time = track.get_start_time();
+                let end_time = track.get_end_time();
+
+                if start_time < self.start_time || !start_set {
+                    self.start_time = start_time;
+                    start_set = true;
+                }
+
+                if end_time > self.end_time || !end_set {
+                    self.end_time = end_time;
+                    end_set = true;
+                }
+            }
+        }
     }
 
     pub fn get_duration(&self) -> f32 {
         self.end_time - self.start_time
     }
+
+    pub fn get_start_time(&self) -> f32 {
+        self.start_time
+    }
+
+    pub fn get_end_time(&self) -> f32 {
+        self.end_time
+    }
+
+    /// true for every loop mode that keeps playing, ping pong included
+    pub fn get_looping(&self) -> bool {
+        self.loop_mode != LoopMode::Clamp
+    }
+
+    pub fn set_looping(&mut self, looping: bool) {
+        self.loop_mode = if looping {
+            LoopMode::Loop
+        } else {
+            LoopMode::Clamp
+        };
+    }
+
+    pub fn get_loop_mode(&self) -> LoopMode {
+        self.loop_mode
+    }
+
+    pub fn set_loop_mode(&mut self, loop_mode: LoopMode) {
+        self.loop_mode = loop_mode;
+    }
+
+    pub fn get_speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+    }
+
+    /// add a named event at `time`, events with the same time are reported in insertion order
+    pub fn add_event(&mut self, name: &str, time: f32) {
+        let index = self.events.partition_point(|event| event.time <= time);
+        self.events.insert(
+            index,
+            ClipEvent {
+                time,
+                name: String::from(name),
+            },
+        );
+    }
+
+    pub fn get_events(&self) -> &[ClipEvent] {
+        &self.events
+    }
+
+    pub fn clear_events(&mut self) {
+        self.events.clear();
+    }
+
+    /// events crossed when playback moves from `from` to `to`, in the order they are crossed
+    /// `from` is a time returned by `sample` and `to` the unwrapped time about to be sampled
+    /// forward playback reports events in (from, to], backward playback in [to, from)
+    /// `include_from` also reports events exactly at `from`, for the first update of a clip
+    pub fn events_between(&self, from: f32, to: f32, include_from: bool) -> Vec<&ClipEvent> {
+        let duration = self.get_duration();
+        if self.events.is_empty() || duration <= 0.0 {
+            return Vec::new();
+        }
+
+        // the timeline is unrolled into one copy of the events per loop crossed,
+        // a ping pong period holds every event twice, once per direction
+        let mut unrolled: Vec<(f32, &ClipEvent)> = Vec::new();
+        let (from, to) = match self.loop_mode {
+            LoopMode::Clamp => {
+                let clamp = |time: f32| time.clamp(self.start_time, self.end_time);
+                unrolled.extend(self.events.iter().map(|event| (event.time, event)));
+                (clamp(from), clamp(to))
+            }
+            LoopMode::Loop | LoopMode::PingPong => {
+                let period = match self.loop_mode {
+                    LoopMode::PingPong => 2.0 * duration,
+                    _ => duration,
+                };
+                let cycle = |time: f32| ((time - self.start_time) / period).floor() as i32;
+
+                for cycle in cycle(from.min(to))..=cycle(from.max(to)) {
+                    let offset = cycle as f32 * period;
+                    for event in &self.events {
+                        unrolled.push((event.time + offset, event));
+
+                        // the turning points are only crossed once
+                        let turns = event.time <= self.start_time || event.time >= self.end_time;
+                        if self.loop_mode == LoopMode::PingPong && !turns {
+                            let mirrored =
+                                self.end_time + duration - (event.time - self.start_time);
+                            unrolled.push((mirrored + offset, event));
+                        }
+                    }
+                }
+                (from, to)
+            }
+        };
+
+        let is_crossed = |time: f32| {
+            if to >= from {
+                (time > from || (include_from && time == from)) && time <= to
+            } else {
+                (time < from || (include_from && time == from)) && time >= to
+            }
+        };
+
+        unrolled.retain(|(time, _)| is_crossed(*time));
+        unrolled.sort_by(|a, b| a.0.total_cmp(&b.0));
+        if to < from {
+            unrolled.reverse();
+        }
+
+        unrolled.into_iter().map(|(_, event)| event).collect()
+    }
+
+    /// build the frame lookup table of every track, see Track::build_lookup
+    pub fn build_lookups(&mut self, samples_per_second: f32) {
+        for track in &mut self.tracks {
+            track.build_lookup(samples_per_second);
+        }
+        for track in &mut self.weights {
+            track.build_lookup(samples_per_second);
+        }
+    }
+
+    /// returns the track animating the specified joint
+    /// an empty track is created for the joint if none exists yet
+    pub fn get_transform_track(&mut self, joint: u32) -> &mut TransformTrack {
+        if let Some(index) = self.tracks.iter().position(|track| track.id == joint) {
+            return &mut self.tracks[index];
+        }
+
+        let mut track = TransformTrack::new();
+        track.id = joint;
+        self.tracks.push(track);
+
+        self.tracks.last_mut().unwrap()
+    }
+}
+
+/// turn a clip into an additive clip holding the difference between each keyframe and `reference`
+/// sampling the result into an identity pose gives a difference pose for `Pose::add`
+/// a common reference is the clip sampled at its start time
+pub fn make_additive(clip: &Clip, reference: &Pose) -> Clip {
+    let mut additive = clip.clone();
+
+    for track in &mut additive.tracks {
+        let joint = &reference.joints[track.id as usize];
+
+        let translation = joint.translation.to_array();
+        for frame in &mut track.position.frames {
+            for (value, reference) in frame.m_value.iter_mut().zip(translation) {
+                *value -= reference;
+            }
+        }
+
+        // the difference is inverse(reference) * value, tangents get the same treatment
+        let inverse = joint.orientation.inverse();
+        for frame in &mut track.rotation.frames {
+            frame.m_value = (inverse * Quat::from(&frame.m_value)).to_array();
+            frame.m_in = (inverse * Quat::from(&frame.m_in)).to_array();
+            frame.m_out = (inverse * Quat::from(&frame.m_out)).to_array();
+        }
+
+        let scaling = joint.scaling.to_array();
+        for frame in &mut track.scaling.frames {
+            for (i, reference) in scaling.iter().enumerate() {
+                frame.m_value[i] /= reference;
+                frame.m_in[i] /= reference;
+                frame.m_out[i] /= reference;
+            }
+        }
+    }
+
+    additive.name = format!("{} (additive)", clip.name);
+
+    additive
 }
@@ -0,0 +1,590 @@
+// clip compression
+// keyframe reduction drops keys that the remaining keys rebuild within a tolerance, it works on
+// any clip and the result is still a regular Clip
+// CompressedClip goes further and quantizes what is left, rotations with the smallest three
+// encoding and vectors relative to the range of their track, 6 bytes per key instead of 52
+
+use std::mem::size_of;
+use std::ops::{Add, Mul};
+
+use super::clip::{Clip, ClipEvent};
+use super::curves::{Interpolation, LoopMode};
+use super::frame::Frame;
+use super::pose::Pose;
+use super::track::{
+    AdjustHermiteResult, Cast, Interpolate, Neighborhood, QuatTrack, ScalarTrack, Track,
+    VectorTrack,
+};
+use super::track_transform::TransformTrack;
+
+use math::quaternion::Quat;
+use math::vec3::Vec3;
+
+/// longest run of keys dropped in a row, keeps reduction linear on long mocap tracks
+const MAX_SPAN: usize = 256;
+
+/// error allowed when dropping keyframes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tolerance {
+    /// distance in the units of the clip
+    pub position: f32,
+    /// distance between unit quaternions, about half the angle in radians
+    pub rotation: f32,
+    pub scaling: f32,
+    /// morph target weight
+    pub weight: f32,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            position: 1e-3,
+            rotation: 5e-4,
+            scaling: 1e-3,
+            weight: 1e-3,
+        }
+    }
+}
+
+fn scalar_distance(a: &f32, b: &f32) -> f32 {
+    (a - b).abs()
+}
+
+fn vector_distance(a: &Vec3, b: &Vec3) -> f32 {
+    (*a - *b).len()
+}
+
+/// distance between unit quaternions, q and -q are the same rotation
+fn quat_distance(a: &Quat, b: &Quat) -> f32 {
+    (2.0 - 2.0 * a.dot(b).abs()).max(0.0).sqrt()
+}
+
+/// drop the keys that the keys around them rebuild within `tolerance`
+/// the first and last keys are always kept
+fn reduce<T, const N: usize>(
+    track: &Track<N>,
+    tolerance: f32,
+    distance: fn(&T, &T) -> f32,
+) -> Track<N>
+where
+    T: Cast<T>
+        + Interpolate<T>
+        + AdjustHermiteResult<T>
+        + Neighborhood<T>
+        + Add<T, Output = T>
+        + Mul<f32, Output = T>,
+{
+    let len = track.frames.len();
+    if len <= 2 {
+        return track.clone();
+    }
+
+    let mut original = track.clone();
+    let mut reduced = Track::<N>::new();
+    reduced.interpolation = track.interpolation;
+    reduced.frames.push(track.frames[0].clone());
+
+    // try to bridge from the last kept key to the key after i, skipping everything in between
+    let mut anchor = 0;
+    for i in 1..len - 1 {
+        let mut bridge = Track::<N>::new();
+        bridge.interpolation = track.interpolation;
+        bridge.frames = vec![track.frames[anchor].clone(), track.frames[i + 1].clone()];
+
+        // every skipped key and the middle of every original segment has to be rebuilt
+        let fits = i - anchor < MAX_SPAN
+            && (anchor..=i).all(|k| {
+                let key = track.frames[k + 1].time;
+                let middle = (track.frames[k].time + key) * 0.5;
+                // keys around frames that can not be sampled are kept
+                [key, middle].into_iter().all(|time| {
+                    let expected = original.try_sample::<T>(time, LoopMode::Clamp);
+                    let rebuilt = bridge.try_sample::<T>(time, LoopMode::Clamp);
+                    match (expected, rebuilt) {
+                        (Ok(expected), Ok(rebuilt)) => distance(&expected, &rebuilt) <= tolerance,
+                        _ => false,
+                    }
+                })
+            });
+
+        if !fits {
+            reduced.frames.push(track.frames[i].clone());
+            anchor = i;
+        }
+    }
+
+    reduced.frames.push(track.frames[len - 1].clone());
+
+    reduced
+}
+
+pub fn reduce_scalar_track(track: &ScalarTrack, tolerance: f32) -> ScalarTrack {
+    reduce::<f32, 1>(track, tolerance, scalar_distance)
+}
+
+pub fn reduce_vector_track(track: &VectorTrack, tolerance: f32) -> VectorTrack {
+    reduce::<Vec3, 3>(track, tolerance, vector_distance)
+}
+
+pub fn reduce_quat_track(track: &QuatTrack, tolerance: f32) -> QuatTrack {
+    reduce::<Quat, 4>(track, tolerance, quat_distance)
+}
+
+/// copy of the clip with every key the other keys can rebuild within `tolerance` removed
+pub fn reduce_clip(clip: &Clip, tolerance: &Tolerance) -> Clip {
+    let mut reduced = clip.clone();
+
+    for track in &mut reduced.tracks {
+        track.position = reduce_vector_track(&track.position, tolerance.position);
+        track.rotation = reduce_quat_track(&track.rotation, tolerance.rotation);
+        track.scaling = reduce_vector_track(&track.scaling, tolerance.scaling);
+    }
+    for track in &mut reduced.weights {
+        for weight in &mut track.weights {
+            *weight = reduce_scalar_track(weight, tolerance.weight);
+        }
+    }
+
+    reduced
+}
+
+/// rough memory used by the keyframes of a clip, in bytes
+pub fn memory_size(clip: &Clip) -> usize {
+    let transforms = clip
+        .tracks
+        .iter()
+        .map(|track| {
+            size_of::<TransformTrack>()
+                + track.position.frames.len() * size_of::<Frame<3>>()
+                + track.rotation.frames.len() * size_of::<Frame<4>>()
+                + track.scaling.frames.len() * size_of::<Frame<3>>()
+        })
+        .sum::<usize>();
+    let weights = clip
+        .weights
+        .iter()
+        .flat_map(|track| &track.weights)
+        .map(|weight| size_of::<ScalarTrack>() + weight.frames.len() * size_of::<Frame<1>>())
+        .sum::<usize>();
+
+    transforms + weights
+}
+
+/// largest component of a quaternion is dropped and rebuilt from the other three,
+/// which are then all within this range
+const QUAT_RANGE: f32 = std::f32::consts::FRAC_1_SQRT_2;
+const QUAT_STEPS: f32 = 32767.0;
+
+/// smallest three encoding, 15 bits per component and the index of the dropped component
+/// in the top bits of the first two values
+pub fn quantize_quat(q: &Quat) -> [u16; 3] {
+    let components = q.unit().to_array();
+
+    let mut largest = 0;
+    for i in 1..4 {
+        if components[i].abs() > components[largest].abs() {
+            largest = i;
+        }
+    }
+    // q and -q are the same rotation, flip so the dropped component is positive
+    let sign = components[largest].signum();
+
+    let mut out = [0u16; 3];
+    let smallest = (0..4).filter(|i| *i != largest);
+    for (value, i) in out.iter_mut().zip(smallest) {
+        let normalized = (components[i] * sign / QUAT_RANGE * 0.5 + 0.5).clamp(0.0, 1.0);
+        *value = (normalized * QUAT_STEPS).round() as u16;
+    }
+    out[0] |= ((largest & 1) as u16) << 15;
+    out[1] |= ((largest >> 1) as u16) << 15;
+
+    out
+}
+
+pub fn dequantize_quat(data: &[u16; 3]) -> Quat {
+    let largest = (data[0] >> 15) as usize | ((data[1] >> 15) as usize) << 1;
+
+    let mut components = [0.0; 4];
+    let mut sum = 0.0;
+    let smallest = (0..4).filter(|i| *i != largest);
+    for (value, i) in data.iter().zip(smallest) {
+        let normalized = (value & 0x7fff) as f32 / QUAT_STEPS;
+        components[i] = (normalized * 2.0 - 1.0) * QUAT_RANGE;
+        sum += components[i] * components[i];
+    }
+    components[largest] = (1.0 - sum).max(0.0).sqrt();
+
+    Quat::from(&components)
+}
+
+/// keys of a compressed track, cubic tracks are baked into linear keys
+#[derive(Clone, Debug)]
+struct Channel {
+    times: Vec<f32>,
+    /// Constant or Linear
+    interpolation: Interpolation,
+    values: Vec<[u16; 3]>,
+}
+
+impl Channel {
+    /// the two keys around `time` and how far between them it is
+    fn locate(&self, time: f32, mode: LoopMode) -> (usize, usize, f32) {
+        let len = self.times.len();
+        let time = mode.fit(time, self.times[0], self.times[len - 1]);
+
+        let first = self.times[..len - 1]
+            .partition_point(|key| *key <= time)
+            .saturating_sub(1);
+        let second = (first + 1).min(len - 1);
+
+        let delta = self.times[second] - self.times[first];
+        let t = match self.interpolation {
+            Interpolation::Constant => 0.0,
+            _ if delta <= 0.0 => 0.0,
+            _ => ((time - self.times[first]) / delta).clamp(0.0, 1.0),
+        };
+
+        (first, second, t)
+    }
+
+    fn memory_size(&self) -> usize {
+        size_of::<Self>() + self.times.len() * size_of::<f32>() + self.values.len() * 6
+    }
+}
+
+/// vector keys stored relative to the bounds of the track
+#[derive(Clone, Debug)]
+struct VectorChannel {
+    keys: Channel,
+    min: Vec3,
+    extent: Vec3,
+}
+
+impl VectorChannel {
+    fn new(track: &VectorTrack) -> Self {
+        let frames = &track.frames;
+        let values = frames
+            .iter()
+            .map(|frame| Vec3::from(&frame.m_value))
+            .collect::<Vec<Vec3>>();
+
+        let min = values.iter().fold(values[0], |min, value| min.min(*value));
+        let max = values.iter().fold(values[0], |max, value| max.max(*value));
+        let extent = max - min;
+
+        let quantize = |value: f32, min: f32, extent: f32| {
+            if extent <= 0.0 {
+                0
+            } else {
+                ((value - min) / extent * 65535.0).round() as u16
+            }
+        };
+
+        Self {
+            keys: Channel {
+                times: frames.iter().map(|frame| frame.time).collect(),
+                interpolation: track.interpolation,
+                values: values
+                    .iter()
+                    .map(|value| {
+                        [
+                            quantize(value.x, min.x, extent.x),
+                            quantize(value.y, min.y, extent.y),
+                            quantize(value.z, min.z, extent.z),
+                        ]
+                    })
+                    .collect(),
+            },
+            min,
+            extent,
+        }
+    }
+
+    fn value(&self, index: usize) -> Vec3 {
+        let [x, y, z] = self.keys.values[index];
+        let scale = self.extent / 65535.0;
+
+        self.min + Vec3::new(x as f32 * scale.x, y as f32 * scale.y, z as f32 * scale.z)
+    }
+
+    fn sample(&self, time: f32, mode: LoopMode) -> Vec3 {
+        let (first, second, t) = self.keys.locate(time, mode);
+        self.value(first).mix(self.value(second), t)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct RotationChannel {
+    keys: Channel,
+}
+
+impl RotationChannel {
+    fn new(track: &QuatTrack) -> Self {
+        Self {
+            keys: Channel {
+                times: track.frames.iter().map(|frame| frame.time).collect(),
+                interpolation: track.interpolation,
+                values: track
+                    .frames
+                    .iter()
+                    .map(|frame| quantize_quat(&Quat::from(&frame.m_value)))
+                    .collect(),
+            },
+        }
+    }
+
+    fn sample(&self, time: f32, mode: LoopMode) -> Quat {
+        let (first, second, t) = self.keys.locate(time, mode);
+        let a = dequantize_quat(&self.keys.values[first]);
+        let b = dequantize_quat(&self.keys.values[second]);
+
+        Quat::interpolate(&a, &b, t)
+    }
+}
+
+/// the same track with linear keys, cubic tracks are resampled `bake_rate` times per second
+fn bake<T, const N: usize>(track: &Track<N>, bake_rate: f32) -> Track<N>
+where
+    T: Cast<T>
+        + Interpolate<T>
+        + AdjustHermiteResult<T>
+        + Neighborhood<T>
+        + Add<T, Output = T>
+        + Mul<f32, Output = T>
+        + ToValue<N>,
+{
+    if track.interpolation != Interpolation::Cubic || track.frames.len() < 2 {
+        return track.clone();
+    }
+
+    let mut baked = Track::<N>::new();
+    baked.interpolation = Interpolation::Linear;
+
+    let mut track = track.clone();
+    let start = track.get_start_time();
+    let duration = track.get_end_time() - start;
+    let samples = ((duration * bake_rate).ceil() as usize).max(1);
+
+    // times that can not be sampled are left out, like broken frames
+    baked.frames = (0..=samples)
+        .filter_map(|i| {
+            let mut frame = Frame::<N>::new();
+            frame.time = start + duration * i as f32 / samples as f32;
+            let value = track.try_sample::<T>(frame.time, LoopMode::Clamp).ok()?;
+            frame.m_value = value.to_value();
+            Some(frame)
+        })
+        .collect();
+
+    baked
+}
+
+/// turn a sampled value back into keyframe data
+trait ToValue<const N: usize> {
+    fn to_value(&self) -> [f32; N];
+}
+
+impl ToValue<3> for Vec3 {
+    fn to_value(&self) -> [f32; 3] {
+        self.to_array()
+    }
+}
+
+impl ToValue<4> for Quat {
+    fn to_value(&self) -> [f32; 4] {
+        self.to_array()
+    }
+}
+
+#[derive(Clone, Debug)]
+struct CompressedTrack {
+    id: u32,
+    position: Option<VectorChannel>,
+    rotation: Option<RotationChannel>,
+    scaling: Option<VectorChannel>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CompressionSettings {
+    /// keys are reduced with this tolerance before quantizing, None keeps every key
+    pub tolerance: Option<Tolerance>,
+    /// compressed clips only interpolate linearly, cubic tracks are resampled this many
+    /// times per second
+    pub bake_rate: f32,
+}
+
+impl Default for CompressionSettings {
+    fn default() -> Self {
+        Self {
+            tolerance: Some(Tolerance::default()),
+            bake_rate: 30.0,
+        }
+    }
+}
+
+/// quantized clip, samples like the clip it was made from
+/// only the joints are compressed, morph target weights are left out
+#[derive(Clone, Debug)]
+pub struct CompressedClip {
+    pub name: String,
+    tracks: Vec<CompressedTrack>,
+    start_time: f32,
+    end_time: f32,
+    loop_mode: LoopMode,
+    speed: f32,
+    events: Vec<ClipEvent>,
+}
+
+impl CompressedClip {
+    pub fn new(clip: &Clip, settings: &CompressionSettings) -> Self {
+        let mut tracks = Vec::new();
+        for track in &clip.tracks {
+            let mut track = TransformTrack {
+                id: track.id,
+                position: bake::<Vec3, 3>(&track.position, settings.bake_rate),
+                rotation: bake::<Quat, 4>(&track.rotation, settings.bake_rate),
+                scaling: bake::<Vec3, 3>(&track.scaling, settings.bake_rate),
+            };
+            if let Some(tolerance) = &settings.tolerance {
+                track.position = reduce_vector_track(&track.position, tolerance.position);
+                track.rotation = reduce_quat_track(&track.rotation, tolerance.rotation);
+                track.scaling = reduce_vector_track(&track.scaling, tolerance.scaling);
+            }
+
+            // like TransformTrack::sample, channels without keys leave the pose alone
+            tracks.push(CompressedTrack {
+                id: track.id,
+                position: (!track.position.frames.is_empty())
+                    .then(|| VectorChannel::new(&track.position)),
+                rotation: (!track.rotation.frames.is_empty())
+                    .then(|| RotationChannel::new(&track.rotation)),
+                scaling: (!track.scaling.frames.is_empty())
+                    .then(|| VectorChannel::new(&track.scaling)),
+            });
+        }
+
+        Self {
+            name: clip.name.clone(),
+            tracks,
+            start_time: clip.get_start_time(),
+            end_time: clip.get_end_time(),
+            loop_mode: clip.get_loop_mode(),
+            speed: clip.get_speed(),
+            events: clip.get_events().to_vec(),
+        }
+    }
+
+    /// same as Clip::sample, compressed tracks are checked when they are built so nothing fails
+    pub fn sample(&self, out_pose: &mut Pose, time: f32) -> f32 {
+        let playback_time = self.loop_mode.wrap(time, self.start_time, self.end_time);
+        let time = self.loop_mode.fit(time, self.start_time, self.end_time);
+
+        for track in &self.tracks {
+            let joint = &mut out_pose.joints[track.id as usize];
+            if let Some(position) = &track.position {
+                joint.translation = position.sample(time, self.loop_mode);
+            }
+            if let Some(rotation) = &track.rotation {
+                joint.orientation = rotation.sample(time, self.loop_mode);
+            }
+            if let Some(scaling) = &track.scaling {
+                joint.scaling = scaling.sample(time, self.loop_mode);
+            }
+        }
+
+        playback_time
+    }
+
+    /// expand back into a regular clip, with linear or constant keys
+    pub fn decompress(&self) -> Clip {
+        let mut clip = Clip::new();
+        clip.name = self.name.clone();
+        clip.set_loop_mode(self.loop_mode);
+        clip.set_speed(self.speed);
+        for event in &self.events {
+            clip.add_event(&event.name, event.time);
+        }
+
+        for compressed in &self.tracks {
+            let track = clip.get_transform_track(compressed.id);
+
+            if let Some(channel) = &compressed.position {
+                track.position = decompress_vector(channel);
+            }
+            if let Some(channel) = &compressed.rotation {
+                track.rotation.interpolation = channel.keys.interpolation;
+                track.rotation.frames = channel
+                    .keys
+                    .times
+                    .iter()
+                    .zip(&channel.keys.values)
+                    .map(|(time, value)| {
+                        let mut frame = Frame::<4>::new();
+                        frame.time = *time;
+                        frame.m_value = dequantize_quat(value).to_array();
+                        frame
+                    })
+                    .collect();
+            }
+            if let Some(channel) = &compressed.scaling {
+                track.scaling = decompress_vector(channel);
+            }
+        }
+        clip.re_calculate_duration();
+
+        clip
+    }
+
+    pub fn get_duration(&self) -> f32 {
+        self.end_time - self.start_time
+    }
+
+    pub fn get_start_time(&self) -> f32 {
+        self.start_time
+    }
+
+    pub fn get_end_time(&self) -> f32 {
+        self.end_time
+    }
+
+    pub fn get_loop_mode(&self) -> LoopMode {
+        self.loop_mode
+    }
+
+    /// rough memory used by the keyframes, in bytes
+    pub fn memory_size(&self) -> usize {
+        self.tracks
+            .iter()
+            .map(|track| {
+                let vector = |channel: &Option<VectorChannel>| {
+                    channel
+                        .as_ref()
+                        .map_or(0, |channel| channel.keys.memory_size())
+                };
+
+                size_of::<CompressedTrack>()
+                    + vector(&track.position)
+                    + track
+                        .rotation
+                        .as_ref()
+                        .map_or(0, |channel| channel.keys.memory_size())
+                    + vector(&track.scaling)
+            })
+            .sum()
+    }
+}
+
+fn decompress_vector(channel: &VectorChannel) -> VectorTrack {
+    let mut track = VectorTrack::new();
+    track.interpolation = channel.keys.interpolation;
+    track.frames = (0..channel.keys.times.len())
+        .map(|i| {
+            let mut frame = Frame::<3>::new();
+            frame.time = channel.keys.times[i];
+            frame.m_value = channel.value(i).to_array();
+            frame
+        })
+        .collect();
+
+    track
+}
@@ -1,7 +1,67 @@
-use super::clip::Clip;
+use super::clip::{Clip, SampleError};
+use super::curves::LoopMode;
+use super::mask::BoneMask;
+use super::morph::MorphWeights;
+use super::palette::MatrixPalette;
 use super::pose::Pose;
+use super::root_motion::{count_loops, RootMotion};
 use super::skeleton::Skeleton;
+use math::dual_quat::DualQuat;
 use math::mat4::Mat4;
+use math::transform::Transform;
+
+/// a clip event crossed during an update
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationEvent {
+    pub name: String,
+    pub clip_index: usize,
+    /// time of the event on the clip timeline
+    pub time: f32,
+}
+
+/// a track that could not be sampled during an update
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AnimationError {
+    pub clip_index: usize,
+    pub error: SampleError,
+}
+
+/// a clip being faded in on top of the current one
+#[derive(Clone)]
+pub struct CrossFadeTarget {
+    pub clip_index: usize,
+    pub time: f32,
+    pub duration: f32,
+    pub elapsed: f32,
+    pose: Pose,
+    weights: MorphWeights,
+    /// root motion of the last sample
+    motion: Option<Transform>,
+    /// false until the first update, events at the start time are reported then
+    sampled: bool,
+}
+
+/// how a layer is combined with the layers below it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LayerMode {
+    /// the clip comes from `make_additive` and its difference is added on top
+    Additive,
+    /// the clip replaces the pose below it, usually restricted with a mask
+    Override,
+}
+
+/// a clip layered on top of the base animation
+#[derive(Clone)]
+pub struct AnimationLayer {
+    pub clip_index: usize,
+    pub mode: LayerMode,
+    pub time: f32,
+    pub weight: f32,
+    /// joints the layer affects, None affects every joint
+    pub mask: Option<BoneMask>,
+    pose: Pose,
+    sampled: bool,
+}
 
 #[derive(Clone)]
 pub struct AnimationController {
@@ -11,40 +71,278 @@ pub struct AnimationController {
     is_playing: bool,
     current_pose: Pose,
     skeleton: Skeleton,
+    /// clips fading in, oldest first. each one is blended over the result of the previous
+    fade_targets: Vec<CrossFadeTarget>,
+    /// layers, applied in order after the base clip and fades
+    layers: Vec<AnimationLayer>,
+    /// every joint at identity, additive clips are sampled into this
+    identity_pose: Pose,
+    /// extracts the root joint motion from the clips when set
+    root_motion: Option<RootMotion>,
+    /// root motion of the last sample of the current clip
+    current_motion: Option<Transform>,
+    /// root motion produced by the last update
+    root_motion_delta: Transform,
+    /// false until the first update of the current clip
+    current_sampled: bool,
+    /// clip events crossed since the last drain
+    events: Vec<AnimationEvent>,
+    /// tracks that failed to sample since the last drain, each one once
+    errors: Vec<AnimationError>,
+    /// playback rate of every clip, on top of the speed of each clip
+    speed: f32,
+    /// skinning matrices of the last update_palette
+    palette: MatrixPalette,
+    /// morph target weights of the meshes when no clip animates them
+    rest_weights: MorphWeights,
+    /// morph target weights written by the last update
+    current_weights: MorphWeights,
 }
 
 impl AnimationController {
+    /// panics when the joints of the skeleton do not form a tree, see try_new
     pub fn new(skeleton: Skeleton) -> Self {
-        Self {
+        match Self::try_new(skeleton) {
+            Ok(controller) => controller,
+            Err(err) => panic!("invalid skeleton: {}", err),
+        }
+    }
+
+    /// fails when the joints of the skeleton do not form a tree or the inverse bind matrices
+    /// do not match them, use this for skeletons read from files
+    pub fn try_new(skeleton: Skeleton) -> Result<Self, String> {
+        let palette = MatrixPalette::new(&skeleton)?;
+        let mut identity_pose = skeleton.rest_pose.clone();
+        identity_pose.joints.fill(Transform::DEFAULT);
+
+        Ok(Self {
             clips: Vec::new(),
             current_clip_index: None,
             current_time: 0.0,
             is_playing: false,
             current_pose: skeleton.rest_pose.clone(),
             skeleton,
-        }
+            fade_targets: Vec::new(),
+            layers: Vec::new(),
+            identity_pose,
+            root_motion: None,
+            current_motion: None,
+            root_motion_delta: Transform::DEFAULT,
+            current_sampled: false,
+            events: Vec::new(),
+            errors: Vec::new(),
+            speed: 1.0,
+            palette,
+            rest_weights: MorphWeights::new(),
+            current_weights: MorphWeights::new(),
+        })
     }
 
     pub fn add_clip(&mut self, clip: Clip) -> usize {
+        if let Some(root_motion) = &mut self.root_motion {
+            root_motion.add_clip(&self.skeleton.rest_pose, &clip);
+        }
         self.clips.push(clip);
         self.clips.len() - 1
     }
 
-    pub fn play(&mut self, clip_index: usize) {
-        if clip_index < self.clips.len() {
-            self.current_clip_index = Some(clip_index);
-            self.current_time = 0.0;
-            self.is_playing = true;
-            self.current_pose = self.skeleton.rest_pose.clone();
+    pub fn play(&mut self, clip_index: usize) -> Result<(), String> {
+        self.check_clip_index(clip_index)?;
+
+        self.current_clip_index = Some(clip_index);
+        self.current_time = self.get_playback_start(clip_index);
+        self.is_playing = true;
+        self.current_pose = self.skeleton.rest_pose.clone();
+        self.current_weights = self.rest_weights.clone();
+        self.current_motion = self.get_start_motion(clip_index);
+        self.current_sampled = false;
+        self.fade_targets.clear();
+
+        Ok(())
+    }
+
+    /// fade from whatever is currently playing to the specified clip over `duration` seconds
+    /// several fades can be in flight at once, fading to the clip already being faded to does nothing
+    pub fn crossfade_to(&mut self, clip_index: usize, duration: f32) -> Result<(), String> {
+        self.check_clip_index(clip_index)?;
+
+        if self.current_clip_index.is_none() || duration <= 0.0 {
+            return self.play(clip_index);
+        }
+
+        let target_clip = match self.fade_targets.last() {
+            Some(target) => Some(target.clip_index),
+            None => self.current_clip_index,
+        };
+        if target_clip == Some(clip_index) {
+            return Ok(());
+        }
+
+        self.push_fade_target(clip_index, duration);
+
+        Ok(())
+    }
+
+    /// like crossfade_to but the clip always fades in again from its start,
+    /// even when it is the one playing or being faded to
+    pub fn crossfade_restart(&mut self, clip_index: usize, duration: f32) -> Result<(), String> {
+        self.check_clip_index(clip_index)?;
+
+        if self.current_clip_index.is_none() || duration <= 0.0 {
+            return self.play(clip_index);
+        }
+
+        self.push_fade_target(clip_index, duration);
+
+        Ok(())
+    }
+
+    fn push_fade_target(&mut self, clip_index: usize, duration: f32) {
+        self.is_playing = true;
+        self.fade_targets.push(CrossFadeTarget {
+            clip_index,
+            time: self.get_playback_start(clip_index),
+            duration,
+            elapsed: 0.0,
+            pose: self.skeleton.rest_pose.clone(),
+            weights: self.rest_weights.clone(),
+            motion: self.get_start_motion(clip_index),
+            sampled: false,
+        });
+    }
+
+    fn check_clip_index(&self, clip_index: usize) -> Result<(), String> {
+        if clip_index >= self.clips.len() {
+            return Err(format!("clip index {} out of bounds", clip_index));
+        }
+
+        Ok(())
+    }
+
+    /// playback rate of a clip, negative rates play backwards
+    fn get_rate(&self, clip_index: usize) -> f32 {
+        self.speed * self.clips[clip_index].get_speed()
+    }
+
+    /// where playback of a clip begins, clips playing backwards start at their end
+    fn get_playback_start(&self, clip_index: usize) -> f32 {
+        let clip = &self.clips[clip_index];
+        if self.get_rate(clip_index) < 0.0 {
+            clip.get_end_time()
         } else {
-            println!("Clip index {} out of bounds", clip_index);
+            clip.get_start_time()
+        }
+    }
+
+    /// root motion of a clip where its playback begins
+    fn get_start_motion(&self, clip_index: usize) -> Option<Transform> {
+        let clip = &self.clips[clip_index];
+        match &self.root_motion {
+            // the end of a looping clip is its start again
+            Some(root_motion)
+                if self.get_rate(clip_index) < 0.0 && clip.get_loop_mode() != LoopMode::Loop =>
+            {
+                Some(root_motion.get_clip_motion(clip_index))
+            }
+            _ => Some(Transform::DEFAULT),
         }
     }
 
+    /// playback rate of the whole controller, multiplied with the speed of each clip
+    /// negative rates play backwards, crossfades follow the magnitude of the rate
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+    }
+
+    pub fn get_speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// move the horizontal translation and yaw of `joint` out of the pose and into
+    /// `get_root_motion`, None turns root motion off
+    /// fails when the joint is out of bounds, root motion is left as it was
+    pub fn set_root_motion(&mut self, joint: Option<usize>) -> Result<(), String> {
+        self.root_motion = match joint {
+            Some(joint) if joint < self.skeleton.rest_pose.joints.len() => {
+                let mut root_motion = RootMotion::new(joint);
+                for clip in &self.clips {
+                    root_motion.add_clip(&self.skeleton.rest_pose, clip);
+                }
+                Some(root_motion)
+            }
+            Some(joint) => return Err(format!("joint index {} out of bounds", joint)),
+            None => None,
+        };
+
+        self.current_motion = None;
+        for target in &mut self.fade_targets {
+            target.motion = None;
+        }
+        self.root_motion_delta = Transform::DEFAULT;
+
+        Ok(())
+    }
+
+    /// motion of the root joint during the last update, in the space of its parent
+    /// identity when root motion is off
+    pub fn get_root_motion(&self) -> &Transform {
+        &self.root_motion_delta
+    }
+
+    /// push a layer on top of the stack and return its index
+    /// additive layers expect a clip made with `make_additive`
+    pub fn push_layer(
+        &mut self,
+        clip_index: usize,
+        mode: LayerMode,
+        weight: f32,
+        mask: Option<BoneMask>,
+    ) -> Result<usize, String> {
+        self.check_clip_index(clip_index)?;
+
+        self.layers.push(AnimationLayer {
+            clip_index,
+            mode,
+            time: self.get_playback_start(clip_index),
+            weight,
+            mask,
+            pose: self.identity_pose.clone(),
+            sampled: false,
+        });
+
+        Ok(self.layers.len() - 1)
+    }
+
+    pub fn remove_layer(&mut self, layer_index: usize) {
+        if layer_index < self.layers.len() {
+            self.layers.remove(layer_index);
+        }
+    }
+
+    pub fn set_layer_weight(&mut self, layer_index: usize, weight: f32) {
+        if let Some(layer) = self.layers.get_mut(layer_index) {
+            layer.weight = weight;
+        }
+    }
+
+    pub fn set_layer_mask(&mut self, layer_index: usize, mask: Option<BoneMask>) {
+        if let Some(layer) = self.layers.get_mut(layer_index) {
+            layer.mask = mask;
+        }
+    }
+
+    pub fn clear_layers(&mut self) {
+        self.layers.clear();
+    }
+
     pub fn stop(&mut self) {
         self.is_playing = false;
         self.current_time = 0.0;
         self.current_pose = self.skeleton.rest_pose.clone();
+        self.current_weights = self.rest_weights.clone();
+        self.current_motion = None;
+        self.current_sampled = false;
+        self.fade_targets.clear();
     }
 
     pub fn pause(&mut self) {
@@ -56,36 +354,213 @@ impl AnimationController {
     }
 
     pub fn update(&mut self, delta_time: f32) {
+        self.root_motion_delta = Transform::DEFAULT;
         if !self.is_playing {
             return;
         }
 
+        // a finished fade replaces the current clip and every fade older than it
+        if let Some(finished) = self
+            .fade_targets
+            .iter()
+            .rposition(|target| target.elapsed >= target.duration)
+        {
+            let target = self.fade_targets[finished].clone();
+            self.current_clip_index = Some(target.clip_index);
+            self.current_time = target.time;
+            self.current_motion = target.motion;
+            self.current_sampled = target.sampled;
+            self.fade_targets.drain(0..=finished);
+        }
+
+        let mut errors = Vec::new();
         if let Some(clip_index) = self.current_clip_index {
-            self.current_time += delta_time;
+            let clip = &mut self.clips[clip_index];
+            let time = self.current_time + delta_time * self.speed * clip.get_speed();
+            let loops = count_loops(clip, self.current_time, time);
+            push_events(
+                &mut self.events,
+                clip,
+                clip_index,
+                self.current_time,
+                time,
+                !self.current_sampled,
+            );
+            self.current_sampled = true;
+
             self.current_pose = self.skeleton.rest_pose.clone();
-            self.clips[clip_index].sample(&mut self.current_pose, self.current_time);
+            self.current_weights = self.rest_weights.clone();
+            clip.sample_weights(&mut self.current_weights, time, &mut errors);
+            self.current_time = clip.sample(&mut self.current_pose, time, &mut errors);
+            push_errors(&mut self.errors, clip_index, &mut errors);
+
+            if let Some(root_motion) = &self.root_motion {
+                self.root_motion_delta = root_motion.extract(
+                    clip_index,
+                    &mut self.current_pose,
+                    &mut self.current_motion,
+                    loops,
+                );
+            }
+        }
+
+        for target in &mut self.fade_targets {
+            target.pose = self.skeleton.rest_pose.clone();
+            let clip = &mut self.clips[target.clip_index];
+            let time = target.time + delta_time * self.speed * clip.get_speed();
+            let loops = count_loops(clip, target.time, time);
+            push_events(
+                &mut self.events,
+                clip,
+                target.clip_index,
+                target.time,
+                time,
+                !target.sampled,
+            );
+            target.sampled = true;
+
+            target.weights = self.rest_weights.clone();
+            clip.sample_weights(&mut target.weights, time, &mut errors);
+            target.time = clip.sample(&mut target.pose, time, &mut errors);
+            push_errors(&mut self.errors, target.clip_index, &mut errors);
+            target.elapsed += delta_time * self.speed.abs();
+
+            let t = (target.elapsed / target.duration).clamp(0.0, 1.0);
+
+            // the motion is blended with the same weight as the pose
+            if let Some(root_motion) = &self.root_motion {
+                let delta = root_motion.extract(
+                    target.clip_index,
+                    &mut target.pose,
+                    &mut target.motion,
+                    loops,
+                );
+                self.root_motion_delta = self.root_motion_delta.lerp(&delta, t);
+            }
+
+            let current = self.current_pose.clone();
+            Pose::blend(&mut self.current_pose, &current, &target.pose, t);
+            let current = self.current_weights.clone();
+            MorphWeights::blend(&mut self.current_weights, &current, &target.weights, t);
+        }
+
+        for layer in &mut self.layers {
+            // additive clips hold differences so they start from identity instead of the rest pose
+            layer.pose = match layer.mode {
+                LayerMode::Additive => self.identity_pose.clone(),
+                LayerMode::Override => self.skeleton.rest_pose.clone(),
+            };
+            let clip = &mut self.clips[layer.clip_index];
+            let time = layer.time + delta_time * self.speed * clip.get_speed();
+            push_events(
+                &mut self.events,
+                clip,
+                layer.clip_index,
+                layer.time,
+                time,
+                !layer.sampled,
+            );
+            layer.sampled = true;
+
+            layer.time = clip.sample(&mut layer.pose, time, &mut errors);
+            push_errors(&mut self.errors, layer.clip_index, &mut errors);
+
+            // layers do not move the character, an override layer would bring the root motion back
+            if let (LayerMode::Override, Some(root_motion)) = (layer.mode, &self.root_motion) {
+                root_motion.remove(layer.clip_index, &mut layer.pose);
+            }
+
+            let base = self.current_pose.clone();
+            match (layer.mode, &layer.mask) {
+                (LayerMode::Additive, None) => Pose::add(
+                    &mut self.current_pose,
+                    &base,
+                    &layer.pose,
+                    &self.identity_pose,
+                    layer.weight,
+                ),
+                (LayerMode::Additive, Some(mask)) => Pose::add_masked(
+                    &mut self.current_pose,
+                    &base,
+                    &layer.pose,
+                    &self.identity_pose,
+                    layer.weight,
+                    mask,
+                ),
+                (LayerMode::Override, None) => {
+                    Pose::blend(&mut self.current_pose, &base, &layer.pose, layer.weight)
+                }
+                (LayerMode::Override, Some(mask)) => Pose::blend_masked(
+                    &mut self.current_pose,
+                    &base,
+                    &layer.pose,
+                    layer.weight,
+                    mask,
+                ),
+            }
         }
     }
 
-    pub fn get_pose_matrices(&self) -> Vec<Mat4> {
-        let mut final_mats = Vec::new();
-        let len = self.skeleton.rest_pose.joints.len();
-        final_mats.resize(len, Mat4::IDENTITY);
+    /// take every clip event crossed since the last call
+    /// each update reports the current clip first, then the fading clips and the layers
+    pub fn drain_events(&mut self) -> Vec<AnimationEvent> {
+        std::mem::take(&mut self.events)
+    }
 
+    /// take every track that failed to sample since the last call
+    /// the joints and nodes of those tracks keep their rest pose and weights
+    pub fn drain_errors(&mut self) -> Vec<AnimationError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    /// skinning matrices of the current pose, or the rest pose when nothing plays
+    /// the matrices live in a buffer reused by every call
+    pub fn update_palette(&mut self) -> &[Mat4] {
         let pose = if self.is_playing {
             &self.current_pose
         } else {
             &self.skeleton.rest_pose
         };
 
-        for i in 0..len {
-            if let Some(inverse_pose) = self.skeleton.inverse_bind_pose[i] {
-                let world = pose.get_global_tranform(i);
-                final_mats[i] = world.to_mat() * inverse_pose;
-            }
+        self.palette.update(pose)
+    }
+
+    /// dual quaternion version of `update_palette`
+    pub fn update_dual_quat_palette(&mut self) -> &[DualQuat] {
+        let pose = if self.is_playing {
+            &self.current_pose
+        } else {
+            &self.skeleton.rest_pose
+        };
+
+        self.palette.update_dual_quats(pose)
+    }
+
+    /// morph target weights `node` shows while no clip animates them, like its mesh weights
+    pub fn set_rest_weights(&mut self, node: u32, weights: &[f32]) {
+        self.rest_weights.set(node, weights);
+        if !self.is_playing {
+            self.current_weights = self.rest_weights.clone();
         }
+    }
+
+    /// morph target weights of the last update, layers leave them alone
+    pub fn get_weights(&self) -> &MorphWeights {
+        &self.current_weights
+    }
 
-        final_mats
+    pub fn get_skeleton(&self) -> &Skeleton {
+        &self.skeleton
+    }
+
+    pub fn get_pose(&self) -> &Pose {
+        &self.current_pose
+    }
+
+    /// pose written by the last update, post processes like ik can change it
+    /// changes last until the next update
+    pub fn get_pose_mut(&mut self) -> &mut Pose {
+        &mut self.current_pose
     }
 
     pub fn is_playing(&self) -> bool {
@@ -96,7 +571,50 @@ impl AnimationController {
         self.current_clip_index.map(|i| &self.clips[i])
     }
 
+    pub fn get_clip(&self, clip_index: usize) -> &Clip {
+        &self.clips[clip_index]
+    }
+
     pub fn clip_count(&self) -> usize {
         self.clips.len()
     }
+
+    pub fn get_fade_targets(&self) -> &[CrossFadeTarget] {
+        &self.fade_targets
+    }
+
+    pub fn get_layers(&self) -> &[AnimationLayer] {
+        &self.layers
+    }
+}
+
+fn push_events(
+    events: &mut Vec<AnimationEvent>,
+    clip: &Clip,
+    clip_index: usize,
+    from: f32,
+    to: f32,
+    include_from: bool,
+) {
+    for event in clip.events_between(from, to, include_from) {
+        events.push(AnimationEvent {
+            name: event.name.clone(),
+            clip_index,
+            time: event.time,
+        });
+    }
+}
+
+/// move the errors of a clip into `errors`, tracks already in there are not added again
+fn push_errors(
+    errors: &mut Vec<AnimationError>,
+    clip_index: usize,
+    sampled: &mut Vec<SampleError>,
+) {
+    for error in sampled.drain(..) {
+        let error = AnimationError { clip_index, error };
+        if !errors.contains(&error) {
+            errors.push(error);
+        }
+    }
 }
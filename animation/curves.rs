@@ -40,9 +40,56 @@ impl Bezier<f32> {
     }
 }
 
-#[derive(PartialEq, Clone, Copy)]
+#[derive(PartialEq, Clone, Copy, Debug)]
 pub enum Interpolation {
     Constant,
     Linear,
     Cubic,
 }
+
+/// what happens when playback runs past either end of a track or clip
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum LoopMode {
+    /// hold the first or last frame
+    Clamp,
+    /// jump back to the other end
+    Loop,
+    /// play forwards and backwards in turn
+    PingPong,
+}
+
+impl LoopMode {
+    /// map any time onto the range start..=end
+    pub fn fit(&self, time: f32, start: f32, end: f32) -> f32 {
+        let duration = end - start;
+        if duration <= 0.0 {
+            return start;
+        }
+
+        match self {
+            LoopMode::Clamp => time.clamp(start, end),
+            LoopMode::Loop => start + (time - start).rem_euclid(duration),
+            LoopMode::PingPong => {
+                // one period is a forward and a backward pass
+                let phase = (time - start).rem_euclid(2.0 * duration);
+                if phase > duration {
+                    start + 2.0 * duration - phase
+                } else {
+                    start + phase
+                }
+            }
+        }
+    }
+
+    /// like fit but ping pong times stay within a full forward and backward period
+    /// so the direction is not lost (between end and end + duration playback runs backwards)
+    pub fn wrap(&self, time: f32, start: f32, end: f32) -> f32 {
+        let duration = end - start;
+        match self {
+            LoopMode::PingPong if duration > 0.0 => {
+                start + (time - start).rem_euclid(2.0 * duration)
+            }
+            _ => self.fit(time, start, end),
+        }
+    }
+}
@@ -0,0 +1,351 @@
+// foot planting, a post process that keeps feet on uneven ground
+// each foot is raycast against the ground, the pelvis is lowered so the lowest foot can reach
+// and the legs are solved with two bone ik
+//
+// the ground lives in its own space, `to_ground` maps model space (the pose) into it
+// the ground up axis is +y
+
+use super::ik::{rotate_global, solve_two_bone};
+use super::pose::Pose;
+use super::skeleton::Skeleton;
+
+use math::quaternion::Quat;
+use math::transform::Transform;
+use math::vec3::{cross, vec3, Vec3};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GroundHit {
+    pub point: Vec3,
+    pub normal: Vec3,
+}
+
+/// geometry feet can stand on
+pub trait Ground {
+    /// cast a ray straight down from origin and return the closest hit within max_distance
+    fn raycast_down(&self, origin: &Vec3, max_distance: f32) -> Option<GroundHit>;
+}
+
+fn down_hit(origin: &Vec3, max_distance: f32, height: f32, normal: Vec3) -> Option<GroundHit> {
+    let distance = origin.y - height;
+    if distance < 0.0 || distance > max_distance {
+        return None;
+    }
+
+    Some(GroundHit {
+        point: vec3(origin.x, height, origin.z),
+        normal,
+    })
+}
+
+/// regular grid of heights on the xz plane, bilinearly interpolated
+#[derive(Clone, Debug)]
+pub struct HeightField {
+    /// position of the first sample, heights are added to origin.y
+    pub origin: Vec3,
+    pub cell_size: f32,
+    /// number of samples along x
+    pub width: usize,
+    /// number of samples along z
+    pub depth: usize,
+    /// row major, heights[z * width + x]
+    pub heights: Vec<f32>,
+}
+
+impl HeightField {
+    pub fn new(origin: Vec3, cell_size: f32, width: usize, depth: usize) -> Self {
+        Self {
+            origin,
+            cell_size,
+            width,
+            depth,
+            heights: vec![0.0; width * depth],
+        }
+    }
+
+    pub fn set_height(&mut self, x: usize, z: usize, height: f32) {
+        self.heights[z * self.width + x] = height;
+    }
+
+    fn get_height(&self, x: usize, z: usize) -> f32 {
+        self.heights[z * self.width + x]
+    }
+
+    /// interpolated height and normal, None outside the field
+    pub fn sample(&self, x: f32, z: f32) -> Option<(f32, Vec3)> {
+        if self.width < 2 || self.depth < 2 || self.cell_size <= 0.0 {
+            return None;
+        }
+
+        let fx = (x - self.origin.x) / self.cell_size;
+        let fz = (z - self.origin.z) / self.cell_size;
+        if fx < 0.0 || fz < 0.0 || fx > (self.width - 1) as f32 || fz > (self.depth - 1) as f32 {
+            return None;
+        }
+
+        let x0 = (fx.floor() as usize).min(self.width - 2);
+        let z0 = (fz.floor() as usize).min(self.depth - 2);
+        let tx = fx - x0 as f32;
+        let tz = fz - z0 as f32;
+
+        let h00 = self.get_height(x0, z0);
+        let h10 = self.get_height(x0 + 1, z0);
+        let h01 = self.get_height(x0, z0 + 1);
+        let h11 = self.get_height(x0 + 1, z0 + 1);
+
+        let height =
+            (h00 * (1.0 - tx) + h10 * tx) * (1.0 - tz) + (h01 * (1.0 - tx) + h11 * tx) * tz;
+
+        // slopes of the bilinear patch at the sample point
+        let dx = ((h10 - h00) * (1.0 - tz) + (h11 - h01) * tz) / self.cell_size;
+        let dz = ((h01 - h00) * (1.0 - tx) + (h11 - h10) * tx) / self.cell_size;
+        let normal = vec3(-dx, 1.0, -dz).unit();
+
+        Some((self.origin.y + height, normal))
+    }
+}
+
+impl Ground for HeightField {
+    fn raycast_down(&self, origin: &Vec3, max_distance: f32) -> Option<GroundHit> {
+        let (height, normal) = self.sample(origin.x, origin.z)?;
+        down_hit(origin, max_distance, height, normal)
+    }
+}
+
+/// triangle soup, every triangle is tested so keep it to the walkable surfaces
+#[derive(Clone, Debug, Default)]
+pub struct GroundMesh {
+    pub triangles: Vec<[Vec3; 3]>,
+}
+
+impl GroundMesh {
+    pub fn new() -> Self {
+        Self {
+            triangles: Vec::new(),
+        }
+    }
+
+    pub fn add_triangle(&mut self, triangle: [Vec3; 3]) {
+        self.triangles.push(triangle);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.triangles.is_empty()
+    }
+}
+
+impl Ground for GroundMesh {
+    fn raycast_down(&self, origin: &Vec3, max_distance: f32) -> Option<GroundHit> {
+        let mut closest: Option<GroundHit> = None;
+
+        for [a, b, c] in &self.triangles {
+            // barycentric coordinates of the ray in the triangle projected onto xz
+            let denominator = (b.z - c.z) * (a.x - c.x) + (c.x - b.x) * (a.z - c.z);
+            if denominator.abs() <= f32::EPSILON {
+                continue;
+            }
+            let u = ((b.z - c.z) * (origin.x - c.x) + (c.x - b.x) * (origin.z - c.z)) / denominator;
+            let v = ((c.z - a.z) * (origin.x - c.x) + (a.x - c.x) * (origin.z - c.z)) / denominator;
+            let w = 1.0 - u - v;
+            if u < 0.0 || v < 0.0 || w < 0.0 {
+                continue;
+            }
+
+            let height = a.y * u + b.y * v + c.y * w;
+            let mut normal = cross(&(*b - *a), &(*c - *a)).unit();
+            if normal.y < 0.0 {
+                normal = -normal;
+            }
+
+            if let Some(hit) = down_hit(origin, max_distance, height, normal) {
+                if closest.is_none_or(|current| hit.point.y > current.point.y) {
+                    closest = Some(hit);
+                }
+            }
+        }
+
+        closest
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Leg {
+    pub hip: usize,
+    pub knee: usize,
+    pub ankle: usize,
+    /// height of the ankle above the ground when the foot is flat, in ground space
+    pub foot_height: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct FootPlanting {
+    pub pelvis: usize,
+    pub legs: Vec<Leg>,
+    /// how far above the ankle rays start, lets feet step up onto higher ground
+    pub ray_height: f32,
+    /// how far below the ankle the ground is searched
+    pub max_distance: f32,
+    /// turn the feet to follow the slope of the ground
+    pub align_feet: bool,
+    /// global transforms of the pose being planted, reused every frame
+    globals: Vec<Transform>,
+}
+
+impl FootPlanting {
+    /// distances are in ground space so they depend on the scale of the scene
+    pub fn new(pelvis: usize, legs: Vec<Leg>, ray_height: f32, max_distance: f32) -> Self {
+        Self {
+            pelvis,
+            legs,
+            ray_height,
+            max_distance,
+            align_feet: true,
+            globals: Vec::new(),
+        }
+    }
+
+    /// find the pelvis and the hip, knee and ankle of each leg by name
+    /// foot heights are measured from the rest pose, which is expected to stand on y = 0
+    pub fn from_names(
+        skeleton: &Skeleton,
+        to_ground: &Transform,
+        pelvis: &str,
+        legs: &[[&str; 3]],
+        ray_height: f32,
+        max_distance: f32,
+    ) -> Result<Self, String> {
+        let find = |name: &str| {
+            skeleton
+                .get_joint_index(name)
+                .ok_or(format!("unknown joint {}", name))
+        };
+
+        let mut rest_globals = Vec::new();
+        skeleton.rest_pose.get_global_transforms(&mut rest_globals);
+
+        let mut found_legs = Vec::new();
+        for [hip, knee, ankle] in legs {
+            let ankle = find(ankle)?;
+            let rest_ankle = rest_globals[ankle].translation;
+
+            found_legs.push(Leg {
+                hip: find(hip)?,
+                knee: find(knee)?,
+                ankle,
+                foot_height: transform_point(to_ground, &rest_ankle).y,
+            });
+        }
+
+        Ok(Self::new(
+            find(pelvis)?,
+            found_legs,
+            ray_height,
+            max_distance,
+        ))
+    }
+
+    pub fn apply(&mut self, pose: &mut Pose, ground: &dyn Ground, to_ground: &Transform) {
+        let from_ground = to_ground.inverse();
+        let globals = &mut self.globals;
+        pose.get_global_transforms(globals);
+
+        // ankle position in ground space and the height it should be at
+        let mut targets: Vec<Option<(Vec3, GroundHit)>> = Vec::new();
+        let mut pelvis_offset: f32 = 0.0;
+        for leg in &self.legs {
+            let ankle = transform_point(to_ground, &globals[leg.ankle].translation);
+            let origin = ankle + vec3(0.0, self.ray_height, 0.0);
+
+            let hit = ground.raycast_down(&origin, self.ray_height + self.max_distance);
+            if let Some(hit) = hit {
+                let offset = hit.point.y + leg.foot_height - ankle.y;
+                pelvis_offset = pelvis_offset.min(offset);
+            }
+            targets.push(hit.map(|hit| (ankle, hit)));
+        }
+
+        // only ever lower the pelvis, raised feet are handled by bending the knees
+        if pelvis_offset < 0.0 {
+            let pelvis = globals[self.pelvis].translation;
+            let lowered = transform_point(to_ground, &pelvis) + vec3(0.0, pelvis_offset, 0.0);
+            let lowered = transform_point(&from_ground, &lowered);
+            set_global_translation(pose, globals, self.pelvis, &lowered);
+        }
+
+        let up = transform_vector(&from_ground, &vec3(0.0, 1.0, 0.0));
+        for (leg, target) in self.legs.iter().zip(targets) {
+            let (ankle, hit) = match target {
+                Some(target) => target,
+                None => continue,
+            };
+
+            let target = transform_point(
+                &from_ground,
+                &vec3(ankle.x, hit.point.y + leg.foot_height, ankle.z),
+            );
+
+            // keep the current bend direction of the knee
+            let hip = globals[leg.hip].translation;
+            let knee = globals[leg.knee];
+            let foot = globals[leg.ankle];
+            let mut bend = knee.translation - (hip + foot.translation) * 0.5;
+            if bend.len() <= 1e-4 {
+                bend = knee.orientation * vec3(0.0, 0.0, 1.0);
+            }
+            let pole = knee.translation + bend;
+
+            let joints = [leg.hip, leg.knee, leg.ankle];
+            solve_two_bone(pose, globals, joints, &target, &pole);
+
+            // the foot keeps its orientation instead of following the leg
+            set_global_orientation(pose, globals, leg.ankle, &foot.orientation);
+
+            if self.align_feet {
+                let normal = transform_vector(&from_ground, &hit.normal);
+                rotate_global(pose, globals, leg.ankle, &Quat::from_to(&up, &normal));
+            }
+        }
+    }
+}
+
+fn transform_point(transform: &Transform, point: &Vec3) -> Vec3 {
+    let local = Transform {
+        translation: *point,
+        ..Transform::DEFAULT
+    };
+
+    transform.combine(&local).translation
+}
+
+fn transform_vector(transform: &Transform, vector: &Vec3) -> Vec3 {
+    transform_point(transform, vector) - transform_point(transform, &Vec3::ZERO)
+}
+
+fn parent_transform(pose: &Pose, globals: &[Transform], joint: usize) -> Transform {
+    match pose.parents[joint] {
+        p if p >= 0 => globals[p as usize],
+        _ => Transform::DEFAULT,
+    }
+}
+
+/// like ik::rotate_global, `globals` are brought up to date
+fn set_global_translation(
+    pose: &mut Pose,
+    globals: &mut Vec<Transform>,
+    joint: usize,
+    translation: &Vec3,
+) {
+    let parent = parent_transform(pose, globals, joint);
+    pose.joints[joint].translation = transform_point(&parent.inverse(), translation);
+    pose.get_global_transforms(globals);
+}
+
+fn set_global_orientation(
+    pose: &mut Pose,
+    globals: &mut Vec<Transform>,
+    joint: usize,
+    orientation: &Quat,
+) {
+    let parent = parent_transform(pose, globals, joint);
+    pose.joints[joint].orientation = (parent.orientation.inverse() * *orientation).unit();
+    pose.get_global_transforms(globals);
+}
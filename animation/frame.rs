@@ -1,4 +1,4 @@
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Frame<const N: usize> {
     pub m_value: [f32; N],
     pub m_in: [f32; N],
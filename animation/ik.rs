@@ -0,0 +1,395 @@
+// inverse kinematics solvers working directly on a pose
+// meant to run as a post process after the controller update, targets are in model space
+// (the same space as Pose::get_global_transforms)
+// every solver takes a scratch buffer for the global transforms of the pose, reuse it between
+// calls to avoid allocating every frame
+
+use super::pose::Pose;
+use super::skeleton::Skeleton;
+
+use math::misc::degrees;
+use math::quaternion::{quat, Quat};
+use math::transform::Transform;
+use math::vec3::{cross, dot, Vec3};
+
+/// limits how far a joint can rotate away from its rest orientation, angles are in degrees
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JointLimit {
+    /// free rotation up to max_angle in any direction, like a shoulder or hip
+    Cone { max_angle: f32 },
+    /// rotation only around a local axis, like a knee or elbow
+    Hinge {
+        axis: Vec3,
+        min_angle: f32,
+        max_angle: f32,
+    },
+}
+
+impl JointLimit {
+    /// constrain a local orientation relative to the rest orientation of the joint
+    pub fn apply(&self, rest: &Quat, orientation: &Quat) -> Quat {
+        let mut relative = rest.inverse() * *orientation;
+        if relative.s < 0.0 {
+            relative = -relative;
+        }
+
+        let limited = match self {
+            JointLimit::Cone { max_angle } => {
+                if relative.angle() <= *max_angle {
+                    relative
+                } else {
+                    Quat::create(*max_angle, relative.axis())
+                }
+            }
+            JointLimit::Hinge {
+                axis,
+                min_angle,
+                max_angle,
+            } => {
+                // keep the twist around the hinge axis and drop everything else
+                let axis = axis.unit();
+                let projected = dot(&relative.axis(), &axis);
+                let twist = quat(
+                    axis.x * projected,
+                    axis.y * projected,
+                    axis.z * projected,
+                    relative.s,
+                );
+                if twist.norm() <= 0.0 {
+                    Quat::ZERO
+                } else {
+                    let twist = twist.unit();
+                    let mut angle = twist.angle();
+                    if dot(&twist.axis(), &axis) < 0.0 {
+                        angle = -angle;
+                    }
+                    Quat::create(angle.clamp(*min_angle, *max_angle), axis)
+                }
+            }
+        };
+
+        (*rest * limited).unit()
+    }
+}
+
+/// joints from the root of the chain to its tip
+/// every joint has to be an ancestor of the next one
+#[derive(Clone)]
+pub struct IKChain {
+    pub joints: Vec<usize>,
+    /// optional limit per joint, parallel to joints
+    pub limits: Vec<Option<JointLimit>>,
+    /// local rest orientations the limits are relative to
+    rest: Vec<Quat>,
+}
+
+impl IKChain {
+    /// rest orientations are taken from `pose`, usually the skeleton rest pose
+    pub fn new(pose: &Pose, joints: Vec<usize>) -> Result<Self, String> {
+        if joints.len() < 2 {
+            return Err(String::from("an ik chain needs at least two joints"));
+        }
+
+        for pair in joints.windows(2) {
+            if !is_ancestor(pose, pair[0], pair[1]) {
+                return Err(format!(
+                    "joint {} is not an ancestor of joint {}",
+                    pair[0], pair[1]
+                ));
+            }
+        }
+
+        Ok(Self {
+            limits: vec![None; joints.len()],
+            rest: joints
+                .iter()
+                .map(|joint| pose.joints[*joint].orientation)
+                .collect(),
+            joints,
+        })
+    }
+
+    /// build a chain from joint names, from the root of the chain to its tip
+    pub fn from_names(skeleton: &Skeleton, names: &[&str]) -> Result<Self, String> {
+        let joints = names
+            .iter()
+            .map(|name| {
+                skeleton
+                    .get_joint_index(name)
+                    .ok_or(format!("unknown joint {}", name))
+            })
+            .collect::<Result<Vec<usize>, String>>()?;
+
+        Self::new(&skeleton.rest_pose, joints)
+    }
+
+    pub fn set_limit(&mut self, index: usize, limit: Option<JointLimit>) {
+        self.limits[index] = limit;
+    }
+
+    pub fn get_tip(&self) -> usize {
+        *self.joints.last().unwrap()
+    }
+
+    /// `globals` are brought up to date when the limit changes the joint
+    fn apply_limit(&self, pose: &mut Pose, globals: &mut Vec<Transform>, index: usize) {
+        if let Some(limit) = &self.limits[index] {
+            let joint = &mut pose.joints[self.joints[index]];
+            joint.orientation = limit.apply(&self.rest[index], &joint.orientation);
+            pose.get_global_transforms(globals);
+        }
+    }
+}
+
+fn is_ancestor(pose: &Pose, ancestor: usize, joint: usize) -> bool {
+    let mut current = pose.parents[joint];
+    while current >= 0 {
+        if current as usize == ancestor {
+            return true;
+        }
+        current = pose.parents[current as usize];
+    }
+
+    false
+}
+
+/// apply a model space rotation to a joint by changing its local orientation
+/// `globals` has to hold the global transforms of the pose, they are brought up to date
+pub(crate) fn rotate_global(
+    pose: &mut Pose,
+    globals: &mut Vec<Transform>,
+    joint: usize,
+    rotation: &Quat,
+) {
+    let global = globals[joint].orientation;
+    let parent = match pose.parents[joint] {
+        p if p >= 0 => globals[p as usize].orientation,
+        _ => Quat::ZERO,
+    };
+
+    pose.joints[joint].orientation = (parent.inverse() * *rotation * global).unit();
+    pose.get_global_transforms(globals);
+}
+
+/// analytic ik for a root, mid and end joint like shoulder, elbow and wrist
+/// the chain bends towards `pole`, a model space point in front of the knee or behind the elbow
+/// unreachable targets stretch the limb as far as it goes
+pub fn solve_two_bone(
+    pose: &mut Pose,
+    globals: &mut Vec<Transform>,
+    joints: [usize; 3],
+    target: &Vec3,
+    pole: &Vec3,
+) {
+    let [root, mid, end] = joints;
+
+    pose.get_global_transforms(globals);
+    let a = globals[root].translation;
+    let b = globals[mid].translation;
+    let c = globals[end].translation;
+
+    let upper = (b - a).len();
+    let lower = (c - b).len();
+    if upper <= 0.0 || lower <= 0.0 {
+        return;
+    }
+
+    let epsilon = 1e-4;
+    let reach = (*target - a).len().clamp(epsilon, upper + lower - epsilon);
+
+    // bend the middle joint so the root to end distance matches the target distance
+    let current_angle = dot(&(a - b).unit(), &(c - b).unit())
+        .clamp(-1.0, 1.0)
+        .acos();
+    let wanted_angle = ((upper * upper + lower * lower - reach * reach) / (2.0 * upper * lower))
+        .clamp(-1.0, 1.0)
+        .acos();
+
+    let mut bend_axis = cross(&(c - a), &(b - a));
+    if bend_axis.len() <= epsilon {
+        bend_axis = cross(&(c - a), &(*pole - a));
+    }
+    if bend_axis.len() > epsilon {
+        let bend = Quat::create(degrees(wanted_angle - current_angle), bend_axis.unit());
+        rotate_global(pose, globals, mid, &bend);
+    }
+
+    // swing the whole limb so the end points at the target
+    let c = globals[end].translation;
+    rotate_global(
+        pose,
+        globals,
+        root,
+        &Quat::from_to(&(c - a), &(*target - a)),
+    );
+
+    // twist around the root to target axis until the middle joint faces the pole
+    let direction = *target - a;
+    if direction.len() <= epsilon {
+        return;
+    }
+    let direction = direction.unit();
+    let b = globals[mid].translation;
+    let to_mid = (b - a) - direction * dot(&(b - a), &direction);
+    let to_pole = (*pole - a) - direction * dot(&(*pole - a), &direction);
+    if to_mid.len() > epsilon && to_pole.len() > epsilon {
+        let angle = f32::atan2(
+            dot(&cross(&to_mid, &to_pole), &direction),
+            dot(&to_mid, &to_pole),
+        );
+        rotate_global(
+            pose,
+            globals,
+            root,
+            &Quat::create(degrees(angle), direction),
+        );
+    }
+}
+
+/// cyclic coordinate descent, rotates each joint from the tip to the root towards the target
+#[derive(Clone, Copy, Debug)]
+pub struct CCDSolver {
+    pub iterations: u32,
+    /// distance from the target counted as reached
+    pub threshold: f32,
+}
+
+impl Default for CCDSolver {
+    fn default() -> Self {
+        Self::new(15, 1e-3)
+    }
+}
+
+impl CCDSolver {
+    pub fn new(iterations: u32, threshold: f32) -> Self {
+        Self {
+            iterations,
+            threshold,
+        }
+    }
+
+    /// returns true when the tip of the chain reached the target
+    pub fn solve(
+        &self,
+        pose: &mut Pose,
+        globals: &mut Vec<Transform>,
+        chain: &IKChain,
+        target: &Vec3,
+    ) -> bool {
+        let tip = chain.get_tip();
+        pose.get_global_transforms(globals);
+
+        for _ in 0..self.iterations {
+            for i in (0..chain.joints.len() - 1).rev() {
+                if (globals[tip].translation - *target).len() < self.threshold {
+                    return true;
+                }
+
+                let joint = chain.joints[i];
+                let origin = globals[joint].translation;
+                let rotation =
+                    Quat::from_to(&(globals[tip].translation - origin), &(*target - origin));
+
+                rotate_global(pose, globals, joint, &rotation);
+                chain.apply_limit(pose, globals, i);
+            }
+        }
+
+        (globals[tip].translation - *target).len() < self.threshold
+    }
+}
+
+/// forward and backward reaching ik, moves the joint positions along the chain
+/// and then turns the joints to match them
+#[derive(Clone, Copy, Debug)]
+pub struct FABRIKSolver {
+    pub iterations: u32,
+    /// distance from the target counted as reached
+    pub threshold: f32,
+}
+
+impl Default for FABRIKSolver {
+    fn default() -> Self {
+        Self::new(15, 1e-3)
+    }
+}
+
+impl FABRIKSolver {
+    pub fn new(iterations: u32, threshold: f32) -> Self {
+        Self {
+            iterations,
+            threshold,
+        }
+    }
+
+    /// returns true when the tip of the chain reached the target
+    pub fn solve(
+        &self,
+        pose: &mut Pose,
+        globals: &mut Vec<Transform>,
+        chain: &IKChain,
+        target: &Vec3,
+    ) -> bool {
+        let len = chain.joints.len();
+        let tip = chain.get_tip();
+        pose.get_global_transforms(globals);
+
+        let mut positions = chain
+            .joints
+            .iter()
+            .map(|joint| globals[*joint].translation)
+            .collect::<Vec<Vec3>>();
+        let lengths = positions
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]).len())
+            .collect::<Vec<f32>>();
+        let base = positions[0];
+
+        for _ in 0..self.iterations {
+            if (globals[tip].translation - *target).len() < self.threshold {
+                return true;
+            }
+
+            // backward, pin the tip to the target
+            positions[len - 1] = *target;
+            for i in (0..len - 1).rev() {
+                positions[i] =
+                    positions[i + 1] + direction(positions[i + 1], positions[i]) * lengths[i];
+            }
+
+            // forward, pin the root back to where it was
+            positions[0] = base;
+            for i in 1..len {
+                positions[i] =
+                    positions[i - 1] + direction(positions[i - 1], positions[i]) * lengths[i - 1];
+            }
+
+            // turn each joint so its child lands on the solved position
+            for i in 0..len - 1 {
+                let origin = globals[chain.joints[i]].translation;
+                let child = globals[chain.joints[i + 1]].translation;
+                let rotation = Quat::from_to(&(child - origin), &(positions[i + 1] - origin));
+
+                rotate_global(pose, globals, chain.joints[i], &rotation);
+                chain.apply_limit(pose, globals, i);
+            }
+
+            // limits may have moved things, continue from what the pose can actually do
+            for (i, joint) in chain.joints.iter().enumerate() {
+                positions[i] = globals[*joint].translation;
+            }
+        }
+
+        (globals[tip].translation - *target).len() < self.threshold
+    }
+}
+
+/// unit direction from a to b, zero when both points are the same
+fn direction(a: Vec3, b: Vec3) -> Vec3 {
+    let delta = b - a;
+    if delta.len() <= 0.0 {
+        Vec3::ZERO
+    } else {
+        delta.unit()
+    }
+}
@@ -1,8 +1,21 @@
+pub mod binary;
+pub mod blend_space;
 pub mod clip;
+pub mod compression;
 pub mod controller;
 pub mod curves;
+pub mod foot_planting;
 pub mod frame;
+pub mod ik;
+pub mod mask;
+pub mod morph;
+pub mod palette;
 pub mod pose;
+pub mod retarget;
+pub mod root_motion;
 pub mod skeleton;
+pub mod skinning;
+pub mod state_machine;
 pub mod track;
 pub mod track_transform;
+pub mod track_weights;
@@ -0,0 +1,70 @@
+use super::skeleton::Skeleton;
+
+/// per joint weights used to restrict blends and layers to part of the body
+/// weights are parallel to the skeleton joints, 0 leaves a joint alone and 1 fully applies
+#[derive(Clone, Debug, PartialEq)]
+pub struct BoneMask {
+    pub weights: Vec<f32>,
+}
+
+impl BoneMask {
+    /// mask with every joint set to 0
+    pub fn new(skeleton: &Skeleton) -> Self {
+        Self {
+            weights: vec![0.0; skeleton.joint_names.len()],
+        }
+    }
+
+    /// mask with every joint set to 1
+    pub fn full(skeleton: &Skeleton) -> Self {
+        Self {
+            weights: vec![1.0; skeleton.joint_names.len()],
+        }
+    }
+
+    pub fn get_weight(&self, joint: usize) -> f32 {
+        self.weights.get(joint).copied().unwrap_or(0.0)
+    }
+
+    /// set the weight of a single joint, returns false when no joint has that name
+    pub fn set_joint(&mut self, skeleton: &Skeleton, name: &str, weight: f32) -> bool {
+        match skeleton.get_joint_index(name) {
+            Some(joint) => {
+                self.weights[joint] = weight;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// set the weight of a joint and all of its children
+    /// returns false when no joint has that name
+    pub fn set_subtree(&mut self, skeleton: &Skeleton, name: &str, weight: f32) -> bool {
+        let root = match skeleton.get_joint_index(name) {
+            Some(root) => root,
+            None => return false,
+        };
+
+        let parents = &skeleton.rest_pose.parents;
+        for (joint, mask_weight) in self.weights.iter_mut().enumerate() {
+            // walk up from the joint until the root of the subtree or the skeleton root is hit
+            let mut current = joint as i32;
+            while current >= 0 {
+                if current as usize == root {
+                    *mask_weight = weight;
+                    break;
+                }
+                current = parents[current as usize];
+            }
+        }
+
+        true
+    }
+
+    /// flip every weight, turning an upper body mask into a lower body one
+    pub fn invert(&mut self) {
+        for weight in &mut self.weights {
+            *weight = 1.0 - *weight;
+        }
+    }
+}
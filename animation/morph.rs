@@ -0,0 +1,144 @@
+// morph targets, also called blend shapes
+// a target stores an offset per vertex and the mesh is its base plus every offset times the
+// weight of its target. the cpu side here mirrors shader.vert like skinning.rs does
+
+use math::vec3::Vec3;
+
+/// most morph targets a primitive can have on the gpu, targets past it are ignored there
+pub const MAX_MORPH_TARGETS: usize = 64;
+
+/// offsets of a single target, one per vertex
+/// attributes the target does not move are left empty
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MorphTarget {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub tangents: Vec<[f32; 3]>,
+}
+
+/// base value plus the offsets of every target weighted by its weight
+fn morph(
+    value: &[f32; 3],
+    vertex: usize,
+    targets: &[MorphTarget],
+    weights: &[f32],
+    offsets: fn(&MorphTarget) -> &[[f32; 3]],
+) -> Vec3 {
+    let mut result = Vec3::from(value);
+    for (target, weight) in targets.iter().zip(weights) {
+        if *weight == 0.0 {
+            continue;
+        }
+        if let Some(offset) = offsets(target).get(vertex) {
+            result = result + Vec3::from(offset) * *weight;
+        }
+    }
+
+    result
+}
+
+pub fn morph_position(
+    position: &[f32; 3],
+    vertex: usize,
+    targets: &[MorphTarget],
+    weights: &[f32],
+) -> [f32; 3] {
+    morph(position, vertex, targets, weights, |target| {
+        &target.positions
+    })
+    .to_array()
+}
+
+/// the result is normalized
+pub fn morph_normal(
+    normal: &[f32; 3],
+    vertex: usize,
+    targets: &[MorphTarget],
+    weights: &[f32],
+) -> [f32; 3] {
+    let result = morph(normal, vertex, targets, weights, |target| &target.normals);
+    if result.len() > 0.0 {
+        result.unit().to_array()
+    } else {
+        *normal
+    }
+}
+
+/// the result is normalized
+pub fn morph_tangent(
+    tangent: &[f32; 3],
+    vertex: usize,
+    targets: &[MorphTarget],
+    weights: &[f32],
+) -> [f32; 3] {
+    let result = morph(tangent, vertex, targets, weights, |target| &target.tangents);
+    if result.len() > 0.0 {
+        result.unit().to_array()
+    } else {
+        *tangent
+    }
+}
+
+/// morph target weights of every node a controller animates
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MorphWeights {
+    nodes: Vec<(u32, Vec<f32>)>,
+}
+
+impl MorphWeights {
+    pub fn new() -> Self {
+        Self { nodes: Vec::new() }
+    }
+
+    pub fn get(&self, node: u32) -> Option<&[f32]> {
+        self.nodes
+            .iter()
+            .find(|(id, _)| *id == node)
+            .map(|(_, weights)| weights.as_slice())
+    }
+
+    /// weights of `node`, created or grown with zeros until there are at least `len`
+    pub fn get_mut(&mut self, node: u32, len: usize) -> &mut [f32] {
+        let index = match self.nodes.iter().position(|(id, _)| *id == node) {
+            Some(index) => index,
+            None => {
+                self.nodes.push((node, Vec::new()));
+                self.nodes.len() - 1
+            }
+        };
+
+        let weights = &mut self.nodes[index].1;
+        if weights.len() < len {
+            weights.resize(len, 0.0);
+        }
+
+        weights
+    }
+
+    pub fn set(&mut self, node: u32, weights: &[f32]) {
+        let out = self.get_mut(node, weights.len());
+        out[..weights.len()].copy_from_slice(weights);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// linear blend between two sets of weights, t = 0 gives a and t = 1 gives b
+    /// a node or target missing from one side counts as a weight of zero there
+    pub fn blend(out: &mut MorphWeights, a: &MorphWeights, b: &MorphWeights, t: f32) {
+        *out = a.clone();
+        for (node, weights) in &b.nodes {
+            let out = out.get_mut(*node, weights.len());
+            for (i, weight) in out.iter_mut().enumerate() {
+                let other = weights.get(i).copied().unwrap_or(0.0);
+                *weight += (other - *weight) * t;
+            }
+        }
+        for (node, weights) in &mut out.nodes {
+            if b.get(*node).is_none() {
+                weights.iter_mut().for_each(|weight| *weight *= 1.0 - t);
+            }
+        }
+    }
+}
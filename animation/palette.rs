@@ -0,0 +1,163 @@
+// skinning matrices for a whole pose in one pass over the joints
+// joints are visited parents first so every global transform is one combine away from its
+// parent's, instead of walking up to the root for each joint like Pose::get_global_tranform
+
+use super::pose::Pose;
+use super::skeleton::Skeleton;
+
+use math::dual_quat::DualQuat;
+use math::mat4::Mat4;
+use math::transform::Transform;
+
+/// joint indices ordered so every parent comes before its children
+/// fails when a parent is out of range or the parents form a cycle
+pub fn joint_order(parents: &[i32]) -> Result<Vec<usize>, String> {
+    let len = parents.len();
+    let mut children: Vec<Vec<usize>> = vec![Vec::new(); len];
+    let mut order = Vec::with_capacity(len);
+
+    for (joint, parent) in parents.iter().enumerate() {
+        match *parent {
+            p if p < 0 => order.push(joint),
+            p if (p as usize) < len => children[p as usize].push(joint),
+            p => return Err(format!("joint {} has parent {} out of range", joint, p)),
+        }
+    }
+
+    // breadth first from the roots, anything never reached sits on a cycle
+    let mut next = 0;
+    while next < order.len() {
+        let joint = order[next];
+        order.extend_from_slice(&children[joint]);
+        next += 1;
+    }
+
+    if order.len() != len {
+        let joint = (0..len).find(|joint| !order.contains(joint)).unwrap();
+        return Err(format!("joint {} is part of a parent cycle", joint));
+    }
+
+    Ok(order)
+}
+
+/// true when every parent index is lower than its child, such poses can be evaluated in index order
+pub fn is_parent_first(parents: &[i32]) -> bool {
+    parents
+        .iter()
+        .enumerate()
+        .all(|(joint, parent)| *parent < joint as i32)
+}
+
+/// global transform of `joint` from its local transform and the already evaluated globals
+fn global_transform(
+    joint: usize,
+    parents: &[i32],
+    joints: &[Transform],
+    globals: &[Transform],
+) -> Transform {
+    match parents[joint] {
+        p if p >= 0 => globals[p as usize].combine(&joints[joint]),
+        _ => joints[joint],
+    }
+}
+
+/// global transforms of every joint of `pose`, written into `out`
+/// fails when the parents of the pose do not form a tree
+pub fn global_transforms(pose: &Pose, out: &mut Vec<Transform>) -> Result<(), String> {
+    let len = pose.joints.len();
+    out.resize(len, Transform::DEFAULT);
+
+    if is_parent_first(&pose.parents) {
+        for joint in 0..len {
+            out[joint] = global_transform(joint, &pose.parents, &pose.joints, out);
+        }
+    } else {
+        for joint in joint_order(&pose.parents)? {
+            out[joint] = global_transform(joint, &pose.parents, &pose.joints, out);
+        }
+    }
+
+    Ok(())
+}
+
+/// skinning matrix of a joint from its global transform
+/// joints without an inverse bind matrix get the identity, like they do not skin anything
+pub fn skinning_matrix(global: &Transform, inverse_bind_pose: &Option<Mat4>) -> Mat4 {
+    match inverse_bind_pose {
+        Some(inverse_bind_pose) => global.to_mat() * *inverse_bind_pose,
+        None => Mat4::IDENTITY,
+    }
+}
+
+/// reusable buffers for the global transforms and skinning matrices of one skeleton
+#[derive(Clone, Debug)]
+pub struct MatrixPalette {
+    order: Vec<usize>,
+    parents: Vec<i32>,
+    inverse_bind_pose: Vec<Option<Mat4>>,
+    globals: Vec<Transform>,
+    matrices: Vec<Mat4>,
+    dual_quats: Vec<DualQuat>,
+}
+
+impl MatrixPalette {
+    pub fn new(skeleton: &Skeleton) -> Result<Self, String> {
+        let parents = skeleton.rest_pose.parents.clone();
+        let len = parents.len();
+        if skeleton.inverse_bind_pose.len() != len {
+            return Err(format!(
+                "{} inverse bind matrices for {} joints",
+                skeleton.inverse_bind_pose.len(),
+                len
+            ));
+        }
+
+        Ok(Self {
+            order: joint_order(&parents)?,
+            parents,
+            inverse_bind_pose: skeleton.inverse_bind_pose.clone(),
+            globals: vec![Transform::DEFAULT; len],
+            matrices: vec![Mat4::IDENTITY; len],
+            dual_quats: vec![DualQuat::IDENTITY; len],
+        })
+    }
+
+    /// evaluate the global transforms and skinning matrices of `pose`
+    pub fn update(&mut self, pose: &Pose) -> &[Mat4] {
+        for &joint in &self.order {
+            self.globals[joint] =
+                global_transform(joint, &self.parents, &pose.joints, &self.globals);
+
+            self.matrices[joint] =
+                skinning_matrix(&self.globals[joint], &self.inverse_bind_pose[joint]);
+        }
+
+        &self.matrices
+    }
+
+    /// evaluate `pose` like `update` and convert the skinning matrices to dual quaternions
+    /// the rigid part of each matrix is kept, scaling left over after the inverse bind matrix is lost
+    pub fn update_dual_quats(&mut self, pose: &Pose) -> &[DualQuat] {
+        self.update(pose);
+        for (dual_quat, matrix) in self.dual_quats.iter_mut().zip(&self.matrices) {
+            *dual_quat = DualQuat::from_mat(matrix);
+        }
+
+        &self.dual_quats
+    }
+
+    /// skinning matrices of the last update
+    pub fn get_matrices(&self) -> &[Mat4] {
+        &self.matrices
+    }
+
+    /// skinning dual quaternions of the last update_dual_quats
+    pub fn get_dual_quats(&self) -> &[DualQuat] {
+        &self.dual_quats
+    }
+
+    /// global transforms of the last update
+    pub fn get_globals(&self) -> &[Transform] {
+        &self.globals
+    }
+}
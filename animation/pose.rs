@@ -1,4 +1,7 @@
-use math::{mat4::Mat4, transform::Transform};
+use super::mask::BoneMask;
+use super::palette::global_transforms;
+
+use math::{quaternion::Quat, transform::Transform, vec3::Vec3};
 
 #[derive(Clone)]
 pub struct Pose {
@@ -31,15 +34,58 @@ impl Pose {
         self.joints.resize(new_len, Transform::DEFAULT);
     }
 
-    pub fn get_matrix_palette(&mut self, out: &mut Vec<Mat4>) {
-        let len = self.joints.len();
-        if out.len() != len {
-            out.resize(len, Mat4::IDENTITY);
+    /// linear blend between two poses, t = 0 gives a and t = 1 gives b
+    /// all three poses are expected to share the same skeleton
+    pub fn blend(out_pose: &mut Pose, a: &Pose, b: &Pose, t: f32) {
+        for (i, joint) in out_pose.joints.iter_mut().enumerate() {
+            *joint = a.joints[i].lerp(&b.joints[i], t);
         }
+    }
 
-        for i in 0..len {
-            let t = &mut self.get_global_tranform(i);
-            out[i] = t.to_mat();
+    /// blend between two poses where each joint is weighted by `t` times its mask weight
+    pub fn blend_masked(out_pose: &mut Pose, a: &Pose, b: &Pose, t: f32, mask: &BoneMask) {
+        for (i, joint) in out_pose.joints.iter_mut().enumerate() {
+            *joint = a.joints[i].lerp(&b.joints[i], t * mask.get_weight(i));
+        }
+    }
+
+    /// layer the difference between `additive` and `reference` on top of `base`
+    /// weight scales how much of the difference gets applied
+    pub fn add(out_pose: &mut Pose, base: &Pose, additive: &Pose, reference: &Pose, weight: f32) {
+        for (i, joint) in out_pose.joints.iter_mut().enumerate() {
+            *joint = add_joint(
+                &base.joints[i],
+                &additive.joints[i],
+                &reference.joints[i],
+                weight,
+            );
+        }
+    }
+
+    /// same as `add` with the weight of each joint scaled by its mask weight
+    pub fn add_masked(
+        out_pose: &mut Pose,
+        base: &Pose,
+        additive: &Pose,
+        reference: &Pose,
+        weight: f32,
+        mask: &BoneMask,
+    ) {
+        for (i, joint) in out_pose.joints.iter_mut().enumerate() {
+            *joint = add_joint(
+                &base.joints[i],
+                &additive.joints[i],
+                &reference.joints[i],
+                weight * mask.get_weight(i),
+            );
+        }
+    }
+
+    /// global transforms of every joint in a single pass, `out` is reused between calls
+    /// panics when the parents do not form a tree, see `palette::joint_order`
+    pub fn get_global_transforms(&self, out: &mut Vec<Transform>) {
+        if let Err(err) = global_transforms(self, out) {
+            panic!("invalid pose: {}", err);
         }
     }
 }
@@ -82,3 +128,31 @@ impl PartialEq for Pose {
         return !self.eq(other);
     }
 }
+
+fn add_joint(
+    base: &Transform,
+    additive: &Transform,
+    reference: &Transform,
+    weight: f32,
+) -> Transform {
+    let translation = additive.translation - reference.translation;
+
+    let mut orientation = reference.orientation.inverse() * additive.orientation;
+    if orientation.s < 0.0 {
+        orientation = -orientation;
+    }
+    let orientation = Quat::ZERO.nlerp(orientation, weight);
+
+    // scale differences are ratios so an unchanged joint has a difference of one
+    let scaling = Vec3::new(
+        additive.scaling.x / reference.scaling.x,
+        additive.scaling.y / reference.scaling.y,
+        additive.scaling.z / reference.scaling.z,
+    );
+
+    Transform {
+        translation: base.translation + translation * weight,
+        orientation: (base.orientation * orientation).unit(),
+        scaling: base.scaling * Vec3::ONE.mix(scaling, weight),
+    }
+}
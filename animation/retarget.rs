@@ -0,0 +1,329 @@
+// retargeting, playing clips made for one rig on another with different joint names and proportions
+// joints of both rigs are matched through shared names, rotations are transferred relative to the
+// rest pose of each rig and the translation of the root is scaled by the ratio of the leg lengths
+//
+// both rigs are expected to face the same way in the space of the scene once their `space` is applied
+
+use super::clip::Clip;
+use super::curves::Interpolation;
+use super::frame::Frame;
+use super::pose::Pose;
+use super::skeleton::Skeleton;
+
+use math::transform::Transform;
+use math::vec3::Vec3;
+
+/// shared names of the hip, knee and ankle used to measure leg length
+pub const LEG: [&str; 3] = ["LeftUpLeg", "LeftLeg", "LeftFoot"];
+
+/// joint names of a rig mapped to the shared names both rigs are matched with
+/// joints missing from the map use their own name as shared name
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NameMap {
+    /// (joint name, shared name)
+    names: Vec<(String, String)>,
+}
+
+impl NameMap {
+    pub fn new() -> Self {
+        Self { names: Vec::new() }
+    }
+
+    /// one `joint = shared` pair per line, empty lines and lines starting with # are skipped
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut map = Self::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            match line.split_once('=') {
+                Some((joint, shared)) if !joint.trim().is_empty() && !shared.trim().is_empty() => {
+                    map.add(joint.trim(), shared.trim())
+                }
+                _ => return Err(format!("line {}: expected `joint = shared`", number + 1)),
+            }
+        }
+
+        Ok(map)
+    }
+
+    /// map a joint to a shared name, replaces any previous mapping of that joint
+    pub fn add(&mut self, joint: &str, shared: &str) {
+        match self.names.iter_mut().find(|(name, _)| name == joint) {
+            Some(pair) => pair.1 = String::from(shared),
+            None => self.names.push((String::from(joint), String::from(shared))),
+        }
+    }
+
+    pub fn get_shared<'a>(&'a self, joint: &'a str) -> &'a str {
+        self.names
+            .iter()
+            .find(|(name, _)| name == joint)
+            .map_or(joint, |(_, shared)| shared.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+/// a skeleton with what is needed to retarget to or from it
+#[derive(Clone)]
+pub struct Rig {
+    pub skeleton: Skeleton,
+    pub names: NameMap,
+    /// transform of the nodes above the root joints, like GPUAnimatedModel root transform
+    pub space: Transform,
+}
+
+impl Rig {
+    pub fn new(skeleton: Skeleton, names: NameMap) -> Self {
+        Self {
+            skeleton,
+            names,
+            space: Transform::DEFAULT,
+        }
+    }
+
+    pub fn find_shared(&self, shared: &str) -> Option<usize> {
+        self.skeleton
+            .joint_names
+            .iter()
+            .position(|name| self.names.get_shared(name) == shared)
+    }
+
+    /// rest pose transform of every joint in the space of the scene
+    fn rest_globals(&self) -> Vec<Transform> {
+        let mut globals = Vec::new();
+        self.skeleton.rest_pose.get_global_transforms(&mut globals);
+        for global in &mut globals {
+            *global = self.space.combine(global);
+        }
+
+        globals
+    }
+
+    /// length of the leg in the rest pose, in the space of the scene
+    /// None when the rig has no joints for the shared `LEG` names
+    pub fn leg_length(&self) -> Option<f32> {
+        let globals = self.rest_globals();
+        let position = |shared: &str| Some(globals[self.find_shared(shared)?].translation);
+
+        let [hip, knee, ankle] = LEG;
+        let (hip, knee, ankle) = (position(hip)?, position(knee)?, position(ankle)?);
+
+        Some((knee - hip).len() + (ankle - knee).len())
+    }
+}
+
+#[derive(Clone)]
+pub struct Retarget {
+    /// source joint driving each target joint, parallel to the target joints
+    sources: Vec<Option<usize>>,
+    /// target joints whose translation follows the source, mapped joints without a mapped parent
+    roots: Vec<bool>,
+    /// target joints sorted so parents come before their children
+    order: Vec<usize>,
+    source_rest: Pose,
+    target_rest: Pose,
+    source_rest_globals: Vec<Transform>,
+    target_rest_globals: Vec<Transform>,
+    source_space: Transform,
+    target_space: Transform,
+    /// root translation is scaled by this, target leg length over source leg length
+    pub translation_scale: f32,
+    /// global transforms of the pose being retargeted and of the result, reused every call
+    source_globals: Vec<Transform>,
+    target_globals: Vec<Transform>,
+}
+
+impl Retarget {
+    pub fn new(source: &Rig, target: &Rig) -> Result<Self, String> {
+        let target_parents = &target.skeleton.rest_pose.parents;
+
+        let sources = target
+            .skeleton
+            .joint_names
+            .iter()
+            .map(|name| source.find_shared(target.names.get_shared(name)))
+            .collect::<Vec<Option<usize>>>();
+        if sources.iter().all(|source| source.is_none()) {
+            return Err(String::from("no joint of the target matches the source"));
+        }
+
+        let order = parents_first(target_parents);
+
+        let mut roots = vec![false; sources.len()];
+        for joint in 0..sources.len() {
+            // walk up until a mapped ancestor or the root of the skeleton
+            let mut parent = target_parents[joint];
+            while parent >= 0 && sources[parent as usize].is_none() {
+                parent = target_parents[parent as usize];
+            }
+            roots[joint] = sources[joint].is_some() && parent < 0;
+        }
+
+        let translation_scale = match (source.leg_length(), target.leg_length()) {
+            (Some(source), Some(target)) if source > 0.0 => target / source,
+            _ => 1.0,
+        };
+
+        Ok(Self {
+            sources,
+            roots,
+            order,
+            source_rest: source.skeleton.rest_pose.clone(),
+            target_rest: target.skeleton.rest_pose.clone(),
+            source_rest_globals: source.rest_globals(),
+            target_rest_globals: target.rest_globals(),
+            source_space: source.space,
+            target_space: target.space,
+            translation_scale,
+            source_globals: Vec::new(),
+            target_globals: Vec::new(),
+        })
+    }
+
+    /// source joint driving a target joint
+    pub fn get_source(&self, target_joint: usize) -> Option<usize> {
+        self.sources.get(target_joint).copied().flatten()
+    }
+
+    /// pose the target like `source_pose`, joints without a source keep their rest pose
+    pub fn retarget_pose(&mut self, source_pose: &Pose, out_pose: &mut Pose) {
+        out_pose.parents.clone_from(&self.target_rest.parents);
+        out_pose.joints.clone_from(&self.target_rest.joints);
+
+        source_pose.get_global_transforms(&mut self.source_globals);
+        let globals = &mut self.target_globals;
+        globals.clone_from(&self.target_rest_globals);
+        for &joint in &self.order {
+            let parent = match self.target_rest.parents[joint] {
+                p if p >= 0 => globals[p as usize],
+                _ => self.target_space,
+            };
+
+            let local = &mut out_pose.joints[joint];
+            if let Some(source) = self.sources[joint] {
+                let rest = &self.source_rest_globals[source];
+                let current = self.source_space.combine(&self.source_globals[source]);
+
+                // rotation of the source joint away from its rest pose, in the space of the scene
+                let delta = current.orientation * rest.orientation.inverse();
+                let orientation = delta * self.target_rest_globals[joint].orientation;
+                local.orientation = (parent.orientation.inverse() * orientation).unit();
+
+                if self.roots[joint] {
+                    let offset = (current.translation - rest.translation) * self.translation_scale;
+                    let position = self.target_rest_globals[joint].translation + offset;
+                    local.translation = transform_point(&parent.inverse(), &position);
+                }
+            }
+
+            globals[joint] = parent.combine(local);
+        }
+    }
+
+    /// bake a source clip into a clip for the target, sampled `sample_rate` times per second
+    /// only joints with a source get tracks, rotations everywhere and translations on the roots
+    /// fails when a track of the source clip can not be sampled
+    pub fn retarget_clip(&mut self, clip: &Clip, sample_rate: f32) -> Result<Clip, String> {
+        let mut source_clip = clip.clone();
+        // sampling a looping clip at its end time gives the start pose again
+        source_clip.set_looping(false);
+
+        let start = clip.get_start_time();
+        let duration = clip.get_duration();
+        let samples = ((duration * sample_rate).ceil() as usize).max(1);
+
+        let mut retargeted = Clip::new();
+        retargeted.name = clip.name.clone();
+        retargeted.set_loop_mode(clip.get_loop_mode());
+        retargeted.set_speed(clip.get_speed());
+        for event in clip.get_events() {
+            retargeted.add_event(&event.name, event.time);
+        }
+
+        for joint in 0..self.sources.len() {
+            if self.sources[joint].is_some() {
+                let track = retargeted.get_transform_track(joint as u32);
+                track.position.interpolation = Interpolation::Linear;
+                track.rotation.interpolation = Interpolation::Linear;
+            }
+        }
+
+        let mut source_pose = self.source_rest.clone();
+        let mut target_pose = self.target_rest.clone();
+        let mut errors = Vec::new();
+        for i in 0..=samples {
+            let time = start + duration * i as f32 / samples as f32;
+            source_clip.sample(&mut source_pose, time, &mut errors);
+            if let Some(error) = errors.first() {
+                return Err(format!("unable to sample {}: {}", clip.name, error));
+            }
+            self.retarget_pose(&source_pose, &mut target_pose);
+
+            for track in &mut retargeted.tracks {
+                let joint = &target_pose.joints[track.id as usize];
+
+                let mut rotation = Frame::<4>::new();
+                rotation.time = time;
+                rotation.m_value = joint.orientation.to_array();
+                // keep neighbouring keys in the same hemisphere so they interpolate the short way
+                if let Some(previous) = track.rotation.frames.last() {
+                    let dot = (0..4)
+                        .map(|k| previous.m_value[k] * rotation.m_value[k])
+                        .sum::<f32>();
+                    if dot < 0.0 {
+                        rotation.m_value = (-joint.orientation).to_array();
+                    }
+                }
+                track.rotation.frames.push(rotation);
+
+                if self.roots[track.id as usize] {
+                    let mut position = Frame::<3>::new();
+                    position.time = time;
+                    position.m_value = joint.translation.to_array();
+                    track.position.frames.push(position);
+                }
+            }
+        }
+        retargeted.re_calculate_duration();
+
+        Ok(retargeted)
+    }
+}
+
+/// joint indices ordered so every parent comes before its children
+fn parents_first(parents: &[i32]) -> Vec<usize> {
+    let depth = |joint: usize| {
+        let mut depth = 0;
+        let mut parent = parents[joint];
+        while parent >= 0 {
+            depth += 1;
+            parent = parents[parent as usize];
+        }
+        depth
+    };
+
+    let mut order = (0..parents.len()).collect::<Vec<usize>>();
+    order.sort_by_key(|joint| depth(*joint));
+
+    order
+}
+
+fn transform_point(transform: &Transform, point: &Vec3) -> Vec3 {
+    let local = Transform {
+        translation: *point,
+        ..Transform::DEFAULT
+    };
+
+    transform.combine(&local).translation
+}
@@ -0,0 +1,136 @@
+// root motion, the character is moved by the animation instead of the root joint wandering off
+// the horizontal translation and yaw of the root joint are taken out of the sampled pose
+// and handed back as a per frame delta
+//
+// the delta is in the space of the root joint's parent, whose up axis is expected to be +y
+// applying it is a matter of `placement = placement.combine(&delta)`
+
+use super::clip::Clip;
+use super::curves::LoopMode;
+use super::pose::Pose;
+
+use math::quaternion::{quat, Quat};
+use math::transform::Transform;
+use math::vec3::{vec3, Vec3};
+
+/// horizontal translation and rotation around +y of a transform
+pub fn extract_root(transform: &Transform) -> Transform {
+    // twist part of a swing twist decomposition around the up axis
+    let q = transform.orientation;
+    let twist = quat(0.0, q.y, 0.0, q.s);
+    let yaw = if twist.norm() <= 1e-6 {
+        Quat::ZERO
+    } else {
+        twist.unit()
+    };
+
+    Transform {
+        translation: vec3(transform.translation.x, 0.0, transform.translation.z),
+        orientation: yaw,
+        scaling: Vec3::ONE,
+    }
+}
+
+/// how many times a looping clip wrapped around going from one unclamped time to another
+/// negative when playing backwards, ping pong clips never jump so they never wrap
+pub fn count_loops(clip: &Clip, from: f32, to: f32) -> i32 {
+    let duration = clip.get_duration();
+    if clip.get_loop_mode() != LoopMode::Loop || duration <= 0.0 {
+        return 0;
+    }
+
+    let start = clip.get_start_time();
+    let from = ((from - start) / duration).floor() as i32;
+    let to = ((to - start) / duration).floor() as i32;
+
+    to - from
+}
+
+/// per clip data needed to pull the motion of `joint` out of sampled poses
+#[derive(Clone, Debug)]
+pub struct RootMotion {
+    pub joint: usize,
+    /// root placement of each clip at its start time, stripped poses keep this placement
+    starts: Vec<Transform>,
+    /// motion of each clip from its start to its end, added every time the clip loops
+    loops: Vec<Transform>,
+}
+
+impl RootMotion {
+    pub fn new(joint: usize) -> Self {
+        Self {
+            joint,
+            starts: Vec::new(),
+            loops: Vec::new(),
+        }
+    }
+
+    /// clips have to be added in the same order as in the controller
+    pub fn add_clip(&mut self, rest_pose: &Pose, clip: &Clip) {
+        // sampling a looping clip at its end time gives the start pose again
+        let mut clip = clip.clone();
+        clip.set_looping(false);
+        let (start_time, end_time) = (clip.get_start_time(), clip.get_end_time());
+
+        // broken tracks are reported by the controller when it plays the clip
+        let mut sample = |time: f32| {
+            let mut pose = rest_pose.clone();
+            clip.sample(&mut pose, time, &mut Vec::new());
+            extract_root(&pose.joints[self.joint])
+        };
+        let start = sample(start_time);
+        let end = sample(end_time);
+
+        self.loops.push(end.combine(&start.inverse()));
+        self.starts.push(start);
+    }
+
+    /// motion of a clip from its start to its end
+    pub fn get_clip_motion(&self, clip_index: usize) -> Transform {
+        self.loops[clip_index]
+    }
+
+    /// take the root motion out of a pose sampled from a clip
+    /// returns the motion of the clip at the sampled time relative to its start
+    pub fn remove(&self, clip_index: usize, pose: &mut Pose) -> Transform {
+        let joint = &mut pose.joints[self.joint];
+        let current = extract_root(joint);
+        let start = &self.starts[clip_index];
+
+        *joint = start.combine(&current.inverse().combine(joint));
+
+        current.combine(&start.inverse())
+    }
+
+    /// strip a pose sampled from a clip and return the motion since the previous sample
+    /// `previous` holds the motion of the previous sample, None on the first frame of a clip
+    /// `loops` comes from `count_loops` for the same time step
+    pub fn extract(
+        &self,
+        clip_index: usize,
+        pose: &mut Pose,
+        previous: &mut Option<Transform>,
+        loops: i32,
+    ) -> Transform {
+        let motion = self.remove(clip_index, pose);
+
+        let delta = match previous {
+            Some(previous) => {
+                let full_loop = match loops {
+                    l if l < 0 => self.loops[clip_index].inverse(),
+                    _ => self.loops[clip_index],
+                };
+
+                let mut delta = previous.inverse();
+                for _ in 0..loops.abs() {
+                    delta = delta.combine(&full_loop);
+                }
+                delta.combine(&motion)
+            }
+            None => Transform::DEFAULT,
+        };
+        *previous = Some(motion);
+
+        delta
+    }
+}
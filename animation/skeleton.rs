@@ -18,4 +18,8 @@ impl Skeleton {
             joint_names: Vec::new(),
         }
     }
+
+    pub fn get_joint_index(&self, name: &str) -> Option<usize> {
+        self.joint_names.iter().position(|joint| joint == name)
+    }
 }
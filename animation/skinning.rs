@@ -0,0 +1,146 @@
+// cpu side linear blend and dual quaternion skinning
+// mirrors the skinning done in shader.vert so it can be used as a reference or a fallback
+
+use math::dual_quat::DualQuat;
+use math::mat4::{inverse, transpose, Mat4};
+use math::vec3::Vec3;
+
+pub const MAX_BONE_INFLUENCE: usize = 4;
+
+/// how the joints influencing a vertex are blended
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SkinningMethod {
+    /// weighted sum of the skinning matrices, cheap but joints twisting far lose volume
+    LinearBlend,
+    /// weighted sum of rigid dual quaternions, keeps the volume but drops joint scaling
+    DualQuat,
+}
+
+/// blend the palette matrices influencing a vertex by their weights
+/// returns None when no valid joint influences the vertex
+pub fn skin_matrix(
+    palette: &[Mat4],
+    joints: &[i32; MAX_BONE_INFLUENCE],
+    weights: &[f32; MAX_BONE_INFLUENCE],
+) -> Option<Mat4> {
+    let mut result = Mat4::from(&[[0.0; 4]; 4]);
+    let mut influenced = false;
+
+    for (joint, weight) in joints.iter().zip(weights.iter()) {
+        if *weight == 0.0 || *joint < 0 {
+            continue;
+        }
+        if let Some(matrix) = palette.get(*joint as usize) {
+            result = result + *matrix * *weight;
+            influenced = true;
+        }
+    }
+
+    if influenced {
+        Some(result)
+    } else {
+        None
+    }
+}
+
+/// skin a vertex position
+/// vertices without influences are left untouched
+pub fn skin_position(
+    palette: &[Mat4],
+    position: &[f32; 3],
+    joints: &[i32; MAX_BONE_INFLUENCE],
+    weights: &[f32; MAX_BONE_INFLUENCE],
+) -> [f32; 3] {
+    match skin_matrix(palette, joints, weights) {
+        Some(skin) => skin.transform_point(&Vec3::from(position)).to_array(),
+        None => *position,
+    }
+}
+
+/// skin a vertex normal using the inverse transpose of the skin matrix
+/// the result is normalized
+pub fn skin_normal(
+    palette: &[Mat4],
+    normal: &[f32; 3],
+    joints: &[i32; MAX_BONE_INFLUENCE],
+    weights: &[f32; MAX_BONE_INFLUENCE],
+) -> [f32; 3] {
+    match skin_matrix(palette, joints, weights) {
+        Some(skin) => {
+            let normal_mat = transpose(&inverse(&skin));
+            let result = normal_mat.transform_vector(&Vec3::from(normal));
+
+            if result.len() > 0.0 {
+                result.unit().to_array()
+            } else {
+                *normal
+            }
+        }
+        None => *normal,
+    }
+}
+
+/// blend the palette dual quaternions influencing a vertex by their weights
+/// quaternions are flipped into the hemisphere of the first influence so the blend takes the short way
+/// returns None when no valid joint influences the vertex
+pub fn skin_dual_quat(
+    palette: &[DualQuat],
+    joints: &[i32; MAX_BONE_INFLUENCE],
+    weights: &[f32; MAX_BONE_INFLUENCE],
+) -> Option<DualQuat> {
+    let mut result: Option<DualQuat> = None;
+    let mut first = DualQuat::IDENTITY;
+
+    for (joint, weight) in joints.iter().zip(weights.iter()) {
+        if *weight == 0.0 || *joint < 0 {
+            continue;
+        }
+        if let Some(dual_quat) = palette.get(*joint as usize) {
+            result = Some(match result {
+                Some(result) => {
+                    let sign = if first.dot(dual_quat) < 0.0 {
+                        -1.0
+                    } else {
+                        1.0
+                    };
+                    result + *dual_quat * (*weight * sign)
+                }
+                None => {
+                    first = *dual_quat;
+                    *dual_quat * *weight
+                }
+            });
+        }
+    }
+
+    // weights cancelling out leave nothing to normalize
+    result
+        .filter(|result| result.real.norm() > 0.0)
+        .map(|result| result.unit())
+}
+
+/// dual quaternion version of `skin_position`
+pub fn skin_position_dual_quat(
+    palette: &[DualQuat],
+    position: &[f32; 3],
+    joints: &[i32; MAX_BONE_INFLUENCE],
+    weights: &[f32; MAX_BONE_INFLUENCE],
+) -> [f32; 3] {
+    match skin_dual_quat(palette, joints, weights) {
+        Some(skin) => skin.transform_point(&Vec3::from(position)).to_array(),
+        None => *position,
+    }
+}
+
+/// dual quaternion version of `skin_normal`, the transform is rigid so the normal is only rotated
+pub fn skin_normal_dual_quat(
+    palette: &[DualQuat],
+    normal: &[f32; 3],
+    joints: &[i32; MAX_BONE_INFLUENCE],
+    weights: &[f32; MAX_BONE_INFLUENCE],
+) -> [f32; 3] {
+    match skin_dual_quat(palette, joints, weights) {
+        Some(skin) => skin.transform_vector(&Vec3::from(normal)).to_array(),
+        None => *normal,
+    }
+}
@@ -0,0 +1,438 @@
+// data driven state machine on top of the animation controller
+// the definition is plain data so designers can write it in json, for example
+//
+// {
+//     "parameters": { "speed": { "float": 0.0 }, "jump": { "trigger": false } },
+//     "states": [
+//         { "name": "idle", "clip": "Idle" },
+//         { "name": "walk", "clip": "Walk", "speed": 1.2 },
+//         { "name": "jump", "clip": "Jump", "looping": false }
+//     ],
+//     "transitions": [
+//         { "from": "idle", "to": "walk", "duration": 0.2,
+//           "conditions": [{ "op": "greater", "parameter": "speed", "value": 0.1 }] },
+//         { "from": "walk", "to": "idle", "duration": 0.2,
+//           "conditions": [{ "op": "less", "parameter": "speed", "value": 0.1 }] },
+//         { "to": "jump", "duration": 0.1, "conditions": [{ "op": "triggered", "parameter": "jump" }] },
+//         { "from": "jump", "to": "idle", "exit_time": 1.0, "duration": 0.2 }
+//     ]
+// }
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::clip::Clip;
+use super::controller::{AnimationController, AnimationError, AnimationEvent};
+use super::skeleton::Skeleton;
+use math::dual_quat::DualQuat;
+use math::mat4::Mat4;
+use math::transform::Transform;
+
+/// a parameter value, triggers stay set until a transition using them fires
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Parameter {
+    Float(f32),
+    Bool(bool),
+    Trigger(bool),
+}
+
+/// a test on a parameter, every condition of a transition has to pass for it to fire
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Condition {
+    Greater { parameter: String, value: f32 },
+    Less { parameter: String, value: f32 },
+    True { parameter: String },
+    False { parameter: String },
+    Triggered { parameter: String },
+}
+
+impl Condition {
+    pub fn get_parameter(&self) -> &str {
+        match self {
+            Condition::Greater { parameter, .. }
+            | Condition::Less { parameter, .. }
+            | Condition::True { parameter }
+            | Condition::False { parameter }
+            | Condition::Triggered { parameter } => parameter,
+        }
+    }
+
+    fn evaluate(&self, parameters: &HashMap<String, Parameter>) -> bool {
+        let value = parameters.get(self.get_parameter());
+
+        match (self, value) {
+            (Condition::Greater { value: limit, .. }, Some(Parameter::Float(value))) => {
+                value > limit
+            }
+            (Condition::Less { value: limit, .. }, Some(Parameter::Float(value))) => value < limit,
+            (Condition::True { .. }, Some(Parameter::Bool(value))) => *value,
+            (Condition::False { .. }, Some(Parameter::Bool(value))) => !*value,
+            (Condition::Triggered { .. }, Some(Parameter::Trigger(value))) => *value,
+            _ => false,
+        }
+    }
+
+    /// whether the parameter has the type this condition expects
+    fn accepts(&self, parameter: &Parameter) -> bool {
+        matches!(
+            (self, parameter),
+            (Condition::Greater { .. }, Parameter::Float(_))
+                | (Condition::Less { .. }, Parameter::Float(_))
+                | (Condition::True { .. }, Parameter::Bool(_))
+                | (Condition::False { .. }, Parameter::Bool(_))
+                | (Condition::Triggered { .. }, Parameter::Trigger(_))
+        )
+    }
+}
+
+fn default_speed() -> f32 {
+    1.0
+}
+
+fn default_looping() -> bool {
+    true
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StateDesc {
+    pub name: String,
+    /// name of the clip played while in this state
+    pub clip: String,
+    #[serde(default = "default_speed")]
+    pub speed: f32,
+    #[serde(default = "default_looping")]
+    pub looping: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TransitionDesc {
+    /// state the transition leaves from, None allows it from any other state
+    #[serde(default)]
+    pub from: Option<String>,
+    pub to: String,
+    #[serde(default)]
+    pub conditions: Vec<Condition>,
+    /// normalized time of the source state the transition waits for
+    /// loops count, so 1.5 on a looping state waits for one and a half loops
+    #[serde(default)]
+    pub exit_time: Option<f32>,
+    /// crossfade duration in seconds, 0 switches instantly
+    #[serde(default)]
+    pub duration: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StateMachineDesc {
+    #[serde(default)]
+    pub parameters: HashMap<String, Parameter>,
+    pub states: Vec<StateDesc>,
+    #[serde(default)]
+    pub transitions: Vec<TransitionDesc>,
+    /// state entered on creation, defaults to the first state
+    #[serde(default)]
+    pub initial: Option<String>,
+}
+
+impl StateMachineDesc {
+    pub fn from_json(src: &str) -> Result<Self, String> {
+        serde_json::from_str(src).map_err(|err| format!("invalid state machine: {}", err))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let src = std::fs::read_to_string(path)
+            .map_err(|err| format!("unable to read {}: {}", path.to_string_lossy(), err))?;
+        Self::from_json(&src)
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|err| err.to_string())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum StateEvent {
+    Entered(String),
+    Exited(String),
+}
+
+#[derive(Clone)]
+struct Transition {
+    from: Option<usize>,
+    to: usize,
+    conditions: Vec<Condition>,
+    exit_time: Option<f32>,
+    duration: f32,
+}
+
+#[derive(Clone)]
+pub struct AnimationStateMachine {
+    controller: AnimationController,
+    state_names: Vec<String>,
+    /// clip index in the controller for every state, parallel to state_names
+    state_clips: Vec<usize>,
+    transitions: Vec<Transition>,
+    parameters: HashMap<String, Parameter>,
+    current_state: usize,
+    /// clip time played since the current state was entered
+    state_time: f32,
+    events: Vec<StateEvent>,
+}
+
+impl AnimationStateMachine {
+    /// build a state machine, clips are looked up by name
+    /// every state gets its own copy of its clip so speed and looping can differ per state
+    pub fn new(
+        desc: &StateMachineDesc,
+        skeleton: Skeleton,
+        clips: &[Clip],
+    ) -> Result<Self, String> {
+        if desc.states.is_empty() {
+            return Err(String::from("state machine has no states"));
+        }
+
+        let find_state = |name: &str| {
+            desc.states
+                .iter()
+                .position(|state| state.name == name)
+                .ok_or(format!("unknown state {}", name))
+        };
+
+        let mut controller = AnimationController::try_new(skeleton)?;
+        let mut state_clips = Vec::new();
+        for state in &desc.states {
+            let mut clip = clips
+                .iter()
+                .find(|clip| clip.name == state.clip)
+                .ok_or(format!(
+                    "state {} uses unknown clip {}",
+                    state.name, state.clip
+                ))?
+                .clone();
+            clip.set_looping(state.looping);
+            clip.set_speed(state.speed);
+
+            state_clips.push(controller.add_clip(clip));
+        }
+
+        let mut transitions = Vec::new();
+        for transition in &desc.transitions {
+            for condition in &transition.conditions {
+                match desc.parameters.get(condition.get_parameter()) {
+                    Some(parameter) if condition.accepts(parameter) => {}
+                    Some(_) => {
+                        return Err(format!(
+                            "parameter {} has the wrong type for {:?}",
+                            condition.get_parameter(),
+                            condition
+                        ))
+                    }
+                    None => return Err(format!("unknown parameter {}", condition.get_parameter())),
+                }
+            }
+
+            let from = match &transition.from {
+                Some(from) => Some(find_state(from)?),
+                None => None,
+            };
+
+            transitions.push(Transition {
+                from,
+                to: find_state(&transition.to)?,
+                conditions: transition.conditions.clone(),
+                exit_time: transition.exit_time,
+                duration: transition.duration,
+            });
+        }
+
+        let current_state = match &desc.initial {
+            Some(initial) => find_state(initial)?,
+            None => 0,
+        };
+        controller.play(state_clips[current_state])?;
+
+        let state_names = desc
+            .states
+            .iter()
+            .map(|state| state.name.clone())
+            .collect::<Vec<String>>();
+
+        Ok(Self {
+            controller,
+            events: vec![StateEvent::Entered(state_names[current_state].clone())],
+            state_names,
+            state_clips,
+            transitions,
+            parameters: desc.parameters.clone(),
+            current_state,
+            state_time: 0.0,
+        })
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        if let Some(index) = self.find_transition() {
+            let transition = self.transitions[index].clone();
+
+            // triggers are consumed by the transition that used them
+            for condition in &transition.conditions {
+                if let Condition::Triggered { parameter } = condition {
+                    self.parameters
+                        .insert(parameter.clone(), Parameter::Trigger(false));
+                }
+            }
+
+            self.events.push(StateEvent::Exited(
+                self.state_names[self.current_state].clone(),
+            ));
+            self.events
+                .push(StateEvent::Entered(self.state_names[transition.to].clone()));
+
+            // a state transitioning to itself starts over
+            let clip_index = self.state_clips[transition.to];
+            let fade = if transition.to == self.current_state {
+                self.controller
+                    .crossfade_restart(clip_index, transition.duration)
+            } else {
+                self.controller
+                    .crossfade_to(clip_index, transition.duration)
+            };
+            fade.expect("every state has a clip in the controller");
+            self.current_state = transition.to;
+            self.state_time = 0.0;
+        }
+
+        let clip = self
+            .controller
+            .get_clip(self.state_clips[self.current_state]);
+        // normalized time measures progress, so it counts up in either direction
+        self.state_time += (delta_time * self.controller.get_speed() * clip.get_speed()).abs();
+
+        self.controller.update(delta_time);
+    }
+
+    /// first transition, in definition order, that can fire from the current state
+    fn find_transition(&self) -> Option<usize> {
+        let normalized_time = self.get_normalized_time();
+
+        self.transitions.iter().position(|transition| {
+            let from_current = match transition.from {
+                Some(from) => from == self.current_state,
+                None => transition.to != self.current_state,
+            };
+            let exit_reached = match transition.exit_time {
+                Some(exit_time) => normalized_time >= exit_time,
+                None => true,
+            };
+
+            from_current
+                && exit_reached
+                && transition
+                    .conditions
+                    .iter()
+                    .all(|condition| condition.evaluate(&self.parameters))
+        })
+    }
+
+    /// time spent in the current state relative to its clip duration
+    /// looping states keep counting up, other states stop at 1
+    pub fn get_normalized_time(&self) -> f32 {
+        let clip = self
+            .controller
+            .get_clip(self.state_clips[self.current_state]);
+        let duration = clip.get_duration();
+        if duration <= 0.0 {
+            return 1.0;
+        }
+
+        let normalized = self.state_time / duration;
+        if clip.get_looping() {
+            normalized
+        } else {
+            normalized.min(1.0)
+        }
+    }
+
+    pub fn set_float(&mut self, name: &str, value: f32) -> Result<(), String> {
+        self.set_parameter(name, Parameter::Float(value))
+    }
+
+    pub fn set_bool(&mut self, name: &str, value: bool) -> Result<(), String> {
+        self.set_parameter(name, Parameter::Bool(value))
+    }
+
+    pub fn set_trigger(&mut self, name: &str) -> Result<(), String> {
+        self.set_parameter(name, Parameter::Trigger(true))
+    }
+
+    pub fn reset_trigger(&mut self, name: &str) -> Result<(), String> {
+        self.set_parameter(name, Parameter::Trigger(false))
+    }
+
+    /// fails when the parameter is unknown or holds another type, it is left as it was
+    fn set_parameter(&mut self, name: &str, value: Parameter) -> Result<(), String> {
+        match self.parameters.get_mut(name) {
+            Some(parameter)
+                if std::mem::discriminant(parameter) == std::mem::discriminant(&value) =>
+            {
+                *parameter = value;
+                Ok(())
+            }
+            Some(_) => Err(format!("parameter {} has a different type", name)),
+            None => Err(format!("unknown parameter {}", name)),
+        }
+    }
+
+    pub fn get_parameter(&self, name: &str) -> Option<Parameter> {
+        self.parameters.get(name).copied()
+    }
+
+    pub fn current_state_name(&self) -> &str {
+        &self.state_names[self.current_state]
+    }
+
+    /// take every state event since the last call
+    pub fn drain_events(&mut self) -> Vec<StateEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// see `AnimationController::update_palette`
+    pub fn update_palette(&mut self) -> &[Mat4] {
+        self.controller.update_palette()
+    }
+
+    /// see `AnimationController::update_dual_quat_palette`
+    pub fn update_dual_quat_palette(&mut self) -> &[DualQuat] {
+        self.controller.update_dual_quat_palette()
+    }
+
+    /// see `AnimationController::set_speed`
+    pub fn set_speed(&mut self, speed: f32) {
+        self.controller.set_speed(speed);
+    }
+
+    /// take every clip event crossed since the last call, clip indices are the state indices
+    pub fn drain_clip_events(&mut self) -> Vec<AnimationEvent> {
+        self.controller.drain_events()
+    }
+
+    /// take every track that failed to sample since the last call, see `drain_clip_events`
+    pub fn drain_clip_errors(&mut self) -> Vec<AnimationError> {
+        self.controller.drain_errors()
+    }
+
+    /// see `AnimationController::set_root_motion`
+    pub fn set_root_motion(&mut self, joint: Option<usize>) -> Result<(), String> {
+        self.controller.set_root_motion(joint)
+    }
+
+    pub fn get_root_motion(&self) -> &Transform {
+        self.controller.get_root_motion()
+    }
+
+    pub fn get_controller(&self) -> &AnimationController {
+        &self.controller
+    }
+}
@@ -0,0 +1,102 @@
+mod common;
+
+use animation::clip::{make_additive, Clip};
+use animation::controller::{AnimationController, LayerMode};
+use animation::curves::Interpolation;
+use animation::frame::Frame;
+
+use math::quaternion::Quat;
+use math::vec3::Vec3;
+
+const EPSILON: f32 = 1e-3;
+
+fn yaw(degrees: f32) -> Quat {
+    Quat::create(degrees, Vec3::new(0.0, 1.0, 0.0))
+}
+
+/// one second linear clip of the root going through (time, x, yaw in degrees, scale) keys
+fn clip(keys: &[(f32, f32, f32, f32)]) -> Clip {
+    let mut clip = Clip::new();
+
+    let track = clip.get_transform_track(0);
+    track.position.interpolation = Interpolation::Linear;
+    track.rotation.interpolation = Interpolation::Linear;
+    track.scaling.interpolation = Interpolation::Linear;
+    for (time, x, degrees, scale) in keys {
+        let mut position = Frame::<3>::new();
+        position.time = *time;
+        position.m_value = [*x, 0.0, 0.0];
+        track.position.frames.push(position);
+
+        let mut rotation = Frame::<4>::new();
+        rotation.time = *time;
+        rotation.m_value = yaw(*degrees).to_array();
+        track.rotation.frames.push(rotation);
+
+        let mut scaling = Frame::<3>::new();
+        scaling.time = *time;
+        scaling.m_value = [*scale; 3];
+        track.scaling.frames.push(scaling);
+    }
+    clip.re_calculate_duration();
+
+    clip
+}
+
+#[test]
+fn additive_clip_is_identity_at_the_reference() {
+    let mut source = clip(&[(0.0, 1.0, 30.0, 2.0), (1.0, 3.0, 90.0, 4.0)]);
+    source.set_looping(false);
+    let mut reference = common::skeleton().rest_pose;
+    source.sample(&mut reference, 0.0, &mut Vec::new());
+
+    let mut additive = make_additive(&source, &reference);
+
+    let mut identity = common::skeleton().rest_pose;
+    additive.sample(&mut identity, 0.0, &mut Vec::new());
+    let joint = identity.joints[0];
+    assert!(joint.translation.len() < EPSILON);
+    assert!((joint.orientation.s.abs() - 1.0).abs() < EPSILON);
+    assert!((joint.scaling - Vec3::ONE).len() < EPSILON);
+
+    // further along the clip only the difference is left
+    additive.sample(&mut identity, 1.0, &mut Vec::new());
+    let joint = identity.joints[0];
+    assert!((joint.translation - Vec3::new(2.0, 0.0, 0.0)).len() < EPSILON);
+    assert!((joint.orientation.angle() - 60.0).abs() < EPSILON);
+    assert!((joint.scaling - Vec3::new(2.0, 2.0, 2.0)).len() < EPSILON);
+}
+
+#[test]
+fn weighted_layer_adds_a_share_of_the_difference() {
+    let mut controller = AnimationController::new(common::skeleton());
+    controller.add_clip(clip(&[(0.0, 1.0, 0.0, 1.0), (1.0, 1.0, 0.0, 1.0)]));
+
+    // two units along x and a quarter turn away from the rest pose
+    let turn = clip(&[(0.0, 2.0, 90.0, 1.0), (1.0, 2.0, 90.0, 1.0)]);
+    let additive = controller.add_clip(make_additive(&turn, &common::skeleton().rest_pose));
+
+    controller.play(0).unwrap();
+    let layer = controller
+        .push_layer(additive, LayerMode::Additive, 0.25, None)
+        .unwrap();
+
+    controller.update(0.1);
+    let joint = controller.get_pose().joints[0];
+    assert!((joint.translation.x - 1.5).abs() < EPSILON);
+    // rotations are weighted along the same nlerp path as blends
+    let share = Quat::ZERO.nlerp(yaw(90.0), 0.25);
+    assert!(joint.orientation.dot(&share).abs() > 1.0 - EPSILON);
+
+    controller.set_layer_weight(layer, 1.0);
+    controller.update(0.1);
+    let joint = controller.get_pose().joints[0];
+    assert!((joint.translation.x - 3.0).abs() < EPSILON);
+    assert!((joint.orientation.angle() - 90.0).abs() < EPSILON);
+
+    controller.remove_layer(layer);
+    controller.update(0.1);
+    let joint = controller.get_pose().joints[0];
+    assert!((joint.translation.x - 1.0).abs() < EPSILON);
+    assert!(joint.orientation.angle().abs() < EPSILON);
+}
@@ -0,0 +1,234 @@
+use animation::binary::{from_bytes, load, save, to_bytes, VERSION};
+use animation::clip::Clip;
+use animation::curves::{Interpolation, LoopMode};
+use animation::frame::Frame;
+use animation::skeleton::Skeleton;
+use animation::track::{ScalarTrack, Track};
+use animation::track_transform::TransformTrack;
+use animation::track_weights::WeightsTrack;
+
+use math::mat4::Mat4;
+use math::quaternion::Quat;
+use math::vec3::vec3;
+
+/// frames with values that do not survive a trip through text, plus every interpolation mode
+fn track(id: u32) -> TransformTrack {
+    let mut track = TransformTrack::new();
+    track.id = id;
+    track.position.interpolation = Interpolation::Constant;
+    track.rotation.interpolation = Interpolation::Cubic;
+    track.scaling.interpolation = Interpolation::Linear;
+
+    for i in 0..4 {
+        let time = i as f32 / 3.0;
+
+        let mut position = Frame::<3>::new();
+        position.time = time;
+        position.m_value = [0.1 * i as f32, -f32::MIN_POSITIVE, 1e-40];
+        position.m_in = [f32::MAX, 0.3, -0.0];
+        position.m_out = [1.0 / 7.0, f32::EPSILON, 2.5];
+        track.position.frames.push(position);
+
+        let mut rotation = Frame::<4>::new();
+        rotation.time = time;
+        rotation.m_value = Quat::create(33.3 * i as f32, vec3(1.0, 2.0, 3.0)).to_array();
+        rotation.m_in = [0.01, 0.02, 0.03, 0.04];
+        rotation.m_out = [-0.01, -0.02, -0.03, -0.04];
+        track.rotation.frames.push(rotation);
+
+        let mut scaling = Frame::<3>::ONE;
+        scaling.time = time;
+        scaling.m_value[1] = 1.0 + time;
+        track.scaling.frames.push(scaling);
+    }
+
+    track
+}
+
+fn same_frames<const N: usize>(a: &Track<N>, b: &Track<N>) {
+    assert!(a.interpolation == b.interpolation);
+    assert_eq!(a.frames.len(), b.frames.len());
+    for (a, b) in a.frames.iter().zip(&b.frames) {
+        // bit for bit, so -0.0 and denormals count too
+        assert_eq!(a.time.to_bits(), b.time.to_bits());
+        for (a, b) in [
+            (&a.m_value, &b.m_value),
+            (&a.m_in, &b.m_in),
+            (&a.m_out, &b.m_out),
+        ] {
+            let a = a.map(f32::to_bits);
+            let b = b.map(f32::to_bits);
+            assert_eq!(a, b);
+        }
+    }
+}
+
+fn same_tracks(a: &TransformTrack, b: &TransformTrack) {
+    assert_eq!(a.id, b.id);
+    same_frames(&a.position, &b.position);
+    same_frames(&a.rotation, &b.rotation);
+    same_frames(&a.scaling, &b.scaling);
+}
+
+fn clip() -> Clip {
+    let mut clip = Clip::new();
+    clip.name = String::from("wave ✋");
+    clip.set_loop_mode(LoopMode::PingPong);
+    clip.set_speed(-1.25);
+    clip.add_event("raise", 0.25);
+    clip.add_event("lower", 0.75);
+    clip.tracks = vec![track(3), track(0), track(7)];
+    clip.re_calculate_duration();
+
+    clip
+}
+
+#[test]
+fn tracks_round_trip_exactly() {
+    let track = track(5);
+    let loaded = from_bytes::<TransformTrack>(&to_bytes(&track)).unwrap();
+    same_tracks(&track, &loaded);
+
+    // the header is magic, version and kind
+    let bytes = to_bytes(&track);
+    assert_eq!(&bytes[0..4], b"ANIM");
+    assert_eq!(u16::from_le_bytes([bytes[4], bytes[5]]), VERSION);
+}
+
+#[test]
+fn clips_round_trip_exactly() {
+    let clip = clip();
+    let loaded = from_bytes::<Clip>(&to_bytes(&clip)).unwrap();
+
+    assert_eq!(loaded.name, clip.name);
+    assert_eq!(loaded.get_loop_mode(), LoopMode::PingPong);
+    assert_eq!(loaded.get_speed(), -1.25);
+    assert_eq!(loaded.get_events(), clip.get_events());
+    assert_eq!(loaded.get_start_time(), clip.get_start_time());
+    assert_eq!(loaded.get_end_time(), clip.get_end_time());
+    assert_eq!(loaded.tracks.len(), 3);
+    for (a, b) in clip.tracks.iter().zip(&loaded.tracks) {
+        same_tracks(a, b);
+    }
+
+    // saving again gives the same bytes
+    assert_eq!(to_bytes(&loaded), to_bytes(&clip));
+}
+
+#[test]
+fn weights_round_trip_exactly() {
+    let mut clip = clip();
+    let mut weights = WeightsTrack::new();
+    weights.id = 9;
+    for interpolation in [Interpolation::Constant, Interpolation::Cubic] {
+        let mut track = ScalarTrack::new();
+        track.interpolation = interpolation;
+        for i in 0..3 {
+            let mut frame = Frame::<1>::new();
+            frame.time = i as f32 / 3.0;
+            frame.m_value = [1.0 / (i + 3) as f32];
+            frame.m_in = [-0.0];
+            frame.m_out = [f32::MIN_POSITIVE];
+            track.frames.push(frame);
+        }
+        weights.weights.push(track);
+    }
+    clip.weights.push(weights);
+
+    let loaded = from_bytes::<Clip>(&to_bytes(&clip)).unwrap();
+    assert_eq!(loaded.weights.len(), 1);
+    assert_eq!(loaded.weights[0].id, 9);
+    assert_eq!(loaded.weights[0].weights.len(), 2);
+    for (a, b) in clip.weights[0]
+        .weights
+        .iter()
+        .zip(&loaded.weights[0].weights)
+    {
+        same_frames(a, b);
+    }
+}
+
+#[test]
+fn version_one_clips_still_load() {
+    // version 1 clips end right after their transform tracks, without a weights count
+    let mut bytes = to_bytes(&clip());
+    bytes.truncate(bytes.len() - 4);
+    bytes[4..6].copy_from_slice(&1u16.to_le_bytes());
+
+    let loaded = from_bytes::<Clip>(&bytes).unwrap();
+    assert_eq!(loaded.tracks.len(), 3);
+    assert!(loaded.weights.is_empty());
+}
+
+#[test]
+fn skeletons_round_trip_exactly() {
+    let mut skeleton = Skeleton::new();
+    skeleton.rest_pose.resize(3);
+    skeleton.rest_pose.parents = vec![-1, 0, 1];
+    skeleton.rest_pose.joints[1].translation = vec3(0.0, 1.0 / 3.0, 0.0);
+    skeleton.rest_pose.joints[2].orientation = Quat::create(45.0, vec3(0.0, 0.0, 1.0));
+    skeleton.rest_pose.joints[2].scaling = vec3(2.0, 2.0, 2.0);
+    skeleton.joint_names = vec![String::from("root"), String::from("spine"), String::new()];
+    let mut matrix = Mat4::IDENTITY;
+    matrix.data[3][1] = -1.0 / 3.0;
+    skeleton.inverse_bind_pose = vec![Some(Mat4::IDENTITY), None, Some(matrix)];
+
+    let path = std::env::temp_dir().join(format!("skeleton_{}.anim", std::process::id()));
+    save(&skeleton, &path).unwrap();
+    let loaded = load::<Skeleton>(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded.joint_names, skeleton.joint_names);
+    assert_eq!(loaded.rest_pose.parents, skeleton.rest_pose.parents);
+    assert!(loaded.rest_pose == skeleton.rest_pose);
+    assert_eq!(loaded.inverse_bind_pose, skeleton.inverse_bind_pose);
+}
+
+#[test]
+fn skeletons_that_are_not_trees_are_rejected() {
+    let mut skeleton = Skeleton::new();
+    skeleton.rest_pose.resize(3);
+    skeleton.joint_names = vec![String::new(); 3];
+    skeleton.inverse_bind_pose = vec![None; 3];
+
+    // children may come before their parents
+    skeleton.rest_pose.parents = vec![2, -1, 1];
+    assert!(from_bytes::<Skeleton>(&to_bytes(&skeleton)).is_ok());
+
+    for parents in [
+        vec![-1, -2, 0],
+        vec![-1, 1, 0],
+        vec![-1, 2, 1],
+        vec![-1, 0, 3],
+    ] {
+        skeleton.rest_pose.parents = parents;
+        assert!(from_bytes::<Skeleton>(&to_bytes(&skeleton)).is_err());
+    }
+}
+
+#[test]
+fn broken_data_is_rejected() {
+    let bytes = to_bytes(&clip());
+
+    // every truncation fails instead of panicking
+    for len in 0..bytes.len() {
+        assert!(from_bytes::<Clip>(&bytes[..len]).is_err());
+    }
+
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert!(from_bytes::<Clip>(&trailing).is_err());
+
+    let mut magic = bytes.clone();
+    magic[0] = b'X';
+    assert!(from_bytes::<Clip>(&magic).is_err());
+
+    let mut version = bytes.clone();
+    version[4..6].copy_from_slice(&(VERSION + 1).to_le_bytes());
+    let error = from_bytes::<Clip>(&version).err().unwrap();
+    assert!(error.contains("version"), "{}", error);
+
+    // a clip is not a skeleton
+    assert!(from_bytes::<Skeleton>(&bytes).is_err());
+    assert!(load::<Clip>(std::path::Path::new("/nonexistent/clip.anim")).is_err());
+}
@@ -0,0 +1,98 @@
+use animation::blend_space::{BlendSpace1D, BlendSpace2D};
+use animation::clip::Clip;
+use animation::curves::Interpolation;
+use animation::frame::Frame;
+use animation::pose::Pose;
+
+use math::vec2::vec2;
+
+const EPSILON: f32 = 1e-5;
+
+/// clip linearly moving the root from 0 to `height` on y over `duration` seconds
+fn clip(height: f32, duration: f32) -> Clip {
+    let mut clip = Clip::new();
+
+    let track = clip.get_transform_track(0);
+    track.position.interpolation = Interpolation::Linear;
+    for (time, y) in [(0.0, 0.0), (duration, height)] {
+        let mut frame = Frame::<3>::new();
+        frame.time = time;
+        frame.m_value = [0.0, y, 0.0];
+        track.position.frames.push(frame);
+    }
+    clip.re_calculate_duration();
+
+    clip
+}
+
+fn assert_weights(weights: &[f32], expected: &[f32]) {
+    for (weight, expected) in weights.iter().zip(expected) {
+        assert!(
+            (weight - expected).abs() < EPSILON,
+            "{:?} != {:?}",
+            weights,
+            expected
+        );
+    }
+}
+
+#[test]
+fn one_dimensional_weights_interpolate_between_neighbours() {
+    let mut blend = BlendSpace1D::new();
+    blend.add_clip(clip(1.0, 1.0), 0.0);
+    blend.add_clip(clip(2.0, 1.0), 4.0);
+    blend.add_clip(clip(3.0, 1.0), 2.0);
+
+    blend.set_parameter(1.0);
+    assert_weights(blend.get_weights(), &[0.5, 0.0, 0.5]);
+
+    blend.set_parameter(3.0);
+    assert_weights(blend.get_weights(), &[0.0, 0.5, 0.5]);
+
+    // outside the line the closest end plays on its own
+    blend.set_parameter(-1.0);
+    assert_weights(blend.get_weights(), &[1.0, 0.0, 0.0]);
+    blend.set_parameter(10.0);
+    assert_weights(blend.get_weights(), &[0.0, 1.0, 0.0]);
+}
+
+#[test]
+fn two_dimensional_weights_match_the_closest_point() {
+    let mut blend = BlendSpace2D::new();
+    blend.add_clip(clip(0.0, 1.0), vec2(0.0, 0.0));
+    blend.add_clip(clip(1.0, 1.0), vec2(1.0, 0.0));
+    blend.add_clip(clip(2.0, 1.0), vec2(0.0, 1.0));
+    blend.add_clip(clip(3.0, 1.0), vec2(-1.0, 0.0));
+
+    blend.set_parameter(vec2(1.0, 0.0));
+    assert_weights(blend.get_weights(), &[0.0, 1.0, 0.0, 0.0]);
+
+    blend.set_parameter(vec2(0.5, 0.0));
+    assert_weights(blend.get_weights(), &[0.5, 0.5, 0.0, 0.0]);
+
+    blend.set_parameter(vec2(0.3, 0.4));
+    let sum = blend.get_weights().iter().sum::<f32>();
+    assert!((sum - 1.0).abs() < EPSILON);
+    assert!(blend.get_weights().iter().all(|weight| *weight >= 0.0));
+}
+
+#[test]
+fn clips_of_different_lengths_stay_in_step() {
+    let mut blend = BlendSpace1D::new();
+    blend.add_clip(clip(1.0, 1.0), 0.0);
+    blend.add_clip(clip(3.0, 3.0), 1.0);
+
+    // halfway between the clips the blend lasts two seconds
+    blend.set_parameter(0.5);
+    blend.update(0.5);
+    assert!((blend.get_normalized_time() - 0.25).abs() < EPSILON);
+
+    // both clips are a quarter of the way through, 0.25 and 0.75 on y
+    let mut pose = Pose::new();
+    pose.resize(1);
+    blend.sample(&mut pose, &mut Vec::new());
+    assert!((pose.joints[0].translation.y - 0.5).abs() < EPSILON);
+
+    blend.update(1.5);
+    assert!(blend.get_normalized_time().abs() < EPSILON);
+}
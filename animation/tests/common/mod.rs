@@ -0,0 +1,29 @@
+// fixtures shared by the integration tests, not every test uses all of them
+#![allow(dead_code)]
+
+use animation::pose::Pose;
+use animation::skeleton::Skeleton;
+
+use math::mat4::Mat4;
+
+/// skeleton of a single "root" joint
+pub fn skeleton() -> Skeleton {
+    skeleton_of(&[("root", -1)])
+}
+
+/// skeleton with a joint per (name, parent), every joint at identity with an identity
+/// inverse bind matrix
+pub fn skeleton_of(joints: &[(&str, i32)]) -> Skeleton {
+    let mut rest_pose = Pose::new();
+    rest_pose.resize(joints.len());
+
+    let mut skeleton = Skeleton::new();
+    for (i, (name, parent)) in joints.iter().enumerate() {
+        rest_pose.parents[i] = *parent;
+        skeleton.joint_names.push(String::from(*name));
+        skeleton.inverse_bind_pose.push(Some(Mat4::IDENTITY));
+    }
+    skeleton.rest_pose = rest_pose;
+
+    skeleton
+}
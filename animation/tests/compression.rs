@@ -0,0 +1,205 @@
+use animation::clip::Clip;
+use animation::compression::{
+    dequantize_quat, memory_size, quantize_quat, reduce_clip, reduce_vector_track, CompressedClip,
+    CompressionSettings, Tolerance,
+};
+use animation::curves::{Interpolation, LoopMode};
+use animation::frame::Frame;
+use animation::pose::Pose;
+use animation::track::VectorTrack;
+
+use math::quaternion::Quat;
+use math::vec3::vec3;
+
+const JOINTS: usize = 3;
+
+/// two second "mocap" clip keyed 60 times per second with smooth motion on every joint
+fn mocap(interpolation: Interpolation) -> Clip {
+    let mut clip = Clip::new();
+    clip.name = String::from("mocap");
+
+    for joint in 0..JOINTS {
+        let track = clip.get_transform_track(joint as u32);
+        track.position.interpolation = interpolation;
+        track.rotation.interpolation = interpolation;
+        track.scaling.interpolation = interpolation;
+
+        for i in 0..=120 {
+            let time = i as f32 / 60.0;
+            let phase = time * (joint + 1) as f32;
+
+            let mut position = Frame::<3>::new();
+            position.time = time;
+            position.m_value = [phase.sin(), 0.5 * time, phase.cos() * 2.0];
+            track.position.frames.push(position);
+
+            let mut rotation = Frame::<4>::new();
+            rotation.time = time;
+            rotation.m_value = Quat::create(90.0 * phase.sin(), vec3(1.0, 1.0, 0.0)).to_array();
+            track.rotation.frames.push(rotation);
+
+            // scale never changes, every key but the ends can go
+            let mut scaling = Frame::<3>::ONE;
+            scaling.time = time;
+            track.scaling.frames.push(scaling);
+        }
+
+        smooth_tangents(&mut track.position.frames);
+        smooth_tangents(&mut track.rotation.frames);
+    }
+    clip.re_calculate_duration();
+    clip.add_event("step", 0.5);
+
+    clip
+}
+
+/// catmull rom style tangents so cubic curves are smooth like exported ones
+fn smooth_tangents<const N: usize>(frames: &mut [Frame<N>]) {
+    for i in 0..frames.len() {
+        let previous = &frames[i.saturating_sub(1)];
+        let next = &frames[(i + 1).min(frames.len() - 1)];
+        let span = next.time - previous.time;
+
+        let mut tangent = [0.0; N];
+        for (k, value) in tangent.iter_mut().enumerate() {
+            *value = (next.m_value[k] - previous.m_value[k]) / span;
+        }
+        frames[i].m_in = tangent;
+        frames[i].m_out = tangent;
+    }
+}
+
+fn sample(clip: &mut Clip, time: f32) -> Pose {
+    let mut pose = Pose::new();
+    pose.resize(JOINTS);
+    clip.sample(&mut pose, time, &mut Vec::new());
+
+    pose
+}
+
+fn sample_compressed(clip: &CompressedClip, time: f32) -> Pose {
+    let mut pose = Pose::new();
+    pose.resize(JOINTS);
+    clip.sample(&mut pose, time);
+
+    pose
+}
+
+/// largest position and rotation difference between two poses
+fn pose_error(a: &Pose, b: &Pose) -> (f32, f32) {
+    let mut position: f32 = 0.0;
+    let mut rotation: f32 = 0.0;
+    for (a, b) in a.joints.iter().zip(&b.joints) {
+        position = position.max((a.translation - b.translation).len());
+        rotation = rotation.max((1.0 - a.orientation.dot(&b.orientation).abs()).max(0.0));
+    }
+
+    (position, rotation)
+}
+
+fn times() -> impl Iterator<Item = f32> {
+    (0..=200).map(|i| i as f32 / 100.0)
+}
+
+#[test]
+fn quaternions_survive_quantization() {
+    for i in 0..200 {
+        let angle = i as f32 * 7.3 - 720.0;
+        let axis = vec3((i as f32).sin(), (i as f32 * 0.7).cos(), 0.3);
+        let q = Quat::create(angle, axis);
+
+        let restored = dequantize_quat(&quantize_quat(&q));
+        assert!(
+            1.0 - q.dot(&restored).abs() < 1e-6,
+            "{:?} {:?}",
+            q,
+            restored
+        );
+    }
+}
+
+#[test]
+fn collinear_keys_are_removed() {
+    let mut track = VectorTrack::new();
+    track.interpolation = Interpolation::Linear;
+    for i in 0..50 {
+        let mut frame = Frame::<3>::new();
+        frame.time = i as f32 * 0.1;
+        frame.m_value = [i as f32, 2.0 * i as f32, 0.0];
+        track.frames.push(frame);
+    }
+    // a corner that has to stay
+    track.frames[25].m_value[2] = 1.0;
+
+    let reduced = reduce_vector_track(&track, 1e-3);
+    let times = reduced
+        .frames
+        .iter()
+        .map(|frame| frame.time)
+        .collect::<Vec<f32>>();
+    assert_eq!(times.len(), 5);
+    assert_eq!(times[0], 0.0);
+    assert_eq!(*times.last().unwrap(), 4.9);
+}
+
+#[test]
+fn reduced_clips_stay_within_tolerance() {
+    let mut clip = mocap(Interpolation::Linear);
+    let tolerance = Tolerance::default();
+    let mut reduced = reduce_clip(&clip, &tolerance);
+
+    assert!(memory_size(&reduced) < memory_size(&clip) / 2);
+    for time in times() {
+        let (position, rotation) =
+            pose_error(&sample(&mut clip, time), &sample(&mut reduced, time));
+        assert!(
+            position <= tolerance.position * 1.01,
+            "{} at {}",
+            position,
+            time
+        );
+        assert!(rotation <= 1e-5, "{} at {}", rotation, time);
+    }
+}
+
+#[test]
+fn compressed_clips_sample_the_same_pose() {
+    for interpolation in [Interpolation::Linear, Interpolation::Cubic] {
+        let mut clip = mocap(interpolation);
+        clip.set_loop_mode(LoopMode::PingPong);
+        // keys are 60 times per second, bake cubic curves finer than that
+        let settings = CompressionSettings {
+            bake_rate: 240.0,
+            ..CompressionSettings::default()
+        };
+        let compressed = CompressedClip::new(&clip, &settings);
+
+        assert!(compressed.memory_size() < memory_size(&clip) / 4);
+        for time in times().map(|time| time * 1.7) {
+            let (position, rotation) = pose_error(
+                &sample(&mut clip, time),
+                &sample_compressed(&compressed, time),
+            );
+            assert!(
+                position < 5e-3,
+                "{:?} {} at {}",
+                interpolation,
+                position,
+                time
+            );
+            assert!(rotation < 1e-4, "{} at {}", rotation, time);
+        }
+
+        // expanding it again gives a clip that plays the same way
+        let mut decompressed = compressed.decompress();
+        assert_eq!(decompressed.get_loop_mode(), LoopMode::PingPong);
+        assert_eq!(decompressed.get_events(), clip.get_events());
+        for time in times() {
+            let (position, rotation) = pose_error(
+                &sample(&mut decompressed, time),
+                &sample_compressed(&compressed, time),
+            );
+            assert!(position < 1e-5 && rotation < 1e-6);
+        }
+    }
+}
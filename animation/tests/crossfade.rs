@@ -0,0 +1,135 @@
+mod common;
+
+use animation::clip::Clip;
+use animation::controller::AnimationController;
+use animation::curves::Interpolation;
+use animation::frame::Frame;
+use animation::pose::Pose;
+
+use math::quaternion::Quat;
+use math::transform::Transform;
+use math::vec3::Vec3;
+
+const EPSILON: f32 = 1e-4;
+
+/// one second clip holding the root at `x`
+fn hold(name: &str, x: f32) -> Clip {
+    let mut clip = Clip::new();
+    clip.name = String::from(name);
+
+    let track = clip.get_transform_track(0);
+    track.position.interpolation = Interpolation::Linear;
+    for time in [0.0, 1.0] {
+        let mut frame = Frame::<3>::new();
+        frame.time = time;
+        frame.m_value = [x, 0.0, 0.0];
+        track.position.frames.push(frame);
+    }
+    clip.re_calculate_duration();
+
+    clip
+}
+
+/// plays "a" at x = 0, "b" at x = 1 and "c" at x = 3 are ready to fade to
+fn controller() -> AnimationController {
+    let mut controller = AnimationController::new(common::skeleton());
+    controller.add_clip(hold("a", 0.0));
+    controller.add_clip(hold("b", 1.0));
+    controller.add_clip(hold("c", 3.0));
+    controller.play(0).unwrap();
+
+    controller
+}
+
+fn x(controller: &AnimationController) -> f32 {
+    controller.get_pose().joints[0].translation.x
+}
+
+fn current(controller: &AnimationController) -> &str {
+    &controller.current_clip().unwrap().name
+}
+
+#[test]
+fn blend_goes_from_a_to_b() {
+    let mut a = Pose::new();
+    a.resize(1);
+    let mut b = a.clone();
+    b.joints[0] = Transform::new(
+        Vec3::new(3.0, 3.0, 3.0),
+        Vec3::new(2.0, 0.0, 0.0),
+        Quat::create(90.0, Vec3::new(0.0, 1.0, 0.0)),
+    );
+
+    let mut out = a.clone();
+    Pose::blend(&mut out, &a, &b, 0.0);
+    assert!(out == a);
+
+    Pose::blend(&mut out, &a, &b, 1.0);
+    let joint = out.joints[0];
+    assert!((joint.translation - b.joints[0].translation).len() < EPSILON);
+    assert!((joint.scaling - b.joints[0].scaling).len() < EPSILON);
+    assert!(joint.orientation.dot(&b.joints[0].orientation).abs() > 1.0 - EPSILON);
+
+    Pose::blend(&mut out, &a, &b, 0.5);
+    let joint = out.joints[0];
+    assert!((joint.translation - Vec3::new(1.0, 0.0, 0.0)).len() < EPSILON);
+    assert!((joint.scaling - Vec3::new(2.0, 2.0, 2.0)).len() < EPSILON);
+    assert!((joint.orientation.angle() - 45.0).abs() < EPSILON);
+}
+
+#[test]
+fn fade_weight_rises_over_the_duration() {
+    let mut controller = controller();
+    controller.crossfade_to(1, 1.0).unwrap();
+
+    for step in 1..=4 {
+        controller.update(0.25);
+
+        let t = step as f32 * 0.25;
+        assert!((x(&controller) - t).abs() < EPSILON);
+        assert!((controller.get_fade_targets()[0].elapsed - t).abs() < EPSILON);
+    }
+}
+
+#[test]
+fn finished_fade_becomes_the_current_clip() {
+    let mut controller = controller();
+    controller.crossfade_to(1, 0.5).unwrap();
+
+    controller.update(0.5);
+    assert_eq!(current(&controller), "a");
+    assert_eq!(controller.get_fade_targets().len(), 1);
+
+    controller.update(0.25);
+    assert_eq!(current(&controller), "b");
+    assert!(controller.get_fade_targets().is_empty());
+    assert!((x(&controller) - 1.0).abs() < EPSILON);
+}
+
+#[test]
+fn stacked_fades_blend_over_each_other() {
+    let mut controller = controller();
+    controller.crossfade_to(1, 1.0).unwrap();
+    controller.update(0.5);
+    assert!((x(&controller) - 0.5).abs() < EPSILON);
+
+    // fading to the clip already being faded to does nothing
+    controller.crossfade_to(2, 1.0).unwrap();
+    controller.crossfade_to(2, 1.0).unwrap();
+    assert_eq!(controller.get_fade_targets().len(), 2);
+
+    // "b" is fully faded in and "c" is half way over it
+    controller.update(0.5);
+    assert_eq!(controller.get_fade_targets().len(), 2);
+    assert!((x(&controller) - 2.0).abs() < EPSILON);
+
+    // "b" takes over from "a", "c" keeps fading
+    controller.update(0.5);
+    assert_eq!(current(&controller), "b");
+    assert_eq!(controller.get_fade_targets().len(), 1);
+    assert!((x(&controller) - 3.0).abs() < EPSILON);
+
+    controller.update(0.5);
+    assert_eq!(current(&controller), "c");
+    assert!(controller.get_fade_targets().is_empty());
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use animation::clip::Clip;
+use animation::controller::AnimationController;
+use animation::frame::Frame;
+
+/// one second clip with a footstep at the start and one halfway through
+fn walk() -> Clip {
+    let mut clip = Clip::new();
+
+    let track = clip.get_transform_track(0);
+    for time in [0.0, 1.0] {
+        let mut frame = Frame::<3>::new();
+        frame.time = time;
+        track.position.frames.push(frame);
+    }
+    clip.re_calculate_duration();
+
+    clip.add_event("right_foot", 0.5);
+    clip.add_event("left_foot", 0.0);
+
+    clip
+}
+
+fn names(clip: &Clip, from: f32, to: f32, include_from: bool) -> Vec<&str> {
+    clip.events_between(from, to, include_from)
+        .into_iter()
+        .map(|event| event.name.as_str())
+        .collect()
+}
+
+/// update the controller `steps` times and collect the names of the events reported
+fn run(controller: &mut AnimationController, steps: usize, delta_time: f32) -> Vec<String> {
+    let mut events = Vec::new();
+    for _ in 0..steps {
+        controller.update(delta_time);
+        events.extend(
+            controller
+                .drain_events()
+                .into_iter()
+                .map(|event| event.name),
+        );
+    }
+
+    events
+}
+
+#[test]
+fn events_are_kept_sorted() {
+    let clip = walk();
+    let times = clip
+        .get_events()
+        .iter()
+        .map(|event| event.time)
+        .collect::<Vec<f32>>();
+
+    assert_eq!(times, vec![0.0, 0.5]);
+}
+
+#[test]
+fn events_wrap_around_looping_clips() {
+    let clip = walk();
+
+    assert_eq!(names(&clip, 0.2, 0.6, false), vec!["right_foot"]);
+    assert_eq!(names(&clip, 0.6, 1.2, false), vec!["left_foot"]);
+    assert_eq!(
+        names(&clip, 0.4, 2.1, false),
+        vec!["right_foot", "left_foot", "right_foot", "left_foot"]
+    );
+    assert!(names(&clip, 0.5, 0.7, false).is_empty());
+    assert_eq!(names(&clip, 0.0, 0.1, true), vec!["left_foot"]);
+}
+
+#[test]
+fn reverse_playback_reports_events_backwards() {
+    let clip = walk();
+
+    assert_eq!(names(&clip, 0.6, 0.4, false), vec!["right_foot"]);
+    assert_eq!(
+        names(&clip, 0.6, -0.6, false),
+        vec!["right_foot", "left_foot", "right_foot"]
+    );
+}
+
+#[test]
+fn clamped_clips_report_events_once() {
+    let mut clip = walk();
+    clip.set_looping(false);
+
+    assert_eq!(names(&clip, 0.4, 3.0, false), vec!["right_foot"]);
+    assert!(names(&clip, 1.0, 2.0, false).is_empty());
+}
+
+#[test]
+fn controller_reports_crossed_events() {
+    let mut controller = AnimationController::new(common::skeleton());
+    controller.add_clip(walk());
+    controller.play(0).unwrap();
+
+    // the event at the start fires on the first update
+    assert_eq!(run(&mut controller, 1, 0.1), vec!["left_foot"]);
+    assert_eq!(
+        run(&mut controller, 10, 0.1),
+        vec!["right_foot", "left_foot"]
+    );
+
+    let events = controller.drain_events();
+    assert!(events.is_empty());
+
+    let mut reversed = walk();
+    reversed.set_speed(-1.0);
+    controller.add_clip(reversed);
+    controller.play(1).unwrap();
+
+    assert_eq!(
+        run(&mut controller, 9, 0.1),
+        vec!["left_foot", "right_foot"]
+    );
+}
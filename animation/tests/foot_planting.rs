@@ -0,0 +1,124 @@
+use animation::foot_planting::{FootPlanting, Ground, GroundMesh, HeightField, Leg};
+use animation::pose::Pose;
+
+use math::quaternion::Quat;
+use math::transform::Transform;
+use math::vec3::{vec3, Vec3};
+
+const EPSILON: f32 = 1e-3;
+
+/// pelvis at 2 units high with a single leg reaching down to the ground
+/// pelvis 0, hip 1, knee 2, ankle 3
+fn leg_pose() -> Pose {
+    let mut pose = Pose::new();
+    pose.resize(4);
+    pose.parents = vec![-1, 0, 1, 2];
+
+    pose.joints[0].translation = vec3(0.0, 2.0, 0.0);
+    pose.joints[2].translation = vec3(0.0, -1.0, 0.1);
+    pose.joints[3].translation = vec3(0.0, -1.0, -0.1);
+
+    pose
+}
+
+fn planting() -> FootPlanting {
+    let leg = Leg {
+        hip: 1,
+        knee: 2,
+        ankle: 3,
+        foot_height: 0.0,
+    };
+
+    FootPlanting::new(0, vec![leg], 0.5, 0.5)
+}
+
+fn flat_field(height: f32) -> HeightField {
+    HeightField::new(vec3(-5.0, height, -5.0), 1.0, 11, 11)
+}
+
+fn position(pose: &Pose, joint: usize) -> Vec3 {
+    pose.get_global_tranform(joint).translation
+}
+
+#[test]
+fn height_field_interpolates_between_samples() {
+    let mut field = HeightField::new(vec3(0.0, 0.0, 0.0), 1.0, 2, 2);
+    field.set_height(1, 0, 1.0);
+    field.set_height(1, 1, 1.0);
+
+    let (height, normal) = field.sample(0.25, 0.5).unwrap();
+    assert!((height - 0.25).abs() < EPSILON);
+    assert!(normal.x < 0.0 && normal.y > 0.0);
+
+    assert!(field.sample(2.0, 0.5).is_none());
+
+    let hit = field.raycast_down(&vec3(0.5, 1.0, 0.5), 2.0).unwrap();
+    assert!((hit.point.y - 0.5).abs() < EPSILON);
+    assert!(field.raycast_down(&vec3(0.5, 1.0, 0.5), 0.1).is_none());
+}
+
+#[test]
+fn ground_mesh_returns_the_closest_surface_below() {
+    let mut mesh = GroundMesh::new();
+    let quad = |height: f32| {
+        [
+            [
+                vec3(-1.0, height, -1.0),
+                vec3(1.0, height, -1.0),
+                vec3(1.0, height, 1.0),
+            ],
+            [
+                vec3(-1.0, height, -1.0),
+                vec3(1.0, height, 1.0),
+                vec3(-1.0, height, 1.0),
+            ],
+        ]
+    };
+    for triangle in quad(0.0).into_iter().chain(quad(0.5)).chain(quad(3.0)) {
+        mesh.add_triangle(triangle);
+    }
+
+    // the surface above the origin of the ray is ignored
+    let hit = mesh.raycast_down(&vec3(0.2, 1.0, 0.3), 5.0).unwrap();
+    assert!((hit.point.y - 0.5).abs() < EPSILON);
+    assert!((hit.normal.y - 1.0).abs() < EPSILON);
+
+    assert!(mesh.raycast_down(&vec3(2.0, 1.0, 0.0), 5.0).is_none());
+}
+
+#[test]
+fn feet_step_up_onto_raised_ground() {
+    let mut pose = leg_pose();
+    planting().apply(&mut pose, &flat_field(0.3), &Transform::DEFAULT);
+
+    assert!((position(&pose, 3).y - 0.3).abs() < EPSILON);
+    assert!((position(&pose, 0).y - 2.0).abs() < EPSILON);
+}
+
+#[test]
+fn pelvis_lowers_for_lower_ground() {
+    let mut pose = leg_pose();
+    planting().apply(&mut pose, &flat_field(-0.3), &Transform::DEFAULT);
+
+    assert!((position(&pose, 3).y + 0.3).abs() < EPSILON);
+    assert!((position(&pose, 0).y - 1.7).abs() < EPSILON);
+}
+
+#[test]
+fn ground_space_is_respected() {
+    // the ground is twice the size of the model, so 0.4 in ground space is 0.2 in model space
+    let to_ground = Transform::new(vec3(2.0, 2.0, 2.0), Vec3::ZERO, Quat::ZERO);
+
+    let mut pose = leg_pose();
+    planting().apply(&mut pose, &flat_field(0.4), &to_ground);
+
+    assert!((position(&pose, 3).y - 0.2).abs() < EPSILON);
+}
+
+#[test]
+fn feet_without_ground_are_left_alone() {
+    let mut pose = leg_pose();
+    planting().apply(&mut pose, &GroundMesh::new(), &Transform::DEFAULT);
+
+    assert!(pose == leg_pose());
+}
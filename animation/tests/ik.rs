@@ -0,0 +1,127 @@
+use animation::ik::{solve_two_bone, CCDSolver, FABRIKSolver, IKChain, JointLimit};
+use animation::pose::Pose;
+
+use math::quaternion::Quat;
+use math::vec3::{vec3, Vec3};
+
+const EPSILON: f32 = 1e-3;
+
+/// straight chain of unit length bones along the y axis
+fn chain_pose(len: usize) -> Pose {
+    let mut pose = Pose::new();
+    pose.resize(len);
+    for i in 1..len {
+        pose.parents[i] = i as i32 - 1;
+        pose.joints[i].translation = vec3(0.0, 1.0, 0.0);
+    }
+
+    pose
+}
+
+fn position(pose: &Pose, joint: usize) -> Vec3 {
+    pose.get_global_tranform(joint).translation
+}
+
+fn assert_close(a: Vec3, b: Vec3, epsilon: f32) {
+    assert!((a - b).len() < epsilon, "{:?} != {:?}", a, b);
+}
+
+#[test]
+fn two_bone_reaches_target_and_bends_towards_pole() {
+    let mut pose = chain_pose(3);
+    let target = vec3(1.0, 1.0, 0.0);
+    let pole = vec3(0.0, 0.0, 5.0);
+
+    solve_two_bone(&mut pose, &mut Vec::new(), [0, 1, 2], &target, &pole);
+
+    assert_close(position(&pose, 2), target, EPSILON);
+
+    // bone lengths are kept
+    assert!(((position(&pose, 1) - position(&pose, 0)).len() - 1.0).abs() < EPSILON);
+    assert!(((position(&pose, 2) - position(&pose, 1)).len() - 1.0).abs() < EPSILON);
+
+    // the elbow sits on the pole side of the root to target line
+    assert!(position(&pose, 1).z > 0.5);
+}
+
+#[test]
+fn two_bone_stretches_towards_unreachable_targets() {
+    let mut pose = chain_pose(3);
+    let target = vec3(10.0, 0.0, 0.0);
+
+    solve_two_bone(
+        &mut pose,
+        &mut Vec::new(),
+        [0, 1, 2],
+        &target,
+        &vec3(0.0, 1.0, 0.0),
+    );
+
+    assert_close(position(&pose, 2), vec3(2.0, 0.0, 0.0), 1e-2);
+}
+
+#[test]
+fn ccd_reaches_target() {
+    let mut pose = chain_pose(4);
+    let chain = IKChain::new(&pose, vec![0, 1, 2, 3]).unwrap();
+    let target = vec3(1.5, 1.5, 0.5);
+
+    assert!(CCDSolver::new(30, 1e-3).solve(&mut pose, &mut Vec::new(), &chain, &target));
+    assert_close(position(&pose, 3), target, EPSILON);
+}
+
+#[test]
+fn fabrik_reaches_target_and_keeps_bone_lengths() {
+    let mut pose = chain_pose(4);
+    let chain = IKChain::new(&pose, vec![0, 1, 2, 3]).unwrap();
+    let target = vec3(-1.0, 2.0, 1.0);
+
+    assert!(FABRIKSolver::new(30, 1e-3).solve(&mut pose, &mut Vec::new(), &chain, &target));
+    assert_close(position(&pose, 3), target, EPSILON);
+
+    for i in 0..3 {
+        let length = (position(&pose, i + 1) - position(&pose, i)).len();
+        assert!((length - 1.0).abs() < EPSILON);
+    }
+}
+
+#[test]
+fn limits_are_respected() {
+    let hinge = JointLimit::Hinge {
+        axis: vec3(0.0, 0.0, 1.0),
+        min_angle: 0.0,
+        max_angle: 90.0,
+    };
+
+    // rotation around another axis is dropped
+    let limited = hinge.apply(&Quat::ZERO, &Quat::create(45.0, vec3(1.0, 0.0, 0.0)));
+    assert!(limited.angle() < EPSILON);
+
+    // rotation around the hinge is clamped to its range
+    let limited = hinge.apply(&Quat::ZERO, &Quat::create(120.0, vec3(0.0, 0.0, 1.0)));
+    assert!((limited.angle() - 90.0).abs() < EPSILON);
+    let limited = hinge.apply(&Quat::ZERO, &Quat::create(-30.0, vec3(0.0, 0.0, 1.0)));
+    assert!(limited.angle() < EPSILON);
+
+    let cone = JointLimit::Cone { max_angle: 30.0 };
+    let limited = cone.apply(&Quat::ZERO, &Quat::create(80.0, vec3(1.0, 1.0, 0.0)));
+    assert!((limited.angle() - 30.0).abs() < EPSILON);
+
+    // a chain with a stiff middle joint still moves but never bends that joint past its limit
+    let mut pose = chain_pose(3);
+    let mut chain = IKChain::new(&pose, vec![0, 1, 2]).unwrap();
+    chain.set_limit(1, Some(JointLimit::Cone { max_angle: 10.0 }));
+
+    CCDSolver::default().solve(&mut pose, &mut Vec::new(), &chain, &vec3(0.0, 0.5, 0.0));
+    assert!(pose.joints[1].orientation.angle() <= 10.0 + EPSILON);
+}
+
+#[test]
+fn chains_must_follow_the_hierarchy() {
+    let mut pose = chain_pose(3);
+    pose.parents[2] = 0;
+
+    assert!(IKChain::new(&pose, vec![0, 1, 2]).is_err());
+    assert!(IKChain::new(&pose, vec![0, 2]).is_ok());
+    assert!(IKChain::new(&pose, vec![2]).is_err());
+}
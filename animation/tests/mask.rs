@@ -0,0 +1,121 @@
+mod common;
+
+use animation::clip::Clip;
+use animation::controller::{AnimationController, LayerMode};
+use animation::curves::Interpolation;
+use animation::frame::Frame;
+use animation::mask::BoneMask;
+use animation::pose::Pose;
+
+const EPSILON: f32 = 1e-5;
+
+const JOINTS: [(&str, i32); 7] = [
+    ("Hips", -1),
+    ("Spine", 0),
+    ("Chest", 1),
+    ("Head", 2),
+    ("LeftArm", 2),
+    ("LeftLeg", 0),
+    ("LeftFoot", 5),
+];
+
+const UPPER_BODY: [f32; 7] = [0.0, 1.0, 1.0, 1.0, 1.0, 0.0, 0.0];
+
+/// one second clip holding every joint at `x`
+fn hold(x: f32) -> Clip {
+    let mut clip = Clip::new();
+
+    for joint in 0..JOINTS.len() {
+        let track = clip.get_transform_track(joint as u32);
+        track.position.interpolation = Interpolation::Linear;
+        for time in [0.0, 1.0] {
+            let mut frame = Frame::<3>::new();
+            frame.time = time;
+            frame.m_value = [x, 0.0, 0.0];
+            track.position.frames.push(frame);
+        }
+    }
+    clip.re_calculate_duration();
+
+    clip
+}
+
+#[test]
+fn subtree_weights_the_joint_and_its_children() {
+    let skeleton = common::skeleton_of(&JOINTS);
+
+    assert_eq!(BoneMask::new(&skeleton).weights, vec![0.0; JOINTS.len()]);
+    assert_eq!(BoneMask::full(&skeleton).weights, vec![1.0; JOINTS.len()]);
+
+    let mut mask = BoneMask::new(&skeleton);
+    assert!(mask.set_subtree(&skeleton, "Spine", 1.0));
+    assert_eq!(mask.weights, UPPER_BODY);
+
+    assert!(mask.set_joint(&skeleton, "Head", 0.5));
+    assert_eq!(mask.get_weight(3), 0.5);
+    assert_eq!(mask.get_weight(2), 1.0);
+
+    assert!(!mask.set_subtree(&skeleton, "Tail", 1.0));
+    assert!(!mask.set_joint(&skeleton, "Tail", 1.0));
+    assert_eq!(mask.get_weight(JOINTS.len()), 0.0);
+}
+
+#[test]
+fn invert_flips_every_weight() {
+    let skeleton = common::skeleton_of(&JOINTS);
+
+    let mut mask = BoneMask::new(&skeleton);
+    mask.set_subtree(&skeleton, "Spine", 1.0);
+    mask.set_joint(&skeleton, "Head", 0.25);
+    mask.invert();
+
+    assert_eq!(mask.weights, [1.0, 0.0, 0.0, 0.75, 0.0, 1.0, 1.0]);
+}
+
+#[test]
+fn masked_blends_scale_by_the_joint_weight() {
+    let skeleton = common::skeleton_of(&JOINTS);
+    let mut mask = BoneMask::new(&skeleton);
+    mask.set_subtree(&skeleton, "Spine", 1.0);
+    mask.set_joint(&skeleton, "Head", 0.5);
+
+    let base = skeleton.rest_pose.clone();
+    let mut moved = base.clone();
+    for joint in &mut moved.joints {
+        joint.translation.x = 2.0;
+    }
+
+    let mut out = base.clone();
+    Pose::blend_masked(&mut out, &base, &moved, 0.5, &mask);
+    let x = out.joints.iter().map(|joint| joint.translation.x);
+    for (x, weight) in x.zip([0.0, 1.0, 1.0, 0.5, 1.0, 0.0, 0.0]) {
+        assert!((x - weight).abs() < EPSILON);
+    }
+
+    Pose::add_masked(&mut out, &base, &moved, &base, 1.0, &mask);
+    let x = out.joints.iter().map(|joint| joint.translation.x);
+    for (x, weight) in x.zip([0.0, 2.0, 2.0, 1.0, 2.0, 0.0, 0.0]) {
+        assert!((x - weight).abs() < EPSILON);
+    }
+}
+
+#[test]
+fn upper_body_override_leaves_the_legs_alone() {
+    let skeleton = common::skeleton_of(&JOINTS);
+    let mut mask = BoneMask::new(&skeleton);
+    mask.set_subtree(&skeleton, "Spine", 1.0);
+
+    let mut controller = AnimationController::new(skeleton);
+    controller.add_clip(hold(1.0));
+    let wave = controller.add_clip(hold(5.0));
+    controller.play(0).unwrap();
+    controller
+        .push_layer(wave, LayerMode::Override, 1.0, Some(mask))
+        .unwrap();
+
+    controller.update(0.1);
+    for (joint, upper) in controller.get_pose().joints.iter().zip(UPPER_BODY) {
+        let expected = if upper > 0.0 { 5.0 } else { 1.0 };
+        assert!((joint.translation.x - expected).abs() < EPSILON);
+    }
+}
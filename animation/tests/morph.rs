@@ -0,0 +1,127 @@
+mod common;
+
+use animation::clip::Clip;
+use animation::controller::AnimationController;
+use animation::curves::Interpolation;
+use animation::frame::Frame;
+use animation::morph::{morph_normal, morph_position, MorphTarget, MorphWeights};
+use animation::track::ScalarTrack;
+use animation::track_weights::WeightsTrack;
+
+const EPSILON: f32 = 1e-5;
+
+fn assert_close(a: &[f32], b: &[f32]) {
+    assert_eq!(a.len(), b.len(), "{:?} != {:?}", a, b);
+    for (a, b) in a.iter().zip(b) {
+        assert!((a - b).abs() < EPSILON, "{:?} != {:?}", a, b);
+    }
+}
+
+/// linear track going through (time, value) keys
+fn scalar(keys: &[(f32, f32)]) -> ScalarTrack {
+    let mut track = ScalarTrack::new();
+    track.interpolation = Interpolation::Linear;
+    for (time, value) in keys {
+        let mut frame = Frame::<1>::new();
+        frame.time = *time;
+        frame.m_value = [*value];
+        track.frames.push(frame);
+    }
+
+    track
+}
+
+/// one second clip fading the first target of node 4 in and the second one out
+fn fade() -> Clip {
+    let mut track = WeightsTrack::new();
+    track.id = 4;
+    track.weights.push(scalar(&[(0.0, 0.0), (1.0, 1.0)]));
+    track.weights.push(scalar(&[(0.0, 1.0), (1.0, 0.0)]));
+
+    let mut clip = Clip::new();
+    clip.weights.push(track);
+    clip.re_calculate_duration();
+
+    clip
+}
+
+#[test]
+fn targets_add_their_weighted_offsets() {
+    let a = MorphTarget {
+        positions: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0]],
+        normals: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0]],
+        tangents: Vec::new(),
+    };
+    let b = MorphTarget {
+        positions: vec![[0.0, 0.0, 0.0], [0.0, 2.0, 0.0]],
+        ..Default::default()
+    };
+    let targets = [a, b];
+
+    let position = [1.0, 1.0, 1.0];
+    assert_close(
+        &morph_position(&position, 1, &targets, &[0.5, 0.25]),
+        &[1.5, 1.5, 1.0],
+    );
+    // no weights, no change
+    assert_close(&morph_position(&position, 1, &targets, &[]), &position);
+    // targets without normals leave them alone, the result is normalized
+    assert_close(
+        &morph_normal(&[0.0, 1.0, 0.0], 1, &targets, &[1.0, 1.0]),
+        &[0.5f32.sqrt(), 0.5f32.sqrt(), 0.0],
+    );
+}
+
+#[test]
+fn missing_weights_blend_from_zero() {
+    let mut a = MorphWeights::new();
+    a.set(1, &[1.0, 1.0]);
+    let mut b = MorphWeights::new();
+    b.set(1, &[0.0]);
+    b.set(2, &[1.0]);
+
+    let mut out = MorphWeights::new();
+    MorphWeights::blend(&mut out, &a, &b, 0.25);
+    assert_close(out.get(1).unwrap(), &[0.75, 0.75]);
+    assert_close(out.get(2).unwrap(), &[0.25]);
+
+    MorphWeights::blend(&mut out, &b, &a, 1.0);
+    assert_close(out.get(1).unwrap(), &[1.0, 1.0]);
+    assert_close(out.get(2).unwrap(), &[0.0]);
+}
+
+#[test]
+fn clips_sample_weights_per_node() {
+    let mut clip = fade();
+    clip.build_lookups(60.0);
+    assert_eq!(clip.get_duration(), 1.0);
+
+    let mut weights = MorphWeights::new();
+    weights.set(4, &[0.0, 0.0, 0.5]);
+    clip.sample_weights(&mut weights, 0.25, &mut Vec::new());
+
+    // targets without a track keep their weight
+    assert_close(weights.get(4).unwrap(), &[0.25, 0.75, 0.5]);
+    assert!(weights.get(0).is_none());
+}
+
+#[test]
+fn controllers_crossfade_weights() {
+    let mut controller = AnimationController::new(common::skeleton());
+    controller.set_rest_weights(4, &[0.5, 0.5]);
+    assert_close(controller.get_weights().get(4).unwrap(), &[0.5, 0.5]);
+
+    controller.add_clip(fade());
+    controller.add_clip(fade());
+    controller.play(0).unwrap();
+    controller.update(0.25);
+    assert_close(controller.get_weights().get(4).unwrap(), &[0.25, 0.75]);
+
+    // halfway between 0.75 into the first clip and 0.5 into the second
+    controller.crossfade_to(1, 1.0).unwrap();
+    controller.update(0.5);
+    assert_close(controller.get_weights().get(4).unwrap(), &[0.625, 0.375]);
+
+    controller.stop();
+    assert_close(controller.get_weights().get(4).unwrap(), &[0.5, 0.5]);
+}
@@ -0,0 +1,122 @@
+use animation::controller::AnimationController;
+use animation::palette::{joint_order, MatrixPalette};
+use animation::pose::Pose;
+use animation::skeleton::Skeleton;
+
+use math::mat4::{inverse, Mat4};
+use math::quaternion::Quat;
+use math::vec3::vec3;
+
+/// branching skeleton whose children are stored before their parents
+/// 4 is the root, 2 and 0 hang off it, 3 hangs off 2 and 1 off 3
+fn unsorted_skeleton() -> Skeleton {
+    let mut rest_pose = Pose::new();
+    rest_pose.resize(5);
+    rest_pose.parents = vec![4, 3, 4, 2, -1];
+    for (i, joint) in rest_pose.joints.iter_mut().enumerate() {
+        joint.translation = vec3(0.1 * i as f32, 1.0, -0.2);
+        joint.orientation = Quat::create(20.0 + 15.0 * i as f32, vec3(1.0, 0.5, i as f32));
+        joint.scaling = vec3(1.0, 1.0, 1.0) * (1.0 + 0.1 * i as f32);
+    }
+
+    let mut skeleton = Skeleton::new();
+    for i in 0..5 {
+        let bind = rest_pose.get_global_tranform(i).to_mat();
+        // leave one joint unskinned
+        skeleton
+            .inverse_bind_pose
+            .push((i != 1).then(|| inverse(&bind)));
+        skeleton.joint_names.push(format!("joint_{}", i));
+    }
+    skeleton.rest_pose = rest_pose;
+
+    skeleton
+}
+
+fn assert_close(a: &Mat4, b: &Mat4) {
+    for (a, b) in a.data.iter().flatten().zip(b.data.iter().flatten()) {
+        assert!((a - b).abs() < 1e-4, "{:?} != {:?}", a, b);
+    }
+}
+
+#[test]
+fn joint_order_puts_parents_first() {
+    let parents = [4, 3, 4, 2, -1];
+    let order = joint_order(&parents).unwrap();
+
+    assert_eq!(order.len(), parents.len());
+    for (position, joint) in order.iter().enumerate() {
+        let parent = parents[*joint];
+        if parent >= 0 {
+            let parent_position = order.iter().position(|j| *j == parent as usize);
+            assert!(parent_position.unwrap() < position);
+        }
+    }
+
+    assert!(joint_order(&[]).unwrap().is_empty());
+}
+
+#[test]
+fn invalid_parents_are_rejected() {
+    assert!(joint_order(&[-1, 5]).is_err());
+
+    // 1 and 2 are each other's parent, neither reaches a root
+    let error = joint_order(&[-1, 2, 1]).unwrap_err();
+    assert!(error.contains("cycle"), "{}", error);
+    assert!(joint_order(&[0]).is_err());
+
+    let mut skeleton = unsorted_skeleton();
+    skeleton.rest_pose.parents[4] = 1;
+    assert!(MatrixPalette::new(&skeleton).is_err());
+    assert!(AnimationController::try_new(skeleton).is_err());
+
+    let mut skeleton = unsorted_skeleton();
+    skeleton.inverse_bind_pose.pop();
+    assert!(MatrixPalette::new(&skeleton).is_err());
+}
+
+#[test]
+fn palette_matches_walking_up_the_hierarchy() {
+    let skeleton = unsorted_skeleton();
+    let mut pose = skeleton.rest_pose.clone();
+    pose.joints[2].orientation = Quat::create(-70.0, vec3(0.0, 0.0, 1.0));
+    pose.joints[4].translation = vec3(3.0, 0.0, 1.0);
+
+    let mut palette = MatrixPalette::new(&skeleton).unwrap();
+    let matrices = palette.update(&pose).to_vec();
+
+    for (i, matrix) in matrices.iter().enumerate() {
+        let global = pose.get_global_tranform(i).to_mat();
+        assert_close(&palette.get_globals()[i].to_mat(), &global);
+
+        let expected = match skeleton.inverse_bind_pose[i] {
+            Some(inverse_bind_pose) => global * inverse_bind_pose,
+            None => Mat4::IDENTITY,
+        };
+        assert_close(matrix, &expected);
+    }
+
+    let mut globals = Vec::new();
+    pose.get_global_transforms(&mut globals);
+    for (i, global) in globals.iter().enumerate() {
+        assert_close(&global.to_mat(), &palette.get_globals()[i].to_mat());
+        assert_close(&global.to_mat(), &pose.get_global_tranform(i).to_mat());
+    }
+}
+
+#[test]
+fn controller_reuses_its_palette() {
+    let mut controller = AnimationController::new(unsorted_skeleton());
+    let expected = controller.update_palette().to_vec();
+    let first = controller.update_palette().as_ptr();
+
+    let matrices = controller.update_palette();
+    assert_eq!(matrices.as_ptr(), first);
+    assert_eq!(matrices.len(), expected.len());
+
+    // the rest pose skins every vertex back onto itself
+    for (matrix, expected) in matrices.iter().zip(&expected) {
+        assert_close(matrix, expected);
+        assert_close(matrix, &Mat4::IDENTITY);
+    }
+}
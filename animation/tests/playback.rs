@@ -0,0 +1,121 @@
+mod common;
+
+use animation::clip::Clip;
+use animation::controller::AnimationController;
+use animation::curves::{Interpolation, LoopMode};
+use animation::frame::Frame;
+use animation::track::ScalarTrack;
+
+const EPSILON: f32 = 1e-4;
+
+/// one second clip linearly moving the root from 0 to 1 on y
+fn rise() -> Clip {
+    let mut clip = Clip::new();
+
+    let track = clip.get_transform_track(0);
+    track.position.interpolation = Interpolation::Linear;
+    for time in [0.0, 1.0] {
+        let mut frame = Frame::<3>::new();
+        frame.time = time;
+        frame.m_value = [0.0, time, 0.0];
+        track.position.frames.push(frame);
+    }
+    clip.re_calculate_duration();
+
+    clip
+}
+
+fn controller(clip: Clip) -> AnimationController {
+    let mut controller = AnimationController::new(common::skeleton());
+    controller.add_clip(clip);
+    controller.play(0).unwrap();
+
+    controller
+}
+
+fn height(controller: &AnimationController) -> f32 {
+    controller.get_pose().joints[0].translation.y
+}
+
+#[test]
+fn loop_modes_fit_time_into_range() {
+    assert!((LoopMode::Clamp.fit(1.5, 0.0, 1.0) - 1.0).abs() < EPSILON);
+    assert!((LoopMode::Loop.fit(-0.25, 0.0, 1.0) - 0.75).abs() < EPSILON);
+    assert!((LoopMode::PingPong.fit(1.25, 0.0, 1.0) - 0.75).abs() < EPSILON);
+    assert!((LoopMode::PingPong.fit(2.25, 0.0, 1.0) - 0.25).abs() < EPSILON);
+    assert!((LoopMode::PingPong.fit(-0.25, 0.0, 1.0) - 0.25).abs() < EPSILON);
+}
+
+#[test]
+fn frame_index_follows_the_loop_mode() {
+    let mut track = ScalarTrack::new();
+    for time in [0.0, 0.5, 1.0] {
+        let mut frame = Frame::<1>::new();
+        frame.time = time;
+        track.frames.push(frame);
+    }
+
+    assert_eq!(track.frame_index(1.2, LoopMode::Clamp), Ok(1));
+    assert_eq!(track.frame_index(1.2, LoopMode::Loop), Ok(0));
+    assert_eq!(track.frame_index(1.2, LoopMode::PingPong), Ok(1));
+    assert_eq!(track.frame_index(1.6, LoopMode::PingPong), Ok(0));
+
+    // landing exactly on the last frame still leaves a segment to interpolate
+    assert_eq!(track.frame_index(1.0, LoopMode::PingPong), Ok(1));
+}
+
+#[test]
+fn ping_pong_turns_around_at_the_ends() {
+    let mut clip = rise();
+    clip.set_loop_mode(LoopMode::PingPong);
+    let mut controller = controller(clip);
+
+    let mut heights = Vec::new();
+    for _ in 0..25 {
+        controller.update(0.1);
+        heights.push(height(&controller));
+    }
+
+    assert!((heights[4] - 0.5).abs() < EPSILON);
+    assert!((heights[14] - 0.5).abs() < EPSILON);
+    assert!(heights[14] < heights[13]);
+    assert!((heights[24] - 0.5).abs() < EPSILON);
+    assert!(heights[24] > heights[23]);
+}
+
+#[test]
+fn negative_rates_play_backwards() {
+    let mut clip = rise();
+    clip.set_looping(false);
+    clip.set_speed(-1.0);
+    let mut controller = controller(clip);
+
+    // clips playing backwards start from their end
+    controller.update(0.25);
+    assert!((height(&controller) - 0.75).abs() < EPSILON);
+
+    // the controller rate multiplies the clip rate
+    controller.set_speed(-2.0);
+    controller.update(0.1);
+    assert!((height(&controller) - 0.95).abs() < EPSILON);
+
+    controller.set_speed(0.5);
+    controller.update(10.0);
+    assert!(height(&controller).abs() < EPSILON);
+}
+
+#[test]
+fn ping_pong_events_fire_in_both_directions() {
+    let mut clip = rise();
+    clip.set_loop_mode(LoopMode::PingPong);
+    clip.add_event("step", 0.25);
+    clip.add_event("top", 1.0);
+
+    let names = clip
+        .events_between(0.0, 2.0, false)
+        .into_iter()
+        .map(|event| event.name.as_str())
+        .collect::<Vec<&str>>();
+
+    assert_eq!(names, vec!["step", "top", "step"]);
+}
@@ -0,0 +1,203 @@
+use animation::clip::Clip;
+use animation::curves::Interpolation;
+use animation::frame::Frame;
+use animation::pose::Pose;
+use animation::retarget::{NameMap, Retarget, Rig};
+use animation::skeleton::Skeleton;
+
+use math::quaternion::Quat;
+use math::transform::Transform;
+use math::vec3::vec3;
+
+const EPSILON: f32 = 1e-4;
+
+/// hips with a spine and a single leg, legs are `leg` long and hang straight down
+/// hips 0, spine 1, hip 2, knee 3, ankle 4
+fn rig(names: [&str; 5], leg: f32, twist: Quat) -> Skeleton {
+    let mut skeleton = Skeleton::new();
+    skeleton.joint_names = names.iter().map(|name| String::from(*name)).collect();
+    skeleton.inverse_bind_pose = vec![None; 5];
+
+    let pose = &mut skeleton.rest_pose;
+    pose.resize(5);
+    pose.parents = vec![-1, 0, 0, 2, 3];
+
+    pose.joints[0].translation = vec3(0.0, leg, 0.0);
+    pose.joints[1].translation = vec3(0.0, 0.5, 0.0);
+    pose.joints[3].translation = vec3(0.0, -leg * 0.5, 0.0);
+    pose.joints[4].translation = vec3(0.0, -leg * 0.5, 0.0);
+
+    // joints of some rigs point their local axes another way, the rest pose compensates
+    pose.joints[2].orientation = twist;
+    pose.joints[3].translation = twist.inverse() * pose.joints[3].translation;
+    pose.joints[3].orientation = twist.inverse();
+
+    skeleton
+}
+
+fn mixamo() -> Rig {
+    let names = ["Hips", "Spine", "LeftUpLeg", "LeftLeg", "LeftFoot"];
+    Rig::new(rig(names, 1.0, Quat::ZERO), NameMap::new())
+}
+
+fn alien(leg: f32, twist: Quat) -> Rig {
+    let names = ["Hips", "Abdomen", "UpperLeg.L", "LowerLeg.L", "Foot.L"];
+    let map = NameMap::parse(
+        "# alien to humanoid\n\
+         Abdomen = Spine\n\
+         UpperLeg.L = LeftUpLeg\n\
+         \n\
+         LowerLeg.L = LeftLeg\n\
+         Foot.L = LeftFoot\n",
+    )
+    .unwrap();
+
+    Rig::new(rig(names, leg, twist), map)
+}
+
+fn global(pose: &Pose, joint: usize) -> Transform {
+    pose.get_global_tranform(joint)
+}
+
+fn same_rotation(a: &Quat, b: &Quat) -> bool {
+    1.0 - a.dot(b).abs() < EPSILON
+}
+
+/// kick the leg forward and move the hips
+fn kick(rest: &Pose, amount: f32) -> Pose {
+    let mut pose = rest.clone();
+    pose.joints[0].translation = pose.joints[0].translation + vec3(0.3, -0.1, 0.5) * amount;
+    pose.joints[2].orientation =
+        Quat::create(-60.0 * amount, vec3(1.0, 0.0, 0.0)) * pose.joints[2].orientation;
+    pose.joints[3].orientation =
+        Quat::create(45.0 * amount, vec3(1.0, 0.0, 0.0)) * pose.joints[3].orientation;
+
+    pose
+}
+
+#[test]
+fn name_maps_parse_pairs() {
+    let map = alien(1.0, Quat::ZERO).names;
+    assert_eq!(map.len(), 4);
+    assert_eq!(map.get_shared("LowerLeg.L"), "LeftLeg");
+    assert_eq!(map.get_shared("Hips"), "Hips");
+
+    assert!(NameMap::parse("Hips = Hips\nSpine").is_err());
+    assert!(NameMap::parse("= Spine").is_err());
+}
+
+#[test]
+fn same_rig_gives_the_same_pose() {
+    let source = mixamo();
+    let mut retarget = Retarget::new(&source, &source).unwrap();
+    assert_eq!(retarget.translation_scale, 1.0);
+
+    let pose = kick(&source.skeleton.rest_pose, 1.0);
+    let mut out = Pose::new();
+    retarget.retarget_pose(&pose, &mut out);
+
+    for joint in 0..5 {
+        let (a, b) = (global(&pose, joint), global(&out, joint));
+        assert!((a.translation - b.translation).len() < EPSILON);
+        assert!(same_rotation(&a.orientation, &b.orientation));
+    }
+}
+
+#[test]
+fn rotations_are_relative_to_the_rest_pose() {
+    let source = mixamo();
+    let target = alien(1.0, Quat::create(90.0, vec3(0.0, 1.0, 0.0)));
+    let mut retarget = Retarget::new(&source, &target).unwrap();
+    assert_eq!(retarget.get_source(2), Some(2));
+
+    let pose = kick(&source.skeleton.rest_pose, 1.0);
+    let mut out = Pose::new();
+    retarget.retarget_pose(&pose, &mut out);
+
+    // the rest poses differ in local axes but the leg ends up in the same place
+    for joint in [3, 4] {
+        let (a, b) = (global(&pose, joint), global(&out, joint));
+        assert!(
+            (a.translation - b.translation).len() < EPSILON,
+            "{:?} {:?}",
+            a.translation,
+            b.translation
+        );
+    }
+    assert!(!same_rotation(
+        &out.joints[2].orientation,
+        &pose.joints[2].orientation
+    ));
+}
+
+#[test]
+fn root_translation_scales_with_leg_length() {
+    let source = mixamo();
+    let target = alien(2.0, Quat::ZERO);
+    let mut retarget = Retarget::new(&source, &target).unwrap();
+    assert!((retarget.translation_scale - 2.0).abs() < EPSILON);
+
+    let pose = kick(&source.skeleton.rest_pose, 1.0);
+    let mut out = Pose::new();
+    retarget.retarget_pose(&pose, &mut out);
+
+    let moved = global(&out, 0).translation - global(&target.skeleton.rest_pose, 0).translation;
+    assert!((moved - vec3(0.6, -0.2, 1.0)).len() < EPSILON);
+
+    // unrelated rigs can not be retargeted
+    let mut other = mixamo();
+    other.skeleton.joint_names = vec![String::from("bone"); 5];
+    assert!(Retarget::new(&source, &other).is_err());
+}
+
+#[test]
+fn retargeted_clips_sample_the_retargeted_pose() {
+    let source = mixamo();
+    let target = alien(1.5, Quat::create(30.0, vec3(0.0, 0.0, 1.0)));
+    let mut retarget = Retarget::new(&source, &target).unwrap();
+
+    // a clip blending from the rest pose into the kick over a second
+    let rest = &source.skeleton.rest_pose;
+    let mut clip = Clip::new();
+    clip.name = String::from("kick");
+    for (time, amount) in [(0.0, 0.0), (1.0, 1.0)] {
+        let pose = kick(rest, amount);
+        for joint in [0, 2, 3] {
+            let track = clip.get_transform_track(joint);
+            track.position.interpolation = Interpolation::Linear;
+            track.rotation.interpolation = Interpolation::Linear;
+
+            let mut position = Frame::<3>::new();
+            position.time = time;
+            position.m_value = pose.joints[joint as usize].translation.to_array();
+            track.position.frames.push(position);
+
+            let mut rotation = Frame::<4>::new();
+            rotation.time = time;
+            rotation.m_value = pose.joints[joint as usize].orientation.to_array();
+            track.rotation.frames.push(rotation);
+        }
+    }
+    clip.re_calculate_duration();
+    clip.add_event("contact", 0.5);
+
+    let mut retargeted = retarget.retarget_clip(&clip, 30.0).unwrap();
+    assert_eq!(retargeted.name, "kick");
+    assert_eq!(retargeted.get_events(), clip.get_events());
+    assert_eq!(retargeted.get_duration(), 1.0);
+
+    for time in [0.0, 0.5, 1.0] {
+        let mut pose = rest.clone();
+        clip.sample(&mut pose, time, &mut Vec::new());
+        let mut expected = Pose::new();
+        retarget.retarget_pose(&pose, &mut expected);
+
+        let mut sampled = target.skeleton.rest_pose.clone();
+        retargeted.sample(&mut sampled, time, &mut Vec::new());
+        for joint in 0..5 {
+            let (a, b) = (global(&expected, joint), global(&sampled, joint));
+            assert!((a.translation - b.translation).len() < EPSILON);
+            assert!(same_rotation(&a.orientation, &b.orientation));
+        }
+    }
+}
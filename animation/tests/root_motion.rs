@@ -0,0 +1,129 @@
+mod common;
+
+use animation::clip::Clip;
+use animation::controller::AnimationController;
+use animation::curves::Interpolation;
+use animation::frame::Frame;
+use animation::root_motion::{count_loops, extract_root};
+
+use math::quaternion::Quat;
+use math::transform::Transform;
+use math::vec3::vec3;
+
+const EPSILON: f32 = 1e-3;
+
+/// one second clip walking the root 2 units along z while bobbing up to 1 on y
+fn walk() -> Clip {
+    let mut clip = Clip::new();
+
+    let track = clip.get_transform_track(0);
+    track.position.interpolation = Interpolation::Linear;
+    for (time, y, z) in [(0.0, 0.0, 0.0), (0.5, 1.0, 1.0), (1.0, 0.0, 2.0)] {
+        let mut frame = Frame::<3>::new();
+        frame.time = time;
+        frame.m_value = [0.0, y, z];
+        track.position.frames.push(frame);
+    }
+    clip.re_calculate_duration();
+
+    clip
+}
+
+/// one second clip turning the root 90 degrees around y
+fn turn() -> Clip {
+    let mut clip = Clip::new();
+
+    let track = clip.get_transform_track(0);
+    track.rotation.interpolation = Interpolation::Linear;
+    for (time, angle) in [(0.0, 0.0), (0.5, 45.0), (1.0, 90.0)] {
+        let mut frame = Frame::<4>::new();
+        frame.time = time;
+        frame.m_value = Quat::create(angle, vec3(0.0, 1.0, 0.0)).to_array();
+        track.rotation.frames.push(frame);
+    }
+    clip.re_calculate_duration();
+
+    clip
+}
+
+fn controller(clip: Clip) -> AnimationController {
+    let mut controller = AnimationController::new(common::skeleton());
+    controller.add_clip(clip);
+    controller.set_root_motion(Some(0)).unwrap();
+    controller.play(0).unwrap();
+
+    controller
+}
+
+/// run the controller for `steps` updates of `delta_time` and return the accumulated placement
+fn run(controller: &mut AnimationController, steps: usize, delta_time: f32) -> Transform {
+    let mut placement = Transform::DEFAULT;
+    for _ in 0..steps {
+        controller.update(delta_time);
+        placement = placement.combine(controller.get_root_motion());
+    }
+
+    placement
+}
+
+#[test]
+fn horizontal_motion_moves_out_of_the_pose() {
+    let mut controller = controller(walk());
+
+    let placement = run(&mut controller, 5, 0.1);
+    assert!((placement.translation.z - 1.0).abs() < EPSILON);
+    assert!(placement.translation.y.abs() < EPSILON);
+
+    // the vertical bob stays in the pose, the forward motion does not
+    let root = controller.get_pose().joints[0].translation;
+    assert!((root.y - 1.0).abs() < EPSILON);
+    assert!(root.z.abs() < EPSILON);
+}
+
+#[test]
+fn looping_does_not_snap_back() {
+    let mut controller = controller(walk());
+
+    let placement = run(&mut controller, 15, 0.1);
+    assert!((placement.translation.z - 3.0).abs() < EPSILON);
+}
+
+#[test]
+fn yaw_is_extracted_and_accumulates() {
+    let mut controller = controller(turn());
+
+    let placement = run(&mut controller, 20, 0.1);
+    assert!((placement.orientation.angle() - 180.0).abs() < 0.1);
+    assert!(controller.get_pose().joints[0].orientation.angle() < 0.1);
+}
+
+#[test]
+fn nothing_is_extracted_when_disabled() {
+    let mut controller = controller(walk());
+    controller.set_root_motion(None).unwrap();
+
+    let placement = run(&mut controller, 5, 0.1);
+    assert!(placement.translation.len() < EPSILON);
+    assert!((controller.get_pose().joints[0].translation.z - 1.0).abs() < EPSILON);
+}
+
+#[test]
+fn root_keeps_pitch_and_height() {
+    let transform = Transform {
+        translation: vec3(1.0, 2.0, 3.0),
+        orientation: Quat::create(30.0, vec3(0.0, 1.0, 0.0))
+            * Quat::create(20.0, vec3(1.0, 0.0, 0.0)),
+        scaling: vec3(1.0, 1.0, 1.0),
+    };
+
+    let root = extract_root(&transform);
+    assert!((root.translation - vec3(1.0, 0.0, 3.0)).len() < EPSILON);
+    assert!((root.orientation.angle() - 30.0).abs() < 0.1);
+
+    let mut clip = walk();
+    assert_eq!(count_loops(&clip, 0.9, 1.1), 1);
+    assert_eq!(count_loops(&clip, 0.1, -0.1), -1);
+    assert_eq!(count_loops(&clip, 0.2, 2.5), 2);
+    clip.set_looping(false);
+    assert_eq!(count_loops(&clip, 0.9, 1.1), 0);
+}
@@ -0,0 +1,318 @@
+use animation::controller::AnimationController;
+use animation::pose::Pose;
+use animation::skeleton::Skeleton;
+use animation::skinning::{
+    skin_dual_quat, skin_normal, skin_normal_dual_quat, skin_position, skin_position_dual_quat,
+};
+
+use math::dual_quat::DualQuat;
+use math::mat4::{inverse, scale, translate, Mat4};
+use math::quaternion::Quat;
+use math::transform::Transform;
+use math::vec3::vec3;
+
+const EPSILON: f32 = 1e-5;
+
+fn assert_close(a: [f32; 3], b: [f32; 3]) {
+    for i in 0..3 {
+        assert!((a[i] - b[i]).abs() < EPSILON, "{:?} != {:?}", a, b);
+    }
+}
+
+#[test]
+fn identity_palette_leaves_vertices_untouched() {
+    let palette = vec![Mat4::IDENTITY; 2];
+    let position = [1.0, 2.0, 3.0];
+    let normal = [0.0, 1.0, 0.0];
+
+    let joints = [0, 1, 0, 0];
+    let weights = [0.25, 0.75, 0.0, 0.0];
+
+    assert_close(
+        skin_position(&palette, &position, &joints, &weights),
+        position,
+    );
+    assert_close(skin_normal(&palette, &normal, &joints, &weights), normal);
+}
+
+#[test]
+fn single_joint_translation() {
+    let palette = vec![Mat4::IDENTITY, translate(&vec3(0.0, 5.0, 0.0))];
+
+    let skinned = skin_position(
+        &palette,
+        &[1.0, 0.0, 0.0],
+        &[1, 0, 0, 0],
+        &[1.0, 0.0, 0.0, 0.0],
+    );
+    assert_close(skinned, [1.0, 5.0, 0.0]);
+
+    // translation does not affect normals
+    let normal = skin_normal(
+        &palette,
+        &[1.0, 0.0, 0.0],
+        &[1, 0, 0, 0],
+        &[1.0, 0.0, 0.0, 0.0],
+    );
+    assert_close(normal, [1.0, 0.0, 0.0]);
+}
+
+#[test]
+fn weighted_blend_of_two_joints() {
+    let palette = vec![
+        translate(&vec3(2.0, 0.0, 0.0)),
+        translate(&vec3(0.0, 2.0, 0.0)),
+    ];
+
+    let skinned = skin_position(
+        &palette,
+        &[0.0, 0.0, 0.0],
+        &[0, 1, 0, 0],
+        &[0.5, 0.5, 0.0, 0.0],
+    );
+    assert_close(skinned, [1.0, 1.0, 0.0]);
+
+    let skinned = skin_position(
+        &palette,
+        &[0.0, 0.0, 0.0],
+        &[0, 1, 0, 0],
+        &[0.25, 0.75, 0.0, 0.0],
+    );
+    assert_close(skinned, [0.5, 1.5, 0.0]);
+}
+
+#[test]
+fn rotation_moves_positions_and_normals() {
+    let rotation = Quat::create(90.0, vec3(0.0, 0.0, 1.0)).to_mat();
+    let palette = vec![rotation];
+
+    let joints = [0, 0, 0, 0];
+    let weights = [1.0, 0.0, 0.0, 0.0];
+
+    assert_close(
+        skin_position(&palette, &[1.0, 0.0, 0.0], &joints, &weights),
+        [0.0, 1.0, 0.0],
+    );
+    assert_close(
+        skin_normal(&palette, &[1.0, 0.0, 0.0], &joints, &weights),
+        [0.0, 1.0, 0.0],
+    );
+}
+
+#[test]
+fn non_uniform_scale_keeps_normals_perpendicular() {
+    let palette = vec![scale(&vec3(2.0, 1.0, 1.0))];
+
+    let joints = [0, 0, 0, 0];
+    let weights = [1.0, 0.0, 0.0, 0.0];
+
+    // surface along the (1, -1) direction, its normal is (1, 1)
+    let half = f32::sqrt(0.5);
+    let normal = skin_normal(&palette, &[half, half, 0.0], &joints, &weights);
+
+    let expected = vec3(0.5, 1.0, 0.0).unit().to_array();
+    assert_close(normal, expected);
+
+    let surface = skin_position(&palette, &[1.0, -1.0, 0.0], &joints, &weights);
+    let dot = surface[0] * normal[0] + surface[1] * normal[1] + surface[2] * normal[2];
+    assert!(dot.abs() < EPSILON);
+}
+
+#[test]
+fn unweighted_and_out_of_range_joints_are_ignored() {
+    let palette = vec![translate(&vec3(1.0, 0.0, 0.0))];
+    let position = [0.0, 0.0, 0.0];
+
+    let skinned = skin_position(&palette, &position, &[0, 0, 0, 0], &[0.0, 0.0, 0.0, 0.0]);
+    assert_close(skinned, position);
+
+    let skinned = skin_position(&palette, &position, &[7, 0, 0, 0], &[1.0, 0.0, 0.0, 0.0]);
+    assert_close(skinned, position);
+}
+
+/// two joint chain along the y axis, the second joint sits 1 unit above the first
+fn chain_skeleton() -> Skeleton {
+    let mut rest_pose = Pose::new();
+    rest_pose.resize(2);
+    rest_pose.parents[1] = 0;
+    rest_pose.joints[1].translation = vec3(0.0, 1.0, 0.0);
+
+    let mut skeleton = Skeleton::new();
+    for i in 0..2 {
+        let bind = rest_pose.get_global_tranform(i).to_mat();
+        skeleton.inverse_bind_pose.push(Some(inverse(&bind)));
+        skeleton.joint_names.push(format!("joint_{}", i));
+    }
+    skeleton.rest_pose = rest_pose;
+
+    skeleton
+}
+
+#[test]
+fn rest_pose_palette_matches_bind_pose() {
+    let mut controller = AnimationController::new(chain_skeleton());
+    let palette = controller.update_palette();
+
+    let position = [0.0, 1.5, 0.0];
+    let skinned = skin_position(palette, &position, &[0, 1, 0, 0], &[0.5, 0.5, 0.0, 0.0]);
+    assert_close(skinned, position);
+}
+
+#[test]
+fn posed_chain_matches_known_result() {
+    let mut skeleton = chain_skeleton();
+
+    // bend the root joint 90 degrees around z, the tip of the chain now points down -x
+    let mut pose = skeleton.rest_pose.clone();
+    pose.joints[0] = Transform::new(
+        vec3(1.0, 1.0, 1.0),
+        vec3(0.0, 0.0, 0.0),
+        Quat::create(90.0, vec3(0.0, 0.0, 1.0)),
+    );
+
+    let palette = (0..2)
+        .map(|i| pose.get_global_tranform(i).to_mat() * skeleton.inverse_bind_pose[i].unwrap())
+        .collect::<Vec<Mat4>>();
+
+    // fully bound to the second joint
+    let skinned = skin_position(
+        &palette,
+        &[0.0, 2.0, 0.0],
+        &[1, 0, 0, 0],
+        &[1.0, 0.0, 0.0, 0.0],
+    );
+    assert_close(skinned, [-2.0, 0.0, 0.0]);
+
+    // the whole chain rotates rigidly with the root so blending keeps the same result
+    skeleton.rest_pose = pose;
+    let mut controller = AnimationController::new(skeleton);
+    let palette = controller.update_palette();
+    let skinned = skin_position(
+        palette,
+        &[0.0, 1.0, 0.0],
+        &[0, 1, 0, 0],
+        &[0.5, 0.5, 0.0, 0.0],
+    );
+    assert_close(skinned, [-1.0, 0.0, 0.0]);
+}
+
+#[test]
+fn dual_quats_match_rigid_matrices() {
+    let a = Transform::new(
+        vec3(1.0, 1.0, 1.0),
+        vec3(1.0, -2.0, 0.5),
+        Quat::create(70.0, vec3(1.0, 2.0, -1.0)),
+    );
+    let b = Transform::new(
+        vec3(1.0, 1.0, 1.0),
+        vec3(0.0, 3.0, -1.0),
+        Quat::create(-135.0, vec3(0.0, 1.0, 1.0)),
+    );
+    let point = vec3(0.3, -1.2, 2.0);
+
+    let dual_quat = DualQuat::from_transform(&a) * DualQuat::from_transform(&b);
+    let matrix = a.to_mat() * b.to_mat();
+    assert_close(
+        dual_quat.transform_point(&point).to_array(),
+        matrix.transform_point(&point).to_array(),
+    );
+
+    // scaling in a matrix is dropped, the rigid part survives
+    let scaled = DualQuat::from_mat(&(matrix * scale(&vec3(2.0, 2.0, 2.0))));
+    assert_close(
+        scaled.transform_point(&point).to_array(),
+        matrix.transform_point(&point).to_array(),
+    );
+    assert_close(
+        scaled.get_translation().to_array(),
+        dual_quat.get_translation().to_array(),
+    );
+}
+
+#[test]
+fn dual_quat_skinning_matches_linear_blend_for_one_joint() {
+    let transform = Transform::new(
+        vec3(1.0, 1.0, 1.0),
+        vec3(2.0, 0.0, -1.0),
+        Quat::create(40.0, vec3(0.0, 0.0, 1.0)),
+    );
+    let matrices = vec![Mat4::IDENTITY, transform.to_mat()];
+    let dual_quats = vec![DualQuat::IDENTITY, DualQuat::from_transform(&transform)];
+    let position = [1.0, 2.0, 3.0];
+    let normal = [1.0, 0.0, 0.0];
+
+    let joints = [1, 0, 0, 0];
+    let weights = [1.0, 0.0, 0.0, 0.0];
+    assert_close(
+        skin_position_dual_quat(&dual_quats, &position, &joints, &weights),
+        skin_position(&matrices, &position, &joints, &weights),
+    );
+    assert_close(
+        skin_normal_dual_quat(&dual_quats, &normal, &joints, &weights),
+        skin_normal(&matrices, &normal, &joints, &weights),
+    );
+
+    // no influences, unknown joints and opposite signs of the same rotation
+    assert!(skin_dual_quat(&dual_quats, &[5, -1, 0, 0], &[1.0, 1.0, 0.0, 0.0]).is_none());
+    let flipped = vec![dual_quats[1], -dual_quats[1]];
+    assert_close(
+        skin_position_dual_quat(&flipped, &position, &[0, 1, 0, 0], &[0.5, 0.5, 0.0, 0.0]),
+        skin_position(&matrices, &position, &joints, &weights),
+    );
+}
+
+#[test]
+fn dual_quat_skinning_keeps_twisted_joints_from_collapsing() {
+    // the second joint twists half a turn around the x axis the arm runs along
+    let twist = Transform::new(
+        vec3(1.0, 1.0, 1.0),
+        vec3(0.0, 0.0, 0.0),
+        Quat::create(180.0, vec3(1.0, 0.0, 0.0)),
+    );
+    let matrices = vec![Mat4::IDENTITY, twist.to_mat()];
+    let dual_quats = vec![DualQuat::IDENTITY, DualQuat::from_transform(&twist)];
+
+    let position = [1.0, 0.5, 0.0];
+    let joints = [0, 1, 0, 0];
+    let weights = [0.5, 0.5, 0.0, 0.0];
+
+    // the matrices average to a flat one and the vertex ends up on the axis
+    let linear = skin_position(&matrices, &position, &joints, &weights);
+    assert_close(linear, [1.0, 0.0, 0.0]);
+
+    // the blended rotation is a quarter turn and keeps the distance to the axis
+    let dual_quat = skin_position_dual_quat(&dual_quats, &position, &joints, &weights);
+    let radius = (dual_quat[1] * dual_quat[1] + dual_quat[2] * dual_quat[2]).sqrt();
+    assert!((radius - 0.5).abs() < EPSILON, "{:?}", dual_quat);
+    assert!((dual_quat[0] - 1.0).abs() < EPSILON);
+}
+
+#[test]
+fn dual_quat_palette_matches_matrix_palette() {
+    let mut skeleton = chain_skeleton();
+    let mut pose = skeleton.rest_pose.clone();
+    pose.joints[0].orientation = Quat::create(60.0, vec3(0.0, 0.0, 1.0));
+    pose.joints[1].orientation = Quat::create(-30.0, vec3(1.0, 0.0, 0.0));
+    // nothing plays so the controller skins the rest pose
+    skeleton.rest_pose = pose.clone();
+    let mut controller = AnimationController::new(skeleton);
+
+    let matrices = controller.update_palette().to_vec();
+    let dual_quats = controller.update_dual_quat_palette().to_vec();
+    let point = vec3(0.5, 1.5, -0.25);
+    for i in 0..2 {
+        assert_close(
+            dual_quats[i].transform_point(&point).to_array(),
+            matrices[i].transform_point(&point).to_array(),
+        );
+        assert_close(
+            DualQuat::from_transform(&pose.get_global_tranform(i))
+                .transform_point(&point)
+                .to_array(),
+            pose.get_global_tranform(i)
+                .to_mat()
+                .transform_point(&point)
+                .to_array(),
+        );
+    }
+}
@@ -0,0 +1,192 @@
+mod common;
+
+use animation::clip::Clip;
+use animation::curves::Interpolation;
+use animation::frame::Frame;
+use animation::state_machine::{AnimationStateMachine, Parameter, StateEvent, StateMachineDesc};
+
+const DESC: &str = r#"{
+    "parameters": {
+        "speed": { "float": 0.0 },
+        "grounded": { "bool": true },
+        "jump": { "trigger": false }
+    },
+    "states": [
+        { "name": "idle", "clip": "Idle" },
+        { "name": "walk", "clip": "Walk", "speed": 2.0 },
+        { "name": "jump", "clip": "Jump", "looping": false }
+    ],
+    "transitions": [
+        { "from": "idle", "to": "walk", "duration": 0.5,
+          "conditions": [{ "op": "greater", "parameter": "speed", "value": 0.1 }] },
+        { "from": "walk", "to": "idle", "duration": 0.5,
+          "conditions": [{ "op": "less", "parameter": "speed", "value": 0.1 }] },
+        { "to": "jump", "conditions": [
+            { "op": "triggered", "parameter": "jump" },
+            { "op": "true", "parameter": "grounded" }
+        ] },
+        { "from": "jump", "to": "idle", "exit_time": 1.0 }
+    ]
+}"#;
+
+/// one second clip moving the root linearly from 0 to `height` on y
+fn clip(name: &str, height: f32) -> Clip {
+    let mut clip = Clip::new();
+    clip.name = String::from(name);
+
+    let track = clip.get_transform_track(0);
+    track.position.interpolation = Interpolation::Linear;
+    for (time, y) in [(0.0, 0.0), (1.0, height)] {
+        let mut frame = Frame::<3>::new();
+        frame.time = time;
+        frame.m_value = [0.0, y, 0.0];
+        track.position.frames.push(frame);
+    }
+    clip.re_calculate_duration();
+
+    clip
+}
+
+fn state_machine() -> AnimationStateMachine {
+    let desc = StateMachineDesc::from_json(DESC).unwrap();
+    let clips = vec![clip("Idle", 1.0), clip("Walk", 2.0), clip("Jump", 3.0)];
+
+    AnimationStateMachine::new(&desc, common::skeleton(), &clips).unwrap()
+}
+
+#[test]
+fn starts_in_the_first_state() {
+    let mut machine = state_machine();
+
+    assert_eq!(machine.current_state_name(), "idle");
+    assert_eq!(
+        machine.drain_events(),
+        vec![StateEvent::Entered(String::from("idle"))]
+    );
+    assert!(machine.drain_events().is_empty());
+}
+
+#[test]
+fn float_condition_changes_state() {
+    let mut machine = state_machine();
+    machine.drain_events();
+
+    machine.update(0.1);
+    assert_eq!(machine.current_state_name(), "idle");
+
+    machine.set_float("speed", 1.0).unwrap();
+    machine.update(0.1);
+    assert_eq!(machine.current_state_name(), "walk");
+    assert_eq!(
+        machine.drain_events(),
+        vec![
+            StateEvent::Exited(String::from("idle")),
+            StateEvent::Entered(String::from("walk")),
+        ]
+    );
+    assert_eq!(machine.get_controller().get_fade_targets().len(), 1);
+
+    // the walk state plays at twice the speed
+    assert!((machine.get_normalized_time() - 0.2).abs() < 1e-5);
+}
+
+#[test]
+fn triggers_are_consumed_and_exit_time_is_respected() {
+    let mut machine = state_machine();
+
+    machine.set_trigger("jump").unwrap();
+    machine.update(0.25);
+    assert_eq!(machine.current_state_name(), "jump");
+    assert_eq!(
+        machine.get_parameter("jump"),
+        Some(Parameter::Trigger(false))
+    );
+
+    // the jump clip lasts a second and only returns to idle once it is done
+    machine.update(0.5);
+    assert_eq!(machine.current_state_name(), "jump");
+    machine.update(0.5);
+    assert_eq!(machine.current_state_name(), "jump");
+    machine.update(0.1);
+    assert_eq!(machine.current_state_name(), "idle");
+}
+
+#[test]
+fn any_state_transitions_need_every_condition() {
+    let mut machine = state_machine();
+
+    machine.set_bool("grounded", false).unwrap();
+    machine.set_trigger("jump").unwrap();
+    machine.update(0.1);
+    assert_eq!(machine.current_state_name(), "idle");
+
+    machine.set_bool("grounded", true).unwrap();
+    machine.update(0.1);
+    assert_eq!(machine.current_state_name(), "jump");
+}
+
+#[test]
+fn self_transitions_restart_the_state() {
+    let desc = StateMachineDesc::from_json(
+        r#"{
+            "parameters": { "attack": { "trigger": false } },
+            "states": [{ "name": "attack", "clip": "Jump", "speed": 2.0, "looping": false }],
+            "transitions": [{ "from": "attack", "to": "attack", "duration": 0.2,
+                "conditions": [{ "op": "triggered", "parameter": "attack" }] }]
+        }"#,
+    )
+    .unwrap();
+    let clips = vec![clip("Jump", 3.0)];
+    let mut machine = AnimationStateMachine::new(&desc, common::skeleton(), &clips).unwrap();
+
+    machine.update(0.3);
+    machine.drain_events();
+
+    machine.set_trigger("attack").unwrap();
+    machine.update(0.1);
+    assert_eq!(
+        machine.drain_events(),
+        vec![
+            StateEvent::Exited(String::from("attack")),
+            StateEvent::Entered(String::from("attack")),
+        ]
+    );
+    assert_eq!(machine.get_controller().get_fade_targets().len(), 1);
+
+    // the clip fades in again from its start at twice the speed instead of holding its last frame
+    machine.update(0.1);
+    machine.update(0.1);
+    assert!(machine.get_controller().get_fade_targets().is_empty());
+    assert!((machine.get_normalized_time() - 0.6).abs() < 1e-5);
+    let y = machine.get_controller().get_pose().joints[0].translation.y;
+    assert!((y - 1.8).abs() < 1e-4);
+}
+
+#[test]
+fn invalid_definitions_are_rejected() {
+    let clips = vec![clip("Idle", 1.0)];
+
+    let desc = StateMachineDesc::from_json(r#"{ "states": [{ "name": "idle", "clip": "Run" }] }"#)
+        .unwrap();
+    assert!(AnimationStateMachine::new(&desc, common::skeleton(), &clips).is_err());
+
+    let desc = StateMachineDesc::from_json(
+        r#"{
+            "parameters": { "speed": { "float": 0.0 } },
+            "states": [{ "name": "idle", "clip": "Idle" }],
+            "transitions": [{ "to": "idle", "conditions": [{ "op": "true", "parameter": "speed" }] }]
+        }"#,
+    )
+    .unwrap();
+    assert!(AnimationStateMachine::new(&desc, common::skeleton(), &clips).is_err());
+
+    assert!(StateMachineDesc::from_json("{ \"states\": 3 }").is_err());
+}
+
+#[test]
+fn definitions_round_trip_through_json() {
+    let desc = StateMachineDesc::from_json(DESC).unwrap();
+    let json = desc.to_json().unwrap();
+
+    assert_eq!(StateMachineDesc::from_json(&json).unwrap(), desc);
+}
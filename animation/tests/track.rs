@@ -0,0 +1,189 @@
+mod common;
+
+use animation::clip::{Clip, SampleError};
+use animation::controller::{AnimationController, AnimationError};
+use animation::curves::{Interpolation, LoopMode};
+use animation::frame::Frame;
+use animation::pose::Pose;
+use animation::track::{ScalarTrack, TrackError};
+use animation::track_transform::TransformTrack;
+
+use math::transform::Transform;
+use math::vec3::{vec3, Vec3};
+
+/// track with unevenly spaced frames whose value is their time
+fn uneven_track() -> ScalarTrack {
+    let mut track = ScalarTrack::new();
+    track.interpolation = Interpolation::Linear;
+
+    let mut time = 0.0;
+    for i in 0..200 {
+        let mut frame = Frame::<1>::new();
+        frame.time = time;
+        frame.m_value = [time];
+        track.frames.push(frame);
+
+        // alternate short and long gaps, some shorter than a lookup sample
+        time += if i % 3 == 0 { 0.001 } else { 0.05 };
+    }
+
+    track
+}
+
+/// the segment containing `time` found by scanning every frame
+fn linear_scan(track: &ScalarTrack, time: f32) -> usize {
+    let len = track.frames.len();
+    (0..len - 1)
+        .rev()
+        .find(|i| time >= track.frames[*i].time)
+        .unwrap_or(0)
+}
+
+fn times(track: &ScalarTrack) -> Vec<f32> {
+    let end = track.get_end_time();
+    (0..=1000).map(|i| end * i as f32 / 1000.0).collect()
+}
+
+#[test]
+fn binary_search_matches_a_linear_scan() {
+    let mut track = uneven_track();
+
+    for time in times(&track) {
+        assert_eq!(
+            track.frame_index(time, LoopMode::Clamp),
+            Ok(linear_scan(&track, time))
+        );
+    }
+
+    // a coarse lookup table still gives the right frame
+    track.build_lookup(10.0);
+    for time in times(&track) {
+        assert_eq!(
+            track.frame_index(time, LoopMode::Clamp),
+            Ok(linear_scan(&track, time))
+        );
+    }
+
+    for frame in &track.frames {
+        assert_eq!(
+            track.frame_index(frame.time, LoopMode::Clamp),
+            Ok(linear_scan(&track, frame.time))
+        );
+    }
+}
+
+#[test]
+fn cached_sampling_matches_fresh_sampling() {
+    let mut cached = uneven_track();
+    cached.build_lookup(60.0);
+    let mut forward = times(&cached);
+    let backward = forward.iter().rev().copied().collect::<Vec<f32>>();
+    // jump around as well
+    forward.extend([0.3, 9.0, 0.0, 4.2]);
+
+    for time in forward.into_iter().chain(backward) {
+        let value = cached.try_sample::<f32>(time, LoopMode::Loop).unwrap();
+        let fresh = uneven_track()
+            .try_sample::<f32>(time, LoopMode::Loop)
+            .unwrap();
+
+        assert!(
+            (value - fresh).abs() < 1e-5,
+            "{} != {} at {}",
+            value,
+            fresh,
+            time
+        );
+    }
+}
+
+/// position channel going through (time, x) keys
+fn position_track(id: u32, keys: &[(f32, f32)]) -> TransformTrack {
+    let mut track = TransformTrack::new();
+    track.id = id;
+    track.position.interpolation = Interpolation::Linear;
+    for (time, x) in keys {
+        let mut frame = Frame::<3>::new();
+        frame.time = *time;
+        frame.m_value = [*x, 0.0, 0.0];
+        track.position.frames.push(frame);
+    }
+
+    track
+}
+
+#[test]
+fn single_frame_channels_hold_their_value() {
+    let mut track = position_track(0, &[(0.5, 2.0)]);
+    let mut scaling = Frame::<3>::new();
+    scaling.m_value = [3.0, 3.0, 3.0];
+    track.scaling.frames.push(scaling);
+
+    let sampled = track.sample(&Transform::DEFAULT, 4.0, LoopMode::Loop).ok();
+    assert_eq!(
+        sampled.map(|joint| joint.translation),
+        Some(vec3(2.0, 0.0, 0.0))
+    );
+    assert_eq!(
+        sampled.map(|joint| joint.scaling),
+        Some(vec3(3.0, 3.0, 3.0))
+    );
+
+    // a clip of constant channels has no duration but still poses its joints
+    let mut clip = Clip::new();
+    clip.tracks.push(track);
+    clip.re_calculate_duration();
+    assert_eq!(clip.get_duration(), 0.0);
+
+    let mut pose = Pose::new();
+    pose.resize(1);
+    let mut errors = Vec::new();
+    clip.sample(&mut pose, 1.0, &mut errors);
+    assert!(errors.is_empty());
+    assert_eq!(pose.joints[0].translation, vec3(2.0, 0.0, 0.0));
+}
+
+#[test]
+fn broken_tracks_leave_their_joint_alone() {
+    let mut broken = position_track(1, &[(0.0, 5.0), (f32::NAN, 5.0)]);
+    assert_eq!(
+        broken
+            .sample(&Transform::DEFAULT, 0.5, LoopMode::Loop)
+            .err(),
+        Some(TrackError::InvalidFrameTime(1))
+    );
+    assert_eq!(broken.validate(), Err(TrackError::InvalidFrameTime(1)));
+
+    let mut clip = Clip::new();
+    clip.tracks
+        .push(position_track(0, &[(0.0, 0.0), (1.0, 1.0)]));
+    clip.tracks.push(broken);
+    clip.re_calculate_duration();
+
+    let mut pose = Pose::new();
+    pose.resize(2);
+    pose.joints[1].translation = vec3(0.0, 7.0, 0.0);
+    let mut errors = Vec::new();
+    clip.sample(&mut pose, 0.5, &mut errors);
+    assert_eq!(pose.joints[0].translation, vec3(0.5, 0.0, 0.0));
+    assert_eq!(pose.joints[1].translation, vec3(0.0, 7.0, 0.0));
+    assert_eq!(
+        errors,
+        [SampleError::Joint(1, TrackError::InvalidFrameTime(1))]
+    );
+
+    // the controller reports each broken track once until its errors are drained
+    let mut controller =
+        AnimationController::new(common::skeleton_of(&[("root", -1), ("child", 0)]));
+    controller.add_clip(clip);
+    controller.play(0).unwrap();
+    controller.update(0.25);
+    controller.update(0.25);
+    let error = AnimationError {
+        clip_index: 0,
+        error: SampleError::Joint(1, TrackError::InvalidFrameTime(1)),
+    };
+    assert_eq!(controller.drain_errors(), [error]);
+    assert!(controller.drain_errors().is_empty());
+    assert_eq!(controller.get_pose().joints[1].translation, Vec3::ZERO);
+}
@@ -0,0 +1,340 @@
+// property tests for track sampling, random tracks are keyed from analytic curves and sampling
+// has to give the curve back. every case comes from a fixed seed so failures reproduce
+
+use animation::curves::{Interpolation, LoopMode};
+use animation::frame::Frame;
+use animation::track::{ScalarTrack, TrackError, VectorTrack};
+
+use math::vec3::{vec3, Vec3};
+
+const CASES: usize = 200;
+const SAMPLES: usize = 50;
+
+/// xorshift, good enough to spread test cases around
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// uniform in min..max
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        let unit = (self.next() >> 40) as f32 / (1u64 << 24) as f32;
+        min + (max - min) * unit
+    }
+
+    fn count(&mut self, min: usize, max: usize) -> usize {
+        min + (self.next() % (max - min + 1) as u64) as usize
+    }
+}
+
+/// run `property` on CASES seeded generators, the failing case number is part of the message
+fn check(property: impl Fn(&mut Rng, usize)) {
+    for case in 0..CASES {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15 ^ (case as u64 + 1).wrapping_mul(0x2545_f491));
+        property(&mut rng, case);
+    }
+}
+
+/// sorted random key times starting at a random offset
+fn key_times(rng: &mut Rng) -> Vec<f32> {
+    let mut time = rng.range(-2.0, 2.0);
+    (0..rng.count(2, 20))
+        .map(|_| {
+            let key = time;
+            time += rng.range(0.01, 0.5);
+            key
+        })
+        .collect()
+}
+
+/// cubic polynomial and its derivative
+#[derive(Clone, Copy, Debug)]
+struct Cubic([f32; 4]);
+
+impl Cubic {
+    fn random(rng: &mut Rng) -> Self {
+        Self([
+            rng.range(-2.0, 2.0),
+            rng.range(-2.0, 2.0),
+            rng.range(-1.0, 1.0),
+            rng.range(-0.5, 0.5),
+        ])
+    }
+
+    fn value(&self, t: f32) -> f32 {
+        let [a, b, c, d] = self.0;
+        a + t * (b + t * (c + t * d))
+    }
+
+    fn slope(&self, t: f32) -> f32 {
+        let [_, b, c, d] = self.0;
+        b + t * (2.0 * c + t * 3.0 * d)
+    }
+}
+
+fn scalar_track(times: &[f32], curve: Cubic, interpolation: Interpolation) -> ScalarTrack {
+    let mut track = ScalarTrack::new();
+    track.interpolation = interpolation;
+    for time in times {
+        let mut frame = Frame::<1>::new();
+        frame.time = *time;
+        frame.m_value = [curve.value(*time)];
+        frame.m_in = [curve.slope(*time)];
+        frame.m_out = [curve.slope(*time)];
+        track.frames.push(frame);
+    }
+
+    track
+}
+
+fn sample_times(rng: &mut Rng, start: f32, end: f32) -> Vec<f32> {
+    (0..SAMPLES).map(|_| rng.range(start, end)).collect()
+}
+
+fn close(a: f32, b: f32, epsilon: f32) -> bool {
+    (a - b).abs() <= epsilon * (1.0 + a.abs().max(b.abs()))
+}
+
+#[test]
+fn cubic_tracks_reproduce_cubic_curves() {
+    // hermite segments with the curve's own tangents are the curve itself
+    check(|rng, case| {
+        let times = key_times(rng);
+        let curve = Cubic::random(rng);
+        let mut track = scalar_track(&times, curve, Interpolation::Cubic);
+
+        for time in sample_times(rng, times[0], *times.last().unwrap()) {
+            let sampled = track.try_sample::<f32>(time, LoopMode::Clamp).unwrap();
+            assert!(
+                close(sampled, curve.value(time), 1e-4),
+                "case {}: {} at {} instead of {} for {:?}",
+                case,
+                sampled,
+                time,
+                curve.value(time),
+                curve
+            );
+        }
+    });
+}
+
+#[test]
+fn linear_tracks_reproduce_lines() {
+    check(|rng, case| {
+        let times = key_times(rng);
+        let (a, b) = (rng.range(-3.0, 3.0), rng.range(-3.0, 3.0));
+        let origin = vec3(rng.range(-1.0, 1.0), rng.range(-1.0, 1.0), 0.0);
+        let line = |t: f32| origin + vec3(a, b, a - b) * t;
+
+        let mut track = VectorTrack::new();
+        track.interpolation = Interpolation::Linear;
+        for time in &times {
+            let mut frame = Frame::<3>::new();
+            frame.time = *time;
+            frame.m_value = line(*time).to_array();
+            track.frames.push(frame);
+        }
+
+        for time in sample_times(rng, times[0], *times.last().unwrap()) {
+            let sampled = track.try_sample::<Vec3>(time, LoopMode::Clamp).unwrap();
+            assert!(
+                (sampled - line(time)).len() < 1e-4,
+                "case {}: {:?} at {}",
+                case,
+                sampled,
+                time
+            );
+        }
+    });
+}
+
+#[test]
+fn constant_tracks_hold_the_previous_key() {
+    check(|rng, case| {
+        let times = key_times(rng);
+        let mut track = scalar_track(&times, Cubic::random(rng), Interpolation::Constant);
+        let last_segment = times.len() - 2;
+
+        for time in sample_times(rng, times[0], *times.last().unwrap()) {
+            let key = (0..=last_segment)
+                .rev()
+                .find(|i| times[*i] <= time)
+                .unwrap();
+            assert_eq!(
+                track.try_sample::<f32>(time, LoopMode::Clamp).unwrap(),
+                track.frames[key].m_value[0],
+                "case {} at {}",
+                case,
+                time
+            );
+        }
+    });
+}
+
+#[test]
+fn loop_modes_repeat_the_track() {
+    check(|rng, case| {
+        let times = key_times(rng);
+        let mut track = scalar_track(&times, Cubic::random(rng), Interpolation::Cubic);
+        let (start, end) = (times[0], *times.last().unwrap());
+        let duration = end - start;
+
+        for time in sample_times(rng, start, end) {
+            let periods = rng.count(1, 4) as f32;
+            let inside = track.try_sample::<f32>(time, LoopMode::Clamp).unwrap();
+
+            // clamped times stick to the ends
+            let before = track
+                .try_sample::<f32>(start - duration * periods, LoopMode::Clamp)
+                .unwrap();
+            assert_eq!(before, track.frames[0].m_value[0], "case {}", case);
+
+            let looped = track
+                .try_sample::<f32>(time + duration * periods, LoopMode::Loop)
+                .unwrap();
+            assert!(close(looped, inside, 1e-3), "case {} at {}", case, time);
+
+            // ping pong mirrors around the end
+            let mirrored = track
+                .try_sample::<f32>(2.0 * end - time, LoopMode::PingPong)
+                .unwrap();
+            assert!(close(mirrored, inside, 1e-3), "case {} at {}", case, time);
+        }
+    });
+}
+
+#[test]
+fn sampling_order_does_not_matter() {
+    // the cursor cache and lookup table only speed things up
+    check(|rng, case| {
+        let times = key_times(rng);
+        let curve = Cubic::random(rng);
+        let mut cached = scalar_track(&times, curve, Interpolation::Linear);
+        cached.build_lookup(rng.range(1.0, 100.0));
+
+        for time in sample_times(rng, times[0] - 1.0, *times.last().unwrap() + 1.0) {
+            let fresh = scalar_track(&times, curve, Interpolation::Linear)
+                .try_sample::<f32>(time, LoopMode::Loop)
+                .unwrap();
+            assert_eq!(
+                cached.try_sample::<f32>(time, LoopMode::Loop).unwrap(),
+                fresh,
+                "case {}",
+                case
+            );
+        }
+    });
+}
+
+#[test]
+fn degenerate_tracks_do_not_panic() {
+    check(|rng, case| {
+        let time = rng.range(-10.0, 10.0);
+        let mode = [LoopMode::Clamp, LoopMode::Loop, LoopMode::PingPong][rng.count(0, 2)];
+
+        let mut empty = ScalarTrack::new();
+        assert_eq!(empty.try_sample::<f32>(time, mode), Err(TrackError::Empty));
+        assert_eq!(empty.frame_index(time, mode), Err(TrackError::Empty));
+        assert_eq!(empty.validate(), Err(TrackError::Empty));
+
+        // a single frame holds its value forever
+        let value = rng.range(-5.0, 5.0);
+        let mut single = scalar_track(
+            &[rng.range(-1.0, 1.0)],
+            Cubic([value, 0.0, 0.0, 0.0]),
+            Interpolation::Cubic,
+        );
+        assert_eq!(
+            single.try_sample::<f32>(time, mode),
+            Ok(value),
+            "case {}",
+            case
+        );
+        assert_eq!(single.frame_index(time, mode), Ok(0));
+        assert_eq!(
+            single.try_sample::<f32>(f32::NAN, mode),
+            Err(TrackError::InvalidTime)
+        );
+
+        // frames sharing a time jump, the later frame wins and nothing turns into NaN
+        let key = rng.range(0.1, 1.0);
+        let mut step = scalar_track(
+            &[0.0, key, key, 2.0, 2.0],
+            Cubic::random(rng),
+            Interpolation::Linear,
+        );
+        step.frames[2].m_value[0] += 1.0;
+        assert_eq!(
+            step.try_sample::<f32>(key, mode).unwrap(),
+            step.frames[2].m_value[0]
+        );
+        assert_eq!(
+            step.try_sample::<f32>(2.0, LoopMode::Clamp).unwrap(),
+            step.frames[4].m_value[0]
+        );
+        for interpolation in [
+            Interpolation::Constant,
+            Interpolation::Linear,
+            Interpolation::Cubic,
+        ] {
+            step.interpolation = interpolation;
+            for time in sample_times(rng, -1.0, 3.0) {
+                assert!(step.try_sample::<f32>(time, mode).unwrap().is_finite());
+            }
+        }
+
+        // a track with no duration gives its last frame instead of dividing by zero
+        let mut flat = scalar_track(&[key, key], Cubic::random(rng), Interpolation::Linear);
+        assert!(flat.try_sample::<f32>(time, mode).unwrap().is_finite());
+    });
+}
+
+#[test]
+fn unsorted_tracks_are_reported_and_sorted() {
+    check(|rng, case| {
+        let mut times = key_times(rng);
+        let curve = Cubic::random(rng);
+
+        // swap two neighbouring keys
+        let swapped = rng.count(1, times.len() - 1);
+        times.swap(swapped - 1, swapped);
+        let mut track = scalar_track(&times, curve, Interpolation::Linear);
+        assert_eq!(
+            track.validate(),
+            Err(TrackError::Unsorted(swapped)),
+            "case {}",
+            case
+        );
+
+        // sampling near the swap either notices it or still gives a number
+        let time = (times[swapped - 1] + times[swapped]) * 0.5;
+        let sampled = track.try_sample::<f32>(time, LoopMode::Clamp);
+        assert!(sampled.map_or(true, f32::is_finite), "case {}", case);
+
+        track.sort_frames();
+        assert_eq!(track.validate(), Ok(()));
+        for time in sample_times(rng, track.get_start_time(), track.get_end_time()) {
+            let expected = scalar_track(&sorted(&times), curve, Interpolation::Linear)
+                .try_sample::<f32>(time, LoopMode::Clamp)
+                .unwrap();
+            assert_eq!(
+                track.try_sample::<f32>(time, LoopMode::Clamp).unwrap(),
+                expected
+            );
+        }
+
+        track.frames[0].time = f32::NAN;
+        assert_eq!(track.validate(), Err(TrackError::InvalidFrameTime(0)));
+    });
+}
+
+fn sorted(times: &[f32]) -> Vec<f32> {
+    let mut times = times.to_vec();
+    times.sort_by(f32::total_cmp);
+    times
+}
@@ -5,19 +5,52 @@
 // tried my best to translate the c++ to rust so maybe a few minor rough edges here and there
 // but hey if it works, it works
 
+use std::fmt;
 use std::ops::*;
 
-use super::curves;
+use super::curves::{self, LoopMode};
 use super::frame;
 
 use math::quaternion::Quat;
 use math::vec3::Vec3;
 
+/// why a track could not be sampled
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TrackError {
+    /// the track has no frames
+    Empty,
+    /// the time to sample at is not a finite number
+    InvalidTime,
+    /// the frame at this index has a time that is not a finite number
+    InvalidFrameTime(usize),
+    /// the frame at this index comes before the frame in front of it
+    Unsorted(usize),
+}
+
+impl fmt::Display for TrackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TrackError::Empty => write!(f, "the track has no frames"),
+            TrackError::InvalidTime => write!(f, "the sample time is not a finite number"),
+            TrackError::InvalidFrameTime(index) => {
+                write!(f, "frame {} has a time that is not a finite number", index)
+            }
+            TrackError::Unsorted(index) => {
+                write!(f, "frame {} is earlier than the one before", index)
+            }
+        }
+    }
+}
+
 #[derive(Clone)]
 /// animation for a single node(joint, bone, which ever you'd prefer to call it)
 pub struct Track<const N: usize> {
     pub frames: Vec<frame::Frame<N>>,
     pub interpolation: curves::Interpolation,
+    /// frame found by the last sample
+    cursor: usize,
+    /// frame index at evenly spaced times, empty until build_lookup is called
+    lookup: Vec<usize>,
 }
 
 #[allow(unused)]
@@ -30,6 +63,8 @@ impl<const N: usize> Track<N> {
         Self {
             frames: Vec::new(),
             interpolation: curves::Interpolation::Cubic,
+            cursor: 0,
+            lookup: Vec::new(),
         }
     }
 
@@ -39,7 +74,10 @@ impl<const N: usize> Track<N> {
     pub fn get_end_time(&self) -> f32 {
         self.frames.last().unwrap().time
     }
-    pub fn sample<T>(&self, time: f32, looping: bool) -> T
+    /// sample the track at `time`
+    /// single frame tracks hold their value, frames sharing a time make a jump where the later
+    /// frame wins. only the frames around `time` are checked for order, use validate for the rest
+    pub fn try_sample<T>(&mut self, time: f32, mode: LoopMode) -> Result<T, TrackError>
     where
         T: Cast<T>
             + Interpolate<T>
@@ -48,39 +86,58 @@ impl<const N: usize> Track<N> {
             + Add<T, Output = T>
             + Mul<f32, Output = T>,
     {
-        match self.interpolation {
-            curves::Interpolation::Cubic => self.sample_cubic(time, looping),
-            curves::Interpolation::Linear => self.sample_linear(time, looping),
-            curves::Interpolation::Constant => self.sample_constant(time, looping),
+        if self.frames.is_empty() {
+            return Err(TrackError::Empty);
+        }
+        if !time.is_finite() {
+            return Err(TrackError::InvalidTime);
+        }
+        if self.frames.len() == 1 {
+            return Ok(T::cast(&self.frames[0].m_value));
         }
-    }
-
-    fn sample_constant<T: Cast<T>>(&self, time: f32, looping: bool) -> T {
-        let frame = self.frame_index(time, looping).unwrap();
-
-        T::cast(&self.frames[frame].m_value)
-    }
 
-    fn sample_linear<T: Cast<T> + Interpolate<T>>(&self, time: f32, looping: bool) -> T {
-        let frame = self.frame_index(time, looping).unwrap();
+        let track_time = self.adjust_to_fit_track(time, mode);
+        let frame = self.cached_frame_index(track_time);
         let next_frame = frame + 1;
 
-        let track_time = self.adjust_to_fit_track(time, looping);
         let this_time = self.frames[frame].time;
-        let frame_delta = self.frames[next_frame].time - this_time;
-
-        if frame_delta <= 0.0 {
-            println!("error while linear sampling occured!");
+        let next_time = self.frames[next_frame].time;
+        if !this_time.is_finite() {
+            return Err(TrackError::InvalidFrameTime(frame));
+        }
+        if !next_time.is_finite() {
+            return Err(TrackError::InvalidFrameTime(next_frame));
+        }
+        if next_time < this_time {
+            return Err(TrackError::Unsorted(next_frame));
         }
 
-        let t = (track_time - this_time) / frame_delta;
+        let frame_delta = next_time - this_time;
+        Ok(match self.interpolation {
+            curves::Interpolation::Constant => T::cast(&self.frames[frame].m_value),
+            // only the last segment can be empty, the search skips the others
+            _ if frame_delta <= 0.0 => T::cast(&self.frames[next_frame].m_value),
+            curves::Interpolation::Linear => self.sample_linear(frame, track_time, frame_delta),
+            curves::Interpolation::Cubic => self.sample_cubic(frame, track_time, frame_delta),
+        })
+    }
+
+    fn sample_linear<T: Cast<T> + Interpolate<T>>(
+        &self,
+        frame: usize,
+        track_time: f32,
+        frame_delta: f32,
+    ) -> T {
+        let next_frame = frame + 1;
+        let t = (track_time - self.frames[frame].time) / frame_delta;
 
         let start = T::cast(&self.frames[frame].m_value);
         let end = T::cast(&self.frames[next_frame].m_value);
 
         T::interpolate(&start, &end, t)
     }
-    fn sample_cubic<T>(&self, time: f32, looping: bool) -> T
+
+    fn sample_cubic<T>(&self, frame: usize, track_time: f32, frame_delta: f32) -> T
     where
         T: Cast<T>
             + Interpolate<T>
@@ -89,18 +146,8 @@ impl<const N: usize> Track<N> {
             + Add<T, Output = T>
             + Mul<f32, Output = T>,
     {
-        let frame = self.frame_index(time, looping).unwrap();
         let next_frame = frame + 1;
-
-        let track_time = self.adjust_to_fit_track(time, looping);
-        let this_time = self.frames[frame].time;
-        let frame_delta = self.frames[next_frame].time - this_time;
-
-        if frame_delta <= 0.0 {
-            println!("error while cubic sampling occured!");
-        }
-
-        let t = (track_time - this_time) / frame_delta;
+        let t = (track_time - self.frames[frame].time) / frame_delta;
 
         let point1 = T::cast(&self.frames[frame].m_value);
         let mut slope1: [f32; N] = [0.0; N];
@@ -119,76 +166,127 @@ impl<const N: usize> Track<N> {
         Self::hermite(t, point1, slope1, point2, slope2)
     }
 
-    pub fn frame_index(&self, _time: f32, looping: bool) -> Result<usize, String> {
-        let mut time = _time;
-        let len = self.frames.len();
-
-        if len <= 0 {
-            // there has been an error
-            return Err(String::from("error finding frame index!"));
+    /// check every frame, times have to be finite and never go down
+    pub fn validate(&self) -> Result<(), TrackError> {
+        if self.frames.is_empty() {
+            return Err(TrackError::Empty);
         }
 
-        if looping {
-            let start_time = self.get_start_time();
-            let end_time = self.get_end_time();
-            let duration = end_time - start_time;
-
-            time = (time - start_time) % duration;
-
-            if time < 0.0 {
-                time += duration;
-            }
-            time += start_time;
-        } else {
-            if time <= self.get_start_time() {
-                return Ok(0);
+        for (i, frame) in self.frames.iter().enumerate() {
+            if !frame.time.is_finite() {
+                return Err(TrackError::InvalidFrameTime(i));
             }
-            if time >= self.frames[len - 2].time {
-                return Ok(len - 2);
+            if i > 0 && frame.time < self.frames[i - 1].time {
+                return Err(TrackError::Unsorted(i));
             }
         }
 
-        for i in (0..len).rev() {
-            if time >= self.frames[i].time {
-                return Ok(i);
-            }
+        Ok(())
+    }
+
+    /// put the frames back in time order, frames sharing a time keep their order
+    pub fn sort_frames(&mut self) {
+        self.frames.sort_by(|a, b| a.time.total_cmp(&b.time));
+        self.cursor = 0;
+        self.lookup.clear();
+    }
+
+    /// index of the frame starting the segment that contains `time`
+    pub fn frame_index(&self, time: f32, mode: LoopMode) -> Result<usize, TrackError> {
+        if self.frames.is_empty() {
+            return Err(TrackError::Empty);
         }
+        if !time.is_finite() {
+            return Err(TrackError::InvalidTime);
+        }
+
+        let time = mode.fit(time, self.get_start_time(), self.get_end_time());
 
-        // there has been an error
-        Err(String::from("error finding frame index!"))
+        Ok(self.find_frame(time))
     }
 
-    pub fn adjust_to_fit_track(&self, time: f32, looping: bool) -> f32 {
-        let len = self.frames.len();
-        let mut time = time;
-        if len <= 1 {
-            return 0.0;
+    /// pre compute the frame index at `samples_per_second` evenly spaced times, like the
+    /// book's fast track. the table only gives a first guess so a stale one costs speed, not
+    /// correctness, but it should be rebuilt when frames change
+    pub fn build_lookup(&mut self, samples_per_second: f32) {
+        self.lookup.clear();
+        if self.frames.len() < 2 || samples_per_second <= 0.0 {
+            return;
         }
 
         let start_time = self.get_start_time();
-        let end_time = self.get_end_time();
-        let duration = end_time - start_time;
-
+        let duration = self.get_end_time() - start_time;
         if duration <= 0.0 {
-            return 0.0;
+            return;
         }
-        if looping {
-            time = (time - start_time) % duration;
-            if time < 0.0 {
-                time += duration;
-            }
 
-            time += start_time;
-        } else {
-            if time <= start_time {
-                time = start_time;
+        let samples = (duration * samples_per_second).ceil() as usize + 1;
+        for i in 0..samples {
+            let time = start_time + duration * i as f32 / (samples - 1) as f32;
+            self.lookup.push(self.search_frame(time));
+        }
+    }
+
+    /// true when the segment starting at `index` contains `time`
+    /// the last segment also contains the time of the last frame
+    fn segment_contains(&self, index: usize, time: f32) -> bool {
+        let len = self.frames.len();
+        index + 1 < len
+            && self.frames[index].time <= time
+            && (time < self.frames[index + 1].time || index + 2 == len)
+    }
+
+    /// binary search, the last frame never starts a segment
+    fn search_frame(&self, time: f32) -> usize {
+        let len = self.frames.len();
+        self.frames[..len.saturating_sub(1)]
+            .partition_point(|frame| frame.time <= time)
+            .saturating_sub(1)
+    }
+
+    /// frame index for a time inside the track, from the lookup table when there is one
+    fn find_frame(&self, time: f32) -> usize {
+        if let Some(last) = self.lookup.len().checked_sub(1) {
+            let start_time = self.get_start_time();
+            let duration = self.get_end_time() - start_time;
+            let sample = ((time - start_time) / duration * last as f32) as usize;
+
+            // the table is sampled, a frame can start between two samples
+            let guess = self.lookup[sample.min(last)];
+            if self.segment_contains(guess, time) {
+                return guess;
             }
-            if time >= end_time {
-                time = end_time;
+            if self.segment_contains(guess + 1, time) {
+                return guess + 1;
             }
         }
 
-        time
+        self.search_frame(time)
+    }
+
+    /// frame index for a time inside the track, playback mostly stays in the segment of the
+    /// previous sample or moves on to the next one so those are checked first
+    fn cached_frame_index(&mut self, time: f32) -> usize {
+        let index = if self.segment_contains(self.cursor, time) {
+            self.cursor
+        } else if self.segment_contains(self.cursor + 1, time) {
+            self.cursor + 1
+        } else {
+            self.find_frame(time)
+        };
+        self.cursor = index;
+
+        index
+    }
+
+    /// map `time` onto the time range of the track, 0 for empty tracks
+    pub fn adjust_to_fit_track(&self, time: f32, mode: LoopMode) -> f32 {
+        if self.frames.is_empty() {
+            return 0.0;
+        }
+
+        // fit gives the start time when the track has no duration
+        mode.fit(time, self.get_start_time(), self.get_end_time())
     }
 
     pub fn hermite<T>(t: f32, p1: T, s1: T, _p2: T, s2: T) -> T
@@ -1,4 +1,5 @@
-use super::track;
+use super::curves::LoopMode;
+use super::track::{self, TrackError};
 use math::quaternion::Quat;
 use math::transform::Transform;
 use math::vec3::Vec3;
@@ -23,12 +24,36 @@ impl TransformTrack {
         }
     }
 
+    /// true when no channel has a frame, such tracks do not animate anything
+    pub fn is_empty(&self) -> bool {
+        self.position.frames.is_empty()
+            && self.rotation.frames.is_empty()
+            && self.scaling.frames.is_empty()
+    }
+
+    /// true when a channel changes over time, only those give the track a duration
     pub fn is_valid(&self) -> bool {
         self.position.frames.len() > 1
             || self.rotation.frames.len() > 1
             || self.scaling.frames.len() > 1
     }
 
+    /// check every channel with frames, see Track::validate
+    /// tracks that pass only fail to sample at times that are not finite
+    pub fn validate(&self) -> Result<(), TrackError> {
+        if !self.position.frames.is_empty() {
+            self.position.validate()?;
+        }
+        if !self.rotation.frames.is_empty() {
+            self.rotation.validate()?;
+        }
+        if !self.scaling.frames.is_empty() {
+            self.scaling.validate()?;
+        }
+
+        Ok(())
+    }
+
     pub fn resize(&mut self, size: usize) {
         for _ in 0..size {
             self.position.frames.push(VectorFrame::new());
@@ -91,21 +116,35 @@ impl TransformTrack {
         result
     }
 
-    pub fn sample(&mut self, reference: &Transform, time: f32, looping: bool) -> Transform {
+    /// see Track::build_lookup
+    pub fn build_lookup(&mut self, samples_per_second: f32) {
+        self.position.build_lookup(samples_per_second);
+        self.rotation.build_lookup(samples_per_second);
+        self.scaling.build_lookup(samples_per_second);
+    }
+
+    /// sample every channel, channels without frames keep the value of `reference`
+    /// and single frame channels hold their value
+    pub fn sample(
+        &mut self,
+        reference: &Transform,
+        time: f32,
+        mode: LoopMode,
+    ) -> Result<Transform, TrackError> {
         let mut result = *reference;
 
-        if self.position.frames.len() > 1 {
-            result.translation = self.position.sample::<Vec3>(time, looping);
+        if !self.position.frames.is_empty() {
+            result.translation = self.position.try_sample::<Vec3>(time, mode)?;
         }
 
-        if self.rotation.frames.len() > 1 {
-            result.orientation = self.rotation.sample::<Quat>(time, looping);
+        if !self.rotation.frames.is_empty() {
+            result.orientation = self.rotation.try_sample::<Quat>(time, mode)?;
         }
 
-        if self.scaling.frames.len() > 1 {
-            result.scaling = self.scaling.sample::<Vec3>(time, looping);
+        if !self.scaling.frames.is_empty() {
+            result.scaling = self.scaling.try_sample::<Vec3>(time, mode)?;
         }
 
-        result
+        Ok(result)
     }
 }
@@ -0,0 +1,88 @@
+// morph target weights of one node
+// gltf animates every weight of a node with a single sampler, here each target gets its own
+// scalar track so the weights can be sampled and reduced like any other track
+
+use super::curves::LoopMode;
+use super::track::{ScalarTrack, TrackError};
+
+#[derive(Clone)]
+pub struct WeightsTrack {
+    /// node whose mesh the weights belong to
+    pub id: u32,
+    /// one track per morph target
+    pub weights: Vec<ScalarTrack>,
+}
+
+impl WeightsTrack {
+    pub fn new() -> Self {
+        Self {
+            id: 0,
+            weights: Vec::new(),
+        }
+    }
+
+    /// true when no target has a frame
+    pub fn is_empty(&self) -> bool {
+        self.weights.iter().all(|track| track.frames.is_empty())
+    }
+
+    /// true when a weight changes over time, only those give the track a duration
+    pub fn is_valid(&self) -> bool {
+        self.weights.iter().any(|track| track.frames.len() > 1)
+    }
+
+    /// like TransformTrack::validate, for every target with frames
+    pub fn validate(&self) -> Result<(), TrackError> {
+        for track in &self.weights {
+            if !track.frames.is_empty() {
+                track.validate()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn get_start_time(&self) -> f32 {
+        self.weights
+            .iter()
+            .filter(|track| track.frames.len() > 1)
+            .map(|track| track.get_start_time())
+            .reduce(f32::min)
+            .unwrap_or(0.0)
+    }
+
+    pub fn get_end_time(&self) -> f32 {
+        self.weights
+            .iter()
+            .filter(|track| track.frames.len() > 1)
+            .map(|track| track.get_end_time())
+            .reduce(f32::max)
+            .unwrap_or(0.0)
+    }
+
+    /// see Track::build_lookup
+    pub fn build_lookup(&mut self, samples_per_second: f32) {
+        for track in &mut self.weights {
+            track.build_lookup(samples_per_second);
+        }
+    }
+
+    /// write the weight of every target into `out`, targets without keys keep their weight
+    /// like TransformTrack::sample, single key targets hold their weight
+    /// on error the targets before the one that failed are already written
+    pub fn sample(&mut self, out: &mut [f32], time: f32, mode: LoopMode) -> Result<(), TrackError> {
+        for (weight, track) in out.iter_mut().zip(&mut self.weights) {
+            if !track.frames.is_empty() {
+                *weight = track.try_sample::<f32>(time, mode)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for WeightsTrack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
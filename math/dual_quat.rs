@@ -0,0 +1,174 @@
+// dual quaternions, a rotation and a translation packed into two quaternions
+// they blend without the volume loss of blended matrices which is what dual quaternion skinning
+// is about. "kavan et al. - skinning with dual quaternions" describes the whole thing
+
+use super::{mat4::*, quaternion::*, transform::*, vec3::*};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DualQuat {
+    /// the rotation
+    pub real: Quat,
+    /// half the translation times the rotation
+    pub dual: Quat,
+}
+
+impl DualQuat {
+    pub const IDENTITY: Self = Self {
+        real: Quat::ZERO,
+        dual: Quat {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+            s: 0.0,
+        },
+    };
+
+    /// rotate by `orientation` then move by `translation`
+    pub fn new(orientation: Quat, translation: Vec3) -> Self {
+        let t = quat(translation.x, translation.y, translation.z, 0.0);
+
+        Self {
+            real: orientation,
+            dual: t * orientation * 0.5,
+        }
+    }
+
+    /// scaling can not be represented and is dropped
+    pub fn from_transform(transform: &Transform) -> Self {
+        Self::new(transform.orientation, transform.translation)
+    }
+
+    /// rigid part of a matrix, scaling and shearing are dropped
+    pub fn from_mat(mat: &Mat4) -> Self {
+        let d = &mat.data;
+        let mut columns = [
+            vec3(d[0][0], d[1][0], d[2][0]),
+            vec3(d[0][1], d[1][1], d[2][1]),
+            vec3(d[0][2], d[1][2], d[2][2]),
+        ];
+        for column in &mut columns {
+            if column.len() > 0.0 {
+                *column = column.unit();
+            }
+        }
+        let [x, y, z] = columns;
+        let rotation = Mat4 {
+            data: [
+                [x.x, y.x, z.x, 0.0],
+                [x.y, y.y, z.y, 0.0],
+                [x.z, y.z, z.z, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        };
+
+        Self::new(rotation.to_quat().unit(), vec3(d[0][3], d[1][3], d[2][3]))
+    }
+
+    /// expects a unit dual quaternion
+    pub fn get_orientation(&self) -> Quat {
+        self.real
+    }
+
+    /// expects a unit dual quaternion
+    pub fn get_translation(&self) -> Vec3 {
+        let t = self.dual * self.real.conjugate() * 2.0;
+        vec3(t.x, t.y, t.z)
+    }
+
+    pub fn to_transform(&self) -> Transform {
+        Transform::new(Vec3::ONE, self.get_translation(), self.real)
+    }
+
+    pub fn to_mat(&self) -> Mat4 {
+        self.to_transform().to_mat()
+    }
+
+    /// rotation and translation in the order x, y, z, s of the real part then of the dual part
+    pub fn to_array(&self) -> [f32; 8] {
+        let [a, b, c, d] = self.real.to_array();
+        let [e, f, g, h] = self.dual.to_array();
+        [a, b, c, d, e, f, g, h]
+    }
+
+    pub fn from(a: &[f32; 8]) -> Self {
+        Self {
+            real: quat(a[0], a[1], a[2], a[3]),
+            dual: quat(a[4], a[5], a[6], a[7]),
+        }
+    }
+
+    /// dot product of the rotations, negative when they lie in opposite hemispheres
+    pub fn dot(&self, rhs: &Self) -> f32 {
+        self.real.dot(&rhs.real)
+    }
+
+    /// scale both parts so the rotation has unit length, blends have to be normalized before use
+    pub fn unit(&self) -> Self {
+        let coeff = 1.0 / self.real.norm();
+
+        Self {
+            real: self.real * coeff,
+            dual: self.dual * coeff,
+        }
+    }
+
+    pub fn conjugate(&self) -> Self {
+        Self {
+            real: self.real.conjugate(),
+            dual: self.dual.conjugate(),
+        }
+    }
+
+    /// expects a unit dual quaternion
+    pub fn transform_point(&self, p: &Vec3) -> Vec3 {
+        self.real * *p + self.get_translation()
+    }
+
+    /// expects a unit dual quaternion
+    pub fn transform_vector(&self, v: &Vec3) -> Vec3 {
+        self.real * *v
+    }
+}
+
+use std::ops::*;
+
+impl Add for DualQuat {
+    type Output = DualQuat;
+    fn add(self, rhs: Self) -> Self::Output {
+        Self {
+            real: self.real + rhs.real,
+            dual: self.dual + rhs.dual,
+        }
+    }
+}
+
+impl Neg for DualQuat {
+    type Output = DualQuat;
+    fn neg(self) -> Self::Output {
+        Self {
+            real: -self.real,
+            dual: -self.dual,
+        }
+    }
+}
+
+impl Mul<f32> for DualQuat {
+    type Output = DualQuat;
+    fn mul(self, rhs: f32) -> Self::Output {
+        Self {
+            real: self.real * rhs,
+            dual: self.dual * rhs,
+        }
+    }
+}
+
+impl Mul<DualQuat> for DualQuat {
+    type Output = DualQuat;
+    /// same order as matrices, rhs is applied first
+    fn mul(self, rhs: DualQuat) -> Self::Output {
+        Self {
+            real: self.real * rhs.real,
+            dual: self.real * rhs.dual + self.dual * rhs.real,
+        }
+    }
+}
@@ -1,3 +1,4 @@
+pub mod dual_quat;
 pub mod mat4;
 pub mod misc;
 pub mod quaternion;
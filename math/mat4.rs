@@ -91,6 +91,26 @@ impl Mat4 {
         let s = coeff * (data[1][0] - data[0][1]);
         return Quat { x, y, z, s };
     }
+
+    /// multiply a point by the matrix, w is assumed to be 1
+    pub fn transform_point(&self, p: &Vec3) -> Vec3 {
+        let d = &self.data;
+        Vec3 {
+            x: d[0][0] * p.x + d[0][1] * p.y + d[0][2] * p.z + d[0][3],
+            y: d[1][0] * p.x + d[1][1] * p.y + d[1][2] * p.z + d[1][3],
+            z: d[2][0] * p.x + d[2][1] * p.y + d[2][2] * p.z + d[2][3],
+        }
+    }
+
+    /// multiply a direction by the matrix, translation is ignored
+    pub fn transform_vector(&self, v: &Vec3) -> Vec3 {
+        let d = &self.data;
+        Vec3 {
+            x: d[0][0] * v.x + d[0][1] * v.y + d[0][2] * v.z,
+            y: d[1][0] * v.x + d[1][1] * v.y + d[1][2] * v.z,
+            z: d[2][0] * v.x + d[2][1] * v.y + d[2][2] * v.z,
+        }
+    }
 }
 use std::fmt::Display;
 use std::ops::*;
@@ -172,6 +192,20 @@ impl Mul<Mat4> for Mat4 {
         }
     }
 }
+impl Add<Mat4> for Mat4 {
+    type Output = Mat4;
+    fn add(self, rhs: Mat4) -> Self::Output {
+        let mut result = Mat4::IDENTITY;
+
+        for i in 0..4 {
+            for j in 0..4 {
+                result.data[i][j] = self.data[i][j] + rhs.data[i][j];
+            }
+        }
+
+        result
+    }
+}
 impl Mul<f32> for Mat4 {
     type Output = Mat4;
     fn mul(self, rhs: f32) -> Self::Output {
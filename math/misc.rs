@@ -3,6 +3,9 @@ pub const PIE: f32 = 3.1415927;
 pub fn radians(v: f32) -> f32 {
     v * (PIE / 180.0)
 }
+pub fn degrees(v: f32) -> f32 {
+    v * (180.0 / PIE)
+}
 pub fn minimum(a: f32, b: f32) -> f32 {
     if a < b {
         a
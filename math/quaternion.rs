@@ -99,6 +99,40 @@ impl Quat {
         vec3(self.x, self.y, self.z)
     }
 
+    /// angle of the rotation in degrees
+    pub fn angle(&self) -> f32 {
+        degrees(2.0 * clamp(self.s.abs(), 0.0, 1.0).acos())
+    }
+
+    /// shortest rotation turning the direction `from` into the direction `to`
+    /// zero length vectors give the identity rotation
+    pub fn from_to(from: &Vec3, to: &Vec3) -> Self {
+        if from.len() <= 0.0 || to.len() <= 0.0 {
+            return Self::ZERO;
+        }
+
+        let f = from.unit();
+        let t = to.unit();
+
+        // opposite directions, any axis perpendicular to from works
+        if dot(&f, &t) <= -1.0 + 1e-6 {
+            let ortho = if f.x.abs() < f.y.abs() && f.x.abs() < f.z.abs() {
+                vec3(1.0, 0.0, 0.0)
+            } else if f.y.abs() < f.z.abs() {
+                vec3(0.0, 1.0, 0.0)
+            } else {
+                vec3(0.0, 0.0, 1.0)
+            };
+            let axis = cross(&f, &ortho).unit();
+            return quat(axis.x, axis.y, axis.z, 0.0);
+        }
+
+        let half = (f + t).unit();
+        let axis = cross(&f, &half);
+
+        quat(axis.x, axis.y, axis.z, dot(&f, &half))
+    }
+
     /// rotate around a specified axis
     /// creates a rotation matrix from a quaternion
     pub fn to_mat(&self) -> Mat4 {
@@ -131,7 +165,7 @@ impl Quat {
 
 use std::ops::*;
 
-use super::misc::radians;
+use super::misc::{clamp, degrees, radians};
 impl Sub for Quat {
     type Output = Quat;
     fn sub(self, rhs: Self) -> Self::Output {
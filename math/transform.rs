@@ -23,10 +23,16 @@ impl Transform {
     }
 
     pub fn lerp(&self, other: &Self, factor: f32) -> Transform {
+        // take the shortest path between the two orientations
+        let mut orientation = other.orientation;
+        if self.orientation.dot(&orientation) < 0.0 {
+            orientation = -orientation;
+        }
+
         Self {
             translation: self.translation.mix(other.translation, factor),
             scaling: self.scaling.mix(other.scaling, factor),
-            orientation: self.orientation.nlerp(other.orientation, factor),
+            orientation: self.orientation.nlerp(orientation, factor),
         }
     }
 
@@ -23,3 +23,28 @@ impl Vec2 {
         [misc::step(self.x, other.x), misc::step(self.y, other.y)]
     }
 }
+
+// dot product with another vector
+pub fn dot(v1: &Vec2, v2: &Vec2) -> f32 {
+    (v1.x * v2.x) + (v1.y * v2.y)
+}
+
+use std::ops::*;
+impl Add for Vec2 {
+    type Output = Vec2;
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            x: rhs.x + self.x,
+            y: rhs.y + self.y,
+        }
+    }
+}
+impl Sub for Vec2 {
+    type Output = Vec2;
+    fn sub(self, rhs: Self) -> Self {
+        Self {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+        }
+    }
+}
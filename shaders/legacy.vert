@@ -0,0 +1,32 @@
+// fallback vertex shader for contexts that can't run shader.vert
+// skinning is done on the cpu so there are no bone attributes here
+// the #version line is prepended at load time depending on the context
+
+attribute vec3 pos;
+
+attribute vec3 norm;
+
+attribute vec2 uv;
+
+uniform mat4 transform;
+
+uniform mat4 view;
+
+uniform mat4 projection;
+
+varying vec3 normal;
+varying vec3 fragPos;
+varying vec2 texCoords;
+
+void main() {
+    vec4 worldPos = transform * vec4(pos, 1.0);
+
+    gl_Position = projection * view * worldPos;
+
+    // no inverse() in older glsl, fine as long as the scaling is uniform
+    normal = (transform * vec4(norm, 0.0)).xyz;
+
+    texCoords = uv;
+
+    fragPos = worldPos.xyz;
+}
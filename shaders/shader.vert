@@ -8,7 +8,7 @@ layout(location = 2) in vec2 uv;
 
 layout(location = 3) in vec4 weights;
 
-layout(location = 4) in vec4 boneIds;
+layout(location = 4) in ivec4 boneIds;
 
 uniform mat4 transform;
 
@@ -29,31 +29,91 @@ out vs_Out {
 
 const int MAX_BONES = 300;
 const int MAX_BONE_INFLUENCE = 4;
+const int MAX_MORPH_TARGETS = 64;
 
 uniform mat4 boneMats[MAX_BONES];
 
+// with dual quaternion skinning the first column of each bone matrix holds the rotation
+// and the second column the dual part, see ShaderManager::set_skeleton_dual_quats
+uniform bool dualQuatSkinning;
+
+mat4 linearBlendSkin() {
+    mat4 skin = mat4(0.0);
+
+    for(int i = 0; i < MAX_BONE_INFLUENCE; i++) {
+        skin += boneMats[boneIds[i]] * weights[i];
+    }
+
+    return skin;
+}
+
+mat4 dualQuatSkin() {
+    vec4 first = boneMats[boneIds[0]][0];
+    vec4 real = vec4(0.0);
+    vec4 dual = vec4(0.0);
+
+    for(int i = 0; i < MAX_BONE_INFLUENCE; i++) {
+        mat4 bone = boneMats[boneIds[i]];
+        // keep every rotation in the hemisphere of the first so the blend takes the short way
+        float weight = dot(first, bone[0]) < 0.0 ? -weights[i] : weights[i];
+        real += bone[0] * weight;
+        dual += bone[1] * weight;
+    }
+
+    float len = length(real);
+    real /= len;
+    dual /= len;
+
+    vec3 r = real.xyz;
+    float s = real.w;
+    vec3 t = 2.0 * (s * dual.xyz - dual.w * r + cross(r, dual.xyz));
+
+    return mat4(
+        vec4(1.0 - 2.0 * (r.y * r.y + r.z * r.z), 2.0 * (r.x * r.y + s * r.z), 2.0 * (r.x * r.z - s * r.y), 0.0),
+        vec4(2.0 * (r.x * r.y - s * r.z), 1.0 - 2.0 * (r.x * r.x + r.z * r.z), 2.0 * (r.y * r.z + s * r.x), 0.0),
+        vec4(2.0 * (r.x * r.z + s * r.y), 2.0 * (r.y * r.z - s * r.x), 1.0 - 2.0 * (r.x * r.x + r.y * r.y), 0.0),
+        vec4(t, 1.0)
+    );
+}
+
+// position and normal offsets of every morph target, two texels per vertex and target,
+// target after target and row after row
+uniform sampler2D morphTargets;
+uniform int morphTargetCount;
+uniform int morphVertexCount;
+uniform float morphWeights[MAX_MORPH_TARGETS];
+
+vec3 morphOffset(int texel) {
+    int width = textureSize(morphTargets, 0).x;
+    return texelFetch(morphTargets, ivec2(texel % width, texel / width), 0).xyz;
+}
+
 void main() {
     mat4 final_mat = transform;
 
-    if (animated){
+    vec3 position = pos;
+    vec3 normal = norm;
+    for(int i = 0; i < morphTargetCount; i++) {
+        int texel = (i * morphVertexCount + gl_VertexID) * 2;
+        position += morphOffset(texel) * morphWeights[i];
+        normal += morphOffset(texel + 1) * morphWeights[i];
+    }
 
-        mat4 skin = mat4(0.0);
+    if (animated){
 
-        for(int i = 0; i < MAX_BONE_INFLUENCE; i++) {
-            skin += boneMats[int(boneIds[i])] * weights[i];
-        }
+        mat4 skin = dualQuatSkinning ? dualQuatSkin() : linearBlendSkin();
 
         final_mat = transform * skin;
     }
 
-    gl_Position = projection * view * final_mat * vec4(pos, 1.0);
+    gl_Position = projection * view * final_mat * vec4(position, 1.0);
 
-    vs_out.normal = mat3(transpose(inverse(final_mat))) * norm;
+    vs_out.normal = mat3(transpose(inverse(final_mat))) * normal;
 
 
     vs_out.texCoords = uv;
 
-    vs_out.fragPos = vec3(final_mat * vec4(pos, 1.0));
+    vs_out.fragPos = vec3(final_mat * vec4(position, 1.0));
     //vs_out.lightSpacePos = lightSpace * final_mat * vec4(pos, 1.0);
 }
 
@@ -138,7 +138,11 @@ impl ApplicationHandler for Demo {
         self.gl = Some(gl);
 
         if let Some(gl) = &self.gl {
-            self.viewer = Some(Viewer::new(gl, Path::new("models/alien")));
+            let mut viewer = Viewer::new(gl, Path::new("models/alien"));
+            // the mixamo clips of the xbot drive the alien through retargeting,
+            // they are loaded the first time N is pressed
+            viewer.set_clip_library(Path::new("models/xbot"));
+            self.viewer = Some(viewer);
         }
 
         // Try setting vsync.
@@ -226,6 +230,26 @@ impl ApplicationHandler for Demo {
                                 viewer.set_camera_dir(crate::src::viewer::camera::Direction::Right);
                             }
                         }
+                        (KeyCode::KeyF, ElementState::Pressed) => {
+                            if let Some(viewer) = self.viewer.as_mut() {
+                                viewer.toggle_foot_planting();
+                            }
+                        }
+                        (KeyCode::KeyR, ElementState::Pressed) => {
+                            if let Some(viewer) = self.viewer.as_mut() {
+                                viewer.toggle_root_motion();
+                            }
+                        }
+                        (KeyCode::KeyN, ElementState::Pressed) => {
+                            if let Some(viewer) = self.viewer.as_mut() {
+                                viewer.play_next_clip();
+                            }
+                        }
+                        (KeyCode::KeyQ, ElementState::Pressed) => {
+                            if let Some(viewer) = self.viewer.as_mut() {
+                                viewer.toggle_dual_quat_skinning();
+                            }
+                        }
                         // When any of WASD keys are released, stop the movement in that direction
                         (
                             KeyCode::KeyW | KeyCode::KeyS | KeyCode::KeyA | KeyCode::KeyD,
@@ -298,7 +322,6 @@ impl ApplicationHandler for Demo {
             //let gl = self.gl.as_ref().unwrap();
 
             if let Some(gl) = self.gl.as_ref() {
-                
                 let width = gl_surface.width().unwrap() as f32;
                 let height = gl_surface.height().unwrap() as f32;
                 let window_ratio = width / height;
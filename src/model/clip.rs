@@ -0,0 +1,327 @@
+use animation::compression::{Tolerance, reduce_clip};
+use animation::{
+    clip::Clip,
+    curves::Interpolation,
+    frame::Frame,
+    track::{Track, TrackError},
+    track_weights::WeightsTrack,
+};
+use math::quaternion::Quat;
+
+use super::loader::{ColladaFile, GltfFile};
+use super::node::{Node, transform_from_collada};
+
+/// convert every animation in the file into a clip
+/// joints: node index of each joint, tracks are keyed by the position of their node in this list
+/// reduction: drop the keyframes that can be rebuilt within the tolerance, see reduce_clip
+/// without it the clips hold exactly the keyframes of the file
+pub fn clips_from_gltf(
+    file: &GltfFile,
+    joints: &[usize],
+    reduction: Option<&Tolerance>,
+) -> Vec<Clip> {
+    file.get_document()
+        .animations()
+        .map(|animation| clip_from_gltf(&animation, file, joints, reduction))
+        .collect::<Vec<Clip>>()
+}
+
+/// convert a single gltf animation into a clip
+/// channels targeting nodes that are not part of `joints` are skipped
+pub fn clip_from_gltf(
+    animation: &gltf::Animation,
+    file: &GltfFile,
+    joints: &[usize],
+    reduction: Option<&Tolerance>,
+) -> Clip {
+    let mut clip = Clip::new();
+
+    if let Some(name) = animation.name() {
+        clip.name = String::from(name);
+    }
+
+    for channel in animation.channels() {
+        let node = channel.target().node().index();
+        let reader = channel.reader(|buffer| Some(&file.get_buffers()[buffer.index()]));
+
+        let times = match reader.read_inputs() {
+            Some(inputs) => inputs.collect::<Vec<f32>>(),
+            None => continue,
+        };
+
+        let interpolation = match channel.sampler().interpolation() {
+            gltf::animation::Interpolation::Step => Interpolation::Constant,
+            gltf::animation::Interpolation::Linear => Interpolation::Linear,
+            gltf::animation::Interpolation::CubicSpline => Interpolation::Cubic,
+        };
+
+        let outputs = match reader.read_outputs() {
+            Some(outputs) => outputs,
+            None => continue,
+        };
+
+        // morph target weights belong to the mesh node, which does not have to be a joint
+        if let gltf::animation::util::ReadOutputs::MorphTargetWeights(weights) = outputs {
+            let values = weights.into_f32().collect::<Vec<f32>>();
+            let mut track = weights_track_from_gltf(&times, &values, interpolation);
+            track.id = node as u32;
+            clip.weights.retain(|weights| weights.id != track.id);
+            clip.weights.push(track);
+            continue;
+        }
+
+        let joint = match joints.iter().position(|j| *j == node) {
+            Some(joint) => joint as u32,
+            None => continue,
+        };
+
+        let track = clip.get_transform_track(joint);
+
+        match outputs {
+            gltf::animation::util::ReadOutputs::Translations(translations) => {
+                let values = translations.collect::<Vec<[f32; 3]>>();
+                track.position = track_from_gltf(&times, &values, interpolation);
+            }
+            gltf::animation::util::ReadOutputs::Rotations(rotations) => {
+                let values = rotations.into_f32().collect::<Vec<[f32; 4]>>();
+                track.rotation = track_from_gltf(&times, &values, interpolation);
+            }
+            gltf::animation::util::ReadOutputs::Scales(scales) => {
+                let values = scales.collect::<Vec<[f32; 3]>>();
+                track.scaling = track_from_gltf(&times, &values, interpolation);
+            }
+            gltf::animation::util::ReadOutputs::MorphTargetWeights(_) => {}
+        }
+    }
+
+    finish_clip(clip, reduction)
+}
+
+/// COLLADA has no clips, every animation of the file makes up a single clip named after it
+/// nodes: every node of the file, joints and reduction: like clips_from_gltf
+pub fn clips_from_collada(
+    file: &ColladaFile,
+    nodes: &[Node],
+    joints: &[usize],
+    reduction: Option<&Tolerance>,
+) -> Vec<Clip> {
+    if file.get_animations().is_empty() {
+        return Vec::new();
+    }
+
+    let mut clip = Clip::new();
+    clip.name = String::from(file.get_name());
+
+    for animation in file.get_animations() {
+        // targets look like "node_id/transform", only whole matrices are supported
+        let (node, property) = animation
+            .target
+            .split_once('/')
+            .unwrap_or((&animation.target, ""));
+        if property != "transform" && property != "matrix" {
+            println!(
+                "animation of {} is not a matrix, skipping it",
+                animation.target
+            );
+            continue;
+        }
+        if animation.sample_times.len() != animation.sample_poses.len() {
+            println!("animation of {} has missing keyframes", animation.target);
+            continue;
+        }
+
+        let joint = match joints
+            .iter()
+            .position(|joint| nodes[*joint].name.as_deref() == Some(node))
+        {
+            Some(joint) => joint as u32,
+            None => continue,
+        };
+
+        let track = clip.get_transform_track(joint);
+        track.position.interpolation = Interpolation::Linear;
+        track.rotation.interpolation = Interpolation::Linear;
+        track.scaling.interpolation = Interpolation::Linear;
+
+        let mut previous: Option<Quat> = None;
+        for (time, pose) in animation.sample_times.iter().zip(&animation.sample_poses) {
+            let transform = transform_from_collada(pose);
+
+            // matrices do not say which of the two quaternions to use, keep the shortest path
+            let mut orientation = transform.orientation;
+            if previous.is_some_and(|previous| previous.dot(&orientation) < 0.0) {
+                orientation = -orientation;
+            }
+            previous = Some(orientation);
+
+            let mut position = Frame::<3>::new();
+            position.time = *time;
+            position.m_value = transform.translation.to_array();
+            track.position.frames.push(position);
+
+            let mut rotation = Frame::<4>::new();
+            rotation.time = *time;
+            rotation.m_value = orientation.to_array();
+            track.rotation.frames.push(rotation);
+
+            let mut scaling = Frame::<3>::new();
+            scaling.time = *time;
+            scaling.m_value = transform.scaling.to_array();
+            track.scaling.frames.push(scaling);
+        }
+    }
+
+    vec![finish_clip(clip, reduction)]
+}
+
+/// drop what cannot be sampled and prepare the clip for playback
+fn finish_clip(mut clip: Clip, reduction: Option<&Tolerance>) -> Clip {
+    // single keyframe channels hold a constant pose, only channels without keys go
+    clip.tracks.retain(|track| !track.is_empty());
+    clip.weights.retain(|track| !track.is_empty());
+
+    // tracks that cannot be sampled are dropped here instead of failing every frame
+    clip.tracks.retain(|track| match track.validate() {
+        Ok(()) => true,
+        Err(err) => {
+            println!("dropping joint {} of {}: {}", track.id, clip.name, err);
+            false
+        }
+    });
+    clip.weights.retain(|track| match track.validate() {
+        Ok(()) => true,
+        Err(err) => {
+            println!(
+                "dropping the weights of node {} of {}: {}",
+                track.id, clip.name, err
+            );
+            false
+        }
+    });
+    clip.re_calculate_duration();
+
+    // exported clips are usually baked with a key every frame, most of them can go when asked to
+    if let Some(tolerance) = reduction {
+        clip = reduce_clip(&clip, tolerance);
+    }
+    clip.build_lookups(60.0);
+
+    clip
+}
+
+/// split the weights of every morph target out of a sampler
+/// the outputs hold the weight of each target one keyframe after the other,
+/// cubic spline samplers store all the in tangents of a keyframe, then its values, then its out tangents
+fn weights_track_from_gltf(
+    times: &[f32],
+    values: &[f32],
+    interpolation: Interpolation,
+) -> WeightsTrack {
+    let mut track = WeightsTrack::new();
+    let per_key = if interpolation == Interpolation::Cubic {
+        3
+    } else {
+        1
+    };
+    if times.is_empty() {
+        return track;
+    }
+    let count = values.len() / (times.len() * per_key);
+
+    for target in 0..count {
+        let target_values = values
+            .iter()
+            .skip(target)
+            .step_by(count)
+            .map(|value| [*value])
+            .collect::<Vec<[f32; 1]>>();
+        track
+            .weights
+            .push(track_from_gltf(times, &target_values, interpolation));
+    }
+
+    track
+}
+
+/// build a track from sampler keyframes
+/// cubic spline samplers store an (in tangent, value, out tangent) triplet per keyframe
+fn track_from_gltf<const N: usize>(
+    times: &[f32],
+    values: &[[f32; N]],
+    interpolation: Interpolation,
+) -> Track<N> {
+    let mut track = Track::<N>::new();
+    track.interpolation = interpolation;
+
+    for (i, time) in times.iter().enumerate() {
+        let mut frame = Frame::<N>::new();
+        frame.time = *time;
+
+        if interpolation == Interpolation::Cubic {
+            frame.m_in = values[i * 3];
+            frame.m_value = values[i * 3 + 1];
+            frame.m_out = values[i * 3 + 2];
+        } else {
+            frame.m_value = values[i];
+        }
+
+        track.frames.push(frame);
+    }
+
+    // the spec asks for increasing times but not every exporter follows it
+    if let Err(TrackError::Unsorted(index)) = track.validate() {
+        println!("keyframe {} is out of order, sorting the track", index);
+        track.sort_frames();
+    }
+
+    track
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn collada_clips_keep_every_keyframe_unless_reduced() {
+        let folder = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("models/fixtures/mixamo");
+        let file = ColladaFile::load_collada(&folder).unwrap();
+        // the fixture has no skeleton, one node per animated joint stands in for it
+        let nodes = file
+            .get_animations()
+            .iter()
+            .map(|animation| {
+                let mut node = Node::new();
+                node.with_name(String::from(animation.target.split('/').next().unwrap()));
+                node
+            })
+            .collect::<Vec<Node>>();
+        let joints = (0..nodes.len()).collect::<Vec<usize>>();
+
+        let clips = clips_from_collada(&file, &nodes, &joints, None);
+        let keys = clips[0]
+            .tracks
+            .iter()
+            .map(|track| track.position.frames.len())
+            .collect::<Vec<usize>>();
+        let expected = file
+            .get_animations()
+            .iter()
+            .map(|animation| animation.sample_times.len())
+            .collect::<Vec<usize>>();
+        assert_eq!(keys, expected);
+
+        // a loose enough tolerance leaves only the first and last key of every channel
+        let tolerance = Tolerance {
+            position: 1000.0,
+            rotation: 1000.0,
+            scaling: 1000.0,
+            weight: 1000.0,
+        };
+        let reduced = clips_from_collada(&file, &nodes, &joints, Some(&tolerance));
+        for track in &reduced[0].tracks {
+            assert!(track.position.frames.len() <= 2);
+        }
+    }
+}
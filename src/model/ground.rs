@@ -0,0 +1,85 @@
+use animation::foot_planting::GroundMesh;
+use math::vec3::Vec3;
+
+use super::loader::{ColladaFile, GltfFile};
+use super::node::{Node, global_transform};
+
+/// collect the triangles of every static (non skinned) mesh into a ground for foot planting
+/// triangles are in the space of the scene root, the same space as `global_transform`
+pub fn ground_from_gltf(file: &GltfFile, nodes: &[Node]) -> GroundMesh {
+    let mut ground = GroundMesh::new();
+    let meshes = file.get_document().meshes().collect::<Vec<gltf::Mesh>>();
+
+    for (index, node) in nodes.iter().enumerate() {
+        let mesh = match node.mesh {
+            Some(mesh) if node.skin.is_none() => &meshes[mesh],
+            _ => continue,
+        };
+        let transform = global_transform(nodes, index).to_mat();
+
+        for primitive in mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                continue;
+            }
+
+            let reader = primitive.reader(|buffer| Some(&file.get_buffers()[buffer.index()]));
+            let positions = match reader.read_positions() {
+                Some(positions) => positions
+                    .map(|position| transform.transform_point(&Vec3::from(&position)))
+                    .collect::<Vec<Vec3>>(),
+                None => continue,
+            };
+            let indices = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect::<Vec<u32>>(),
+                None => (0..positions.len() as u32).collect::<Vec<u32>>(),
+            };
+
+            for triangle in indices.chunks_exact(3) {
+                ground.add_triangle([
+                    positions[triangle[0] as usize],
+                    positions[triangle[1] as usize],
+                    positions[triangle[2] as usize],
+                ]);
+            }
+        }
+    }
+
+    ground
+}
+
+/// same as ground_from_gltf for the meshes of a COLLADA file, see nodes_from_collada
+pub fn ground_from_collada(file: &ColladaFile, nodes: &[Node]) -> GroundMesh {
+    let mut ground = GroundMesh::new();
+    let objects = file.get_objects();
+
+    for (index, node) in nodes.iter().enumerate() {
+        let object = match node.mesh {
+            Some(mesh) if node.skin.is_none() => &objects[mesh],
+            _ => continue,
+        };
+        let transform = global_transform(nodes, index).to_mat();
+        let position = |vertex: usize| {
+            let p = &object.vertices[vertex];
+            transform.transform_point(&Vec3::new(p.x as f32, p.y as f32, p.z as f32))
+        };
+
+        for element in object.geometry.iter().flat_map(|geometry| &geometry.mesh) {
+            match element {
+                collada::PrimitiveElement::Triangles(triangles) => {
+                    for (a, b, c) in &triangles.vertices {
+                        ground.add_triangle([position(*a), position(*b), position(*c)]);
+                    }
+                }
+                collada::PrimitiveElement::Polylist(polylist) => {
+                    for shape in &polylist.shapes {
+                        if let collada::Shape::Triangle(a, b, c) = shape {
+                            ground.add_triangle([position(a.0), position(b.0), position(c.0)]);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    ground
+}
@@ -1,7 +1,10 @@
 use std::fs;
-use std::io::Error;
+use std::io::{Error, ErrorKind};
 use std::path::Path;
 
+use collada::document::ColladaDocument;
+use xml::{Element, Xml};
+
 pub struct GltfFile {
     /// parent folder holding gltf/glb assets
     folder: String,
@@ -49,3 +52,222 @@ impl GltfFile {
         &self.document
     }
 }
+
+/// A parsed COLLADA (.dae) file
+/// the collada crate walks the whole xml tree on every query, so everything is read once here
+pub struct ColladaFile {
+    /// parent folder holding the dae file and its images
+    folder: String,
+    /// name of the dae file without its extension, used to name its clip
+    name: String,
+
+    document: ColladaDocument,
+    objects: Vec<collada::Object>,
+    /// only the first skeleton of a file is read by the collada crate
+    skeleton: Option<collada::Skeleton>,
+    animations: Vec<collada::Animation>,
+}
+
+impl ColladaFile {
+    pub fn load_collada(folder: &Path) -> Result<ColladaFile, Error> {
+        let path = find_file(folder, &["dae"])?;
+
+        let mut document = ColladaDocument::from_path(&path)
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+        flatten_animations(&mut document.root_element);
+
+        let objects = match document.get_obj_set() {
+            Some(set) => set.objects,
+            None => Vec::new(),
+        };
+        let skeleton = document
+            .get_skeletons()
+            .and_then(|skeletons| skeletons.into_iter().next());
+        let animations = document.get_animations().unwrap_or_default();
+
+        Ok(ColladaFile {
+            folder: String::from(folder.to_str().unwrap()),
+            name: path
+                .file_stem()
+                .map_or(String::new(), |stem| stem.to_string_lossy().into_owned()),
+            document,
+            objects,
+            skeleton,
+            animations,
+        })
+    }
+
+    pub fn get_folder(&self) -> &String {
+        &self.folder
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_document(&self) -> &ColladaDocument {
+        &self.document
+    }
+
+    pub fn get_objects(&self) -> &[collada::Object] {
+        &self.objects
+    }
+
+    pub fn get_skeleton(&self) -> Option<&collada::Skeleton> {
+        self.skeleton.as_ref()
+    }
+
+    pub fn get_animations(&self) -> &[collada::Animation] {
+        &self.animations
+    }
+
+    /// namespace of the document, needed to look up its elements
+    pub fn get_ns(&self) -> Option<&str> {
+        self.document.root_element.ns.as_deref()
+    }
+
+    /// top level library element, like library_images or library_effects
+    pub fn get_library(&self, name: &str) -> Option<&Element> {
+        self.document.root_element.get_child(name, self.get_ns())
+    }
+}
+
+/// Either kind of model file the viewer can open
+pub enum ModelFile {
+    Gltf(GltfFile),
+    Collada(ColladaFile),
+}
+
+impl ModelFile {
+    /// load the model in `folder`, dae files are used when the folder has no gltf or glb file
+    pub fn load(folder: &Path) -> Result<ModelFile, Error> {
+        if find_file(folder, &["gltf", "glb"]).is_err() && find_file(folder, &["dae"]).is_ok() {
+            return Ok(ModelFile::Collada(ColladaFile::load_collada(folder)?));
+        }
+
+        Ok(ModelFile::Gltf(GltfFile::load_gltf(folder)?))
+    }
+}
+
+/// first file in `folder` with one of the extensions
+fn find_file(folder: &Path, extensions: &[&str]) -> Result<std::path::PathBuf, Error> {
+    for entry in fs::read_dir(folder)? {
+        let path = entry?.path();
+
+        let extension = path.extension();
+        if extension.is_some_and(|extension| extensions.iter().any(|ext| extension.eq(*ext))) {
+            return Ok(path);
+        }
+    }
+
+    Err(Error::new(
+        ErrorKind::NotFound,
+        format!("no {} file in {}", extensions.join("/"), folder.display()),
+    ))
+}
+
+/// exporters like the one behind mixamo group the channels of a joint in nested animations,
+/// the collada crate only reads animations holding a channel directly and panics on the others
+fn flatten_animations(root: &mut Element) {
+    let ns = root.ns.clone();
+    let library = root.children.iter_mut().find_map(|child| match child {
+        Xml::ElementNode(element) if element.name == "library_animations" => Some(element),
+        _ => None,
+    });
+    let library = match library {
+        Some(library) => library,
+        None => return,
+    };
+
+    let mut leaves = Vec::new();
+    for child in &library.children {
+        match child {
+            Xml::ElementNode(element) if element.name == "animation" => {
+                collect_channels(element, ns.as_deref(), &mut leaves);
+            }
+            _ => {}
+        }
+    }
+
+    library.children.retain(|child| match child {
+        Xml::ElementNode(element) => element.name != "animation",
+        _ => true,
+    });
+    library
+        .children
+        .extend(leaves.into_iter().map(Xml::ElementNode));
+}
+
+/// animations holding a channel, animations with several channels are split so each one has one
+fn collect_channels(animation: &Element, ns: Option<&str>, out: &mut Vec<Element>) {
+    let channels = animation.get_children("channel", ns).count();
+    if channels == 1 {
+        out.push(animation.clone());
+    } else if channels > 1 {
+        for channel in animation.get_children("channel", ns) {
+            let mut single = animation.clone();
+            single.children.retain(|child| match child {
+                Xml::ElementNode(element) => element.name != "channel",
+                _ => true,
+            });
+            // the crate reads the first sampler it finds, keep only the one of this channel
+            let sampler = channel.get_attribute("source", None).unwrap_or_default();
+            single.children.retain(|child| match child {
+                Xml::ElementNode(element) if element.name == "sampler" => {
+                    format!("#{}", element.get_attribute("id", None).unwrap_or_default()) == sampler
+                }
+                _ => true,
+            });
+            single.tag(channel.clone());
+            out.push(single);
+        }
+    }
+
+    for child in animation.get_children("animation", ns) {
+        collect_channels(child, ns, out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// mixamo style file, a container animation groups the joints and one joint holds two channels
+    fn fixture() -> ColladaFile {
+        let folder = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("models/fixtures/mixamo");
+        ColladaFile::load_collada(&folder).unwrap()
+    }
+
+    #[test]
+    fn nested_animations_are_split_into_single_channels() {
+        let file = fixture();
+        let animations = file.get_animations();
+
+        let targets = animations
+            .iter()
+            .map(|animation| animation.target.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(
+            targets,
+            [
+                "mixamorig_Hips/transform",
+                "mixamorig_LeftArm/transform",
+                "mixamorig_RightArm/transform",
+                "mixamorig_Head/transform",
+            ]
+        );
+
+        // every channel keeps the sampler it points at, not the first one of its animation
+        let keys = animations
+            .iter()
+            .map(|animation| animation.sample_times.len())
+            .collect::<Vec<usize>>();
+        assert_eq!(keys, [2, 3, 4, 1]);
+
+        let right_arm = &animations[2];
+        assert_eq!(right_arm.sample_times, [0.0, 0.25, 0.5, 1.0]);
+        assert_eq!(right_arm.sample_poses[3][0][3], 3.0);
+    }
+}
@@ -1,3 +1,10 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use collada::document::{Diffuse, MaterialEffect};
+
+use super::loader::ColladaFile;
+
 /// CPU representation of a material
 /// pbr material with textures and factors
 #[derive(Clone, Debug, PartialEq)]
@@ -49,3 +56,105 @@ impl Material {
         }
     }
 }
+
+/// materials of a COLLADA file and the index of each material id
+/// images: see images_from_collada, texture indices follow its order
+pub fn materials_from_collada(
+    file: &ColladaFile,
+    images: &[(String, PathBuf)],
+) -> (Vec<Material>, HashMap<String, usize>) {
+    let mut materials = Vec::new();
+    let mut indices = HashMap::new();
+
+    // the crate panics on documents without these libraries
+    if file.get_library("library_materials").is_none()
+        || file.get_library("library_effects").is_none()
+    {
+        return (materials, indices);
+    }
+
+    let document = file.get_document();
+    let effects = document.get_effect_library();
+    let mut material_effects = document
+        .get_material_to_effect()
+        .into_iter()
+        .collect::<Vec<(String, String)>>();
+    material_effects.sort();
+
+    for (material, effect_id) in material_effects {
+        if let Some(effect) = effects.get(&effect_id) {
+            indices.insert(material, materials.len());
+            materials.push(Material::from_collada(effect, &effect_id, file, images));
+        }
+    }
+
+    (materials, indices)
+}
+
+impl Material {
+    /// COLLADA materials are phong or lambert, the closest rough dielectric is used
+    pub fn from_collada(
+        effect: &MaterialEffect,
+        effect_id: &str,
+        file: &ColladaFile,
+        images: &[(String, PathBuf)],
+    ) -> Self {
+        let (diffuse, roughness_factor) = match effect {
+            // the usual blinn phong to roughness conversion
+            MaterialEffect::Phong(phong) => (
+                &phong.diffuse,
+                (2.0 / (phong.shininess.max(0.0) + 2.0)).sqrt(),
+            ),
+            MaterialEffect::Lambert(lambert) => (&lambert.diffuse, 1.0),
+        };
+
+        let mut material = Self {
+            metallic_factor: 0.0,
+            roughness_factor,
+            ..Default::default()
+        };
+
+        match diffuse {
+            Diffuse::Color(color) => material.base_color_factor = *color,
+            Diffuse::Texture(sampler) => {
+                let image = image_of_sampler(file, effect_id, sampler);
+                material.base_color_texture = images.iter().position(|(id, _)| *id == image);
+            }
+        }
+
+        material
+    }
+}
+
+/// follow the sampler2D and surface params of an effect down to the id of their image
+/// some exporters name the image directly, then the sampler is returned as is
+fn image_of_sampler(file: &ColladaFile, effect_id: &str, sampler: &str) -> String {
+    let ns = file.get_ns();
+    let effect = file.get_library("library_effects").and_then(|library| {
+        library
+            .get_children("effect", ns)
+            .find(|effect| effect.get_attribute("id", None) == Some(effect_id))
+    });
+    let profile = match effect.and_then(|effect| effect.get_child("profile_COMMON", ns)) {
+        Some(profile) => profile,
+        None => return String::from(sampler),
+    };
+
+    let param = |sid: &str, kind: &str, child: &str| {
+        profile
+            .get_children("newparam", ns)
+            .find(|param| param.get_attribute("sid", None) == Some(sid))
+            .and_then(|param| param.get_child(kind, ns))
+            .and_then(|kind| kind.get_child(child, ns))
+            .map(|child| child.content_str().trim().to_string())
+    };
+
+    let surface = match param(sampler, "sampler2D", "source") {
+        Some(surface) => surface,
+        None => return String::from(sampler),
+    };
+    match param(&surface, "surface", "init_from") {
+        Some(image) => image,
+        None => String::from(sampler),
+    }
+}
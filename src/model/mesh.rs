@@ -1,22 +1,60 @@
-use super::loader::GltfFile;
+use std::collections::HashMap;
+
+use super::loader::{ColladaFile, GltfFile};
 
 use super::primitive::Primitive;
+use crate::src::renderer::managers::shader::SkinningMode;
 
 #[derive(Clone)]
 pub struct Mesh {
     primitives: Vec<Primitive>,
+    /// default morph target weights, shared by every primitive
+    weights: Vec<f32>,
 }
 
 impl Mesh {
-    pub fn from_gltf(gl: &glow::Context, mesh: &gltf::Mesh, file: &GltfFile) -> Self {
+    pub fn from_gltf(
+        gl: &glow::Context,
+        mesh: &gltf::Mesh,
+        file: &GltfFile,
+        skinning_mode: SkinningMode,
+    ) -> Self {
         Self {
             primitives: mesh
                 .primitives()
-                .map(|primitive| Primitive::from_gltf(gl, &primitive, file))
+                .map(|primitive| Primitive::from_gltf(gl, &primitive, file, skinning_mode))
+                .collect::<Vec<Primitive>>(),
+            weights: mesh
+                .weights()
+                .map_or(Vec::new(), |weights| weights.to_vec()),
+        }
+    }
+
+    /// materials: index of every COLLADA material id, see materials_from_collada
+    pub fn from_collada(
+        gl: &glow::Context,
+        object: &collada::Object,
+        file: &ColladaFile,
+        materials: &HashMap<String, usize>,
+        skinning_mode: SkinningMode,
+    ) -> Self {
+        Self {
+            primitives: object
+                .geometry
+                .iter()
+                .flat_map(|geometry| &geometry.mesh)
+                .filter_map(|element| {
+                    Primitive::from_collada(gl, object, element, file, materials, skinning_mode)
+                })
                 .collect::<Vec<Primitive>>(),
+            weights: Vec::new(),
         }
     }
 
+    pub fn get_weights(&self) -> &[f32] {
+        &self.weights
+    }
+
     pub fn get_primitive(&self, index: usize) -> &Primitive {
         &self.primitives[index]
     }
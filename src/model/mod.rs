@@ -1,8 +1,12 @@
+pub mod clip;
+pub mod ground;
 pub mod loader;
 pub mod material;
 pub mod mesh;
 pub mod node;
+pub mod node_animation;
 pub mod primitive;
+pub mod rig;
 pub mod scene;
 pub mod skeleton;
 pub mod skin;
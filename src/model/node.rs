@@ -1,4 +1,14 @@
-use math::{quaternion::Quat, transform::Transform, vec3::Vec3};
+use math::{
+    mat4::{Mat4, inverse},
+    quaternion::Quat,
+    transform::Transform,
+    vec3::{Vec3, vec3},
+};
+
+use super::loader::ColladaFile;
+
+/// node holding the up axis and unit of a COLLADA file, parent of everything else
+pub const COLLADA_ROOT: usize = 0;
 
 #[derive(Clone)]
 pub struct Node {
@@ -7,6 +17,8 @@ pub struct Node {
     pub skin: Option<usize>,
     pub mesh: Option<usize>,
     pub transform: Transform,
+    /// morph target weights replacing the default weights of the mesh
+    pub weights: Option<Vec<f32>>,
 }
 
 impl Node {
@@ -17,6 +29,7 @@ impl Node {
             skin: None,
             mesh: None,
             transform: Transform::DEFAULT,
+            weights: None,
         }
     }
 
@@ -51,6 +64,7 @@ impl Node {
             skin,
             mesh,
             transform,
+            weights: node.weights().map(|weights| weights.to_vec()),
         }
     }
 
@@ -70,3 +84,237 @@ impl Node {
         self.children = children;
     }
 }
+
+/// index of the node holding the specified node as a child
+pub fn find_parent(nodes: &[Node], index: usize) -> Option<usize> {
+    nodes.iter().position(|node| node.children.contains(&index))
+}
+
+/// transform of a node relative to the scene root
+pub fn global_transform(nodes: &[Node], index: usize) -> Transform {
+    let mut result = nodes[index].transform;
+    let mut current = index;
+
+    while let Some(parent) = find_parent(nodes, current) {
+        result = Transform::combine(&nodes[parent].transform, &result);
+        current = parent;
+    }
+
+    result
+}
+
+/// nodes of a COLLADA file, the collada crate does not expose the scene hierarchy
+/// so they are rebuilt as COLLADA_ROOT, the joints of the skeleton in skeleton order,
+/// the parent of the root joints when there is a skeleton, then one node per mesh
+pub fn nodes_from_collada(file: &ColladaFile) -> Vec<Node> {
+    let mut root = Node::new();
+    root.with_name(String::from("collada_root"));
+    root.transform = root_transform_from_collada(file);
+
+    let mut nodes = vec![root];
+
+    if let Some(skeleton) = file.get_skeleton() {
+        // parent of the root joints, right after the joints
+        let armature = skeleton.joints.len() + 1;
+
+        for (i, joint) in skeleton.joints.iter().enumerate() {
+            let mut node = Node::new();
+            node.with_name(joint.name.clone());
+            node.transform = transform_from_collada(&skeleton.bind_poses[i]);
+
+            let parent = match joint.is_root() {
+                true => armature,
+                false => joint.parent_index as usize + 1,
+            };
+            if parent != armature {
+                nodes[parent].children.push(i + 1);
+            }
+            nodes.push(node);
+        }
+
+        let mut node = Node::new();
+        node.with_name(String::from("collada_armature"));
+        node.transform = armature_transform(skeleton);
+        node.with_children(
+            (0..skeleton.joints.len())
+                .filter(|joint| skeleton.joints[*joint].is_root())
+                .map(|joint| joint + 1)
+                .collect::<Vec<usize>>(),
+        );
+        nodes[COLLADA_ROOT].children.push(armature);
+        nodes.push(node);
+    }
+
+    for (i, object) in file.get_objects().iter().enumerate() {
+        let mut node = Node::new();
+        node.with_name(object.name.clone());
+        node.with_mesh(i);
+        if !object.joint_weights.is_empty() {
+            node.with_skin(0);
+        }
+
+        let index = nodes.len();
+        nodes[COLLADA_ROOT].children.push(index);
+        nodes.push(node);
+    }
+
+    nodes
+}
+
+/// the collada crate skips the nodes above the skeleton, their transform is found again
+/// from the bind pose of the first root joint, assuming the file is saved in its bind pose
+fn armature_transform(skeleton: &collada::Skeleton) -> Transform {
+    let root = match skeleton.joints.iter().position(|joint| joint.is_root()) {
+        Some(root) => root,
+        None => return Transform::DEFAULT,
+    };
+
+    // joints missing from the skin get an identity inverse bind pose, which says nothing
+    let inverse_bind_pose = Mat4::from(&skeleton.joints[root].inverse_bind_pose);
+    if inverse_bind_pose.data == Mat4::IDENTITY.data {
+        return Transform::DEFAULT;
+    }
+
+    let bind_pose = inverse(&inverse_bind_pose);
+    let local = transform_from_collada(&skeleton.bind_poses[root]);
+    transform_from_collada(&bind_pose.data).combine(&local.inverse())
+}
+
+/// COLLADA matrices are written row after row, like Mat4
+/// the scale is taken out of the rotation columns before they become a quaternion
+pub fn transform_from_collada(matrix: &[[f32; 4]; 4]) -> Transform {
+    let mat = Mat4::from(matrix);
+    let d = &mat.data;
+
+    let column = |i: usize| vec3(d[0][i], d[1][i], d[2][i]);
+    let scaling = vec3(column(0).len(), column(1).len(), column(2).len());
+
+    let mut rotation = Mat4::IDENTITY;
+    for i in 0..3 {
+        let axis = column(i).unit();
+        rotation.data[0][i] = axis.x;
+        rotation.data[1][i] = axis.y;
+        rotation.data[2][i] = axis.z;
+    }
+
+    Transform::new(
+        scaling,
+        vec3(d[0][3], d[1][3], d[2][3]),
+        rotation.to_quat().unit(),
+    )
+}
+
+/// turn the file into our y up meters, mixamo exports are in centimeters
+fn root_transform_from_collada(file: &ColladaFile) -> Transform {
+    let mut transform = Transform::DEFAULT;
+    let asset = match file
+        .get_document()
+        .root_element
+        .get_child("asset", file.get_ns())
+    {
+        Some(asset) => asset,
+        None => return transform,
+    };
+
+    let meter = asset
+        .get_child("unit", file.get_ns())
+        .and_then(|unit| unit.get_attribute("meter", None))
+        .and_then(|meter| meter.parse::<f32>().ok());
+    if let Some(meter) = meter {
+        transform.scaling = vec3(meter, meter, meter);
+    }
+
+    if let Some(up_axis) = asset.get_child("up_axis", file.get_ns()) {
+        transform.orientation = match up_axis.content_str().trim() {
+            "Z_UP" => Quat::create(-90.0, vec3(1.0, 0.0, 0.0)),
+            "X_UP" => Quat::create(90.0, vec3(0.0, 0.0, 1.0)),
+            _ => Quat::ZERO,
+        };
+    }
+
+    transform
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use collada::{Joint, ROOT_JOINT_PARENT_INDEX, Skeleton};
+    use math::mat4::{rotation_z, scale, translate};
+
+    use super::*;
+
+    const EPSILON: f32 = 1e-4;
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).len() < EPSILON, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn root_transform_turns_z_up_centimeters_into_y_up_meters() {
+        // centimeters and z up, like the files exported by mixamo
+        let folder = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("models/fixtures/mixamo");
+        let root = root_transform_from_collada(&ColladaFile::load_collada(&folder).unwrap());
+
+        assert_close(root.scaling, vec3(0.01, 0.01, 0.01));
+        assert_close(root.translation, Vec3::ZERO);
+
+        // z goes up and -y comes towards the camera
+        let rotation = root.orientation.to_mat();
+        assert_close(
+            rotation.transform_vector(&vec3(0.0, 0.0, 1.0)),
+            vec3(0.0, 1.0, 0.0),
+        );
+        assert_close(
+            rotation.transform_vector(&vec3(0.0, -1.0, 0.0)),
+            vec3(0.0, 0.0, 1.0),
+        );
+    }
+
+    #[test]
+    fn scaled_matrices_split_into_translation_rotation_and_scale() {
+        let matrix =
+            translate(&vec3(1.0, 2.0, 3.0)) * rotation_z(30.0) * scale(&vec3(2.0, 0.5, 4.0));
+
+        let transform = transform_from_collada(&matrix.data);
+        assert_close(transform.scaling, vec3(2.0, 0.5, 4.0));
+        assert_close(transform.translation, vec3(1.0, 2.0, 3.0));
+
+        let expected = Quat::create(30.0, vec3(0.0, 0.0, 1.0));
+        assert!(transform.orientation.dot(&expected).abs() > 1.0 - EPSILON);
+
+        let point = vec3(0.5, -1.0, 2.0);
+        assert_close(
+            transform.to_mat().transform_point(&point),
+            matrix.transform_point(&point),
+        );
+    }
+
+    #[test]
+    fn armature_transform_is_found_from_the_root_bind_pose() {
+        let armature = translate(&vec3(0.0, 1.0, 0.0)) * rotation_z(90.0);
+        let local = translate(&vec3(2.0, 0.0, 0.0));
+        let bind_pose = armature * local;
+
+        let mut skeleton = Skeleton {
+            joints: vec![Joint {
+                name: String::from("mixamorig_Hips"),
+                parent_index: ROOT_JOINT_PARENT_INDEX,
+                inverse_bind_pose: inverse(&bind_pose).data,
+            }],
+            bind_poses: vec![local.data],
+        };
+
+        let transform = armature_transform(&skeleton);
+        let point = vec3(0.5, -1.0, 2.0);
+        assert_close(
+            transform.to_mat().transform_point(&point),
+            armature.transform_point(&point),
+        );
+
+        // joints missing from the skin say nothing about the armature
+        skeleton.joints[0].inverse_bind_pose = Mat4::IDENTITY.data;
+        let transform = armature_transform(&skeleton);
+        assert_eq!(transform.to_mat().data, Mat4::IDENTITY.data);
+    }
+}
@@ -0,0 +1,132 @@
+use animation::{
+    clip::Clip, controller::AnimationController, morph::MorphWeights, pose::Pose,
+    skeleton::Skeleton,
+};
+
+use super::loader::GltfFile;
+use super::node::{Node, find_parent};
+use super::{clip::clips_from_gltf, skin::Skin};
+
+/// Plays the animations of plain nodes, like doors and propellers
+/// every node of the file is a joint of the player so clips are keyed by node index,
+/// skin joints and skinned meshes are left to the controller of their model
+#[derive(Clone)]
+pub struct NodeAnimationPlayer {
+    pub controller: AnimationController,
+    /// nodes with a track in at least one clip, the only ones written back
+    animated: Vec<usize>,
+    /// clip last asked for with play_next_clip
+    clip: usize,
+}
+
+impl NodeAnimationPlayer {
+    /// fails when the node hierarchy is not a tree
+    pub fn new(nodes: &[Node], skins: &[Skin], mut clips: Vec<Clip>) -> Result<Self, String> {
+        let skinned = |node: usize| {
+            nodes[node].skin.is_some() || skins.iter().any(|skin| skin.joints.contains(&node))
+        };
+
+        let mut animated = Vec::new();
+        for clip in &mut clips {
+            clip.tracks.retain(|track| !skinned(track.id as usize));
+            clip.weights.retain(|track| !skinned(track.id as usize));
+            clip.re_calculate_duration();
+
+            for track in &clip.tracks {
+                if !animated.contains(&(track.id as usize)) {
+                    animated.push(track.id as usize);
+                }
+            }
+        }
+
+        let mut controller = AnimationController::try_new(build_node_skeleton(nodes))?;
+        for clip in clips {
+            controller.add_clip(clip);
+        }
+        if controller.clip_count() > 0 {
+            controller.play(0)?;
+        }
+
+        Ok(Self {
+            controller,
+            animated,
+            clip: 0,
+        })
+    }
+
+    pub fn from_gltf(file: &GltfFile, nodes: &[Node], skins: &[Skin]) -> Result<Self, String> {
+        let all = (0..nodes.len()).collect::<Vec<usize>>();
+        Self::new(nodes, skins, clips_from_gltf(file, &all, None))
+    }
+
+    /// weights a node keeps while no clip animates them
+    pub fn set_rest_weights(&mut self, node: usize, weights: &[f32]) {
+        self.controller.set_rest_weights(node as u32, weights);
+    }
+
+    /// true when some clip moves a node or changes its morph target weights
+    pub fn is_animated(&self) -> bool {
+        (0..self.controller.clip_count()).any(|index| {
+            let clip = self.controller.get_clip(index);
+            !clip.tracks.is_empty() || !clip.weights.is_empty()
+        })
+    }
+
+    /// advance the animation and write the sampled transforms into the nodes
+    pub fn update(&mut self, delta_time: f32, nodes: &mut [Node]) {
+        self.controller.update(delta_time);
+        for err in self.controller.drain_errors() {
+            println!("unable to sample clip {}: {}", err.clip_index, err.error);
+        }
+
+        let pose = self.controller.get_pose();
+        for index in &self.animated {
+            nodes[*index].transform = pose.joints[*index];
+        }
+    }
+
+    pub fn get_weights(&self) -> &MorphWeights {
+        self.controller.get_weights()
+    }
+
+    pub fn play_next_clip(&mut self) {
+        let count = self.controller.clip_count();
+        if count == 0 {
+            return;
+        }
+
+        self.clip = (self.clip + 1) % count;
+        if let Err(err) = self.controller.crossfade_to(self.clip, 0.3) {
+            println!("unable to play the next clip: {}", err);
+        }
+    }
+}
+
+/// skeleton with a joint per node, the rest pose is the transform stored in each node
+fn build_node_skeleton(nodes: &[Node]) -> Skeleton {
+    let mut skeleton = Skeleton::new();
+
+    let mut rest_pose = Pose::new();
+    rest_pose.resize(nodes.len());
+
+    for (i, node) in nodes.iter().enumerate() {
+        rest_pose.joints[i] = node.transform;
+        rest_pose.parents[i] = match find_parent(nodes, i) {
+            Some(parent) => parent as i32,
+            None => -1,
+        };
+
+        let name = match &node.name {
+            Some(name) => name.clone(),
+            None => format!("node_{}", i),
+        };
+        skeleton.joint_names.push(name);
+
+        // nodes are never skinned against, they have no bind pose
+        skeleton.inverse_bind_pose.push(None);
+    }
+
+    skeleton.rest_pose = rest_pose;
+
+    skeleton
+}
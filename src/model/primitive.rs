@@ -1,7 +1,15 @@
-use super::loader::GltfFile;
+use std::collections::HashMap;
+
+use super::loader::{ColladaFile, GltfFile};
 
 use super::vertex::Vertex;
+use crate::src::renderer::managers::shader::SkinningMode;
+use animation::morph::{MAX_MORPH_TARGETS, MorphTarget};
 use glow::HasContext;
+use math::{dual_quat::DualQuat, mat4::Mat4};
+
+/// width of the morph target texture, rows are filled one after the other
+pub const MORPH_TEXTURE_WIDTH: usize = 2048;
 
 #[derive(Debug, Clone, Copy)]
 pub struct BoundingBox {
@@ -23,18 +31,46 @@ pub struct Primitive {
     vertex_count: u32,
 
     bounds: BoundingBox,
+
+    /// bind pose vertices of skinned and morphed primitives, only kept for cpu skinning
+    bind_vertices: Option<Vec<Vertex>>,
+    skinned: bool,
+
+    morph_targets: Vec<MorphTarget>,
+    /// position and normal offsets of the morph targets read by shader.vert,
+    /// None without targets or when the context has no float textures
+    morph_texture: Option<glow::NativeTexture>,
 }
 
 impl Primitive {
     // upload CPU mesh data to GPU and create VAO, VBO, EBO
 
-    pub fn from_gltf(gl: &glow::Context, primitive: &gltf::Primitive, file: &GltfFile) -> Self {
+    pub fn from_gltf(
+        gl: &glow::Context,
+        primitive: &gltf::Primitive,
+        file: &GltfFile,
+        skinning_mode: SkinningMode,
+    ) -> Self {
         let data = primitive_data_from_gltf(primitive, file);
 
-        Self::upload(gl, &data)
+        Self::upload(gl, &data, skinning_mode)
+    }
+
+    /// None when the element has no triangles
+    pub fn from_collada(
+        gl: &glow::Context,
+        object: &collada::Object,
+        element: &collada::PrimitiveElement,
+        file: &ColladaFile,
+        materials: &HashMap<String, usize>,
+        skinning_mode: SkinningMode,
+    ) -> Option<Self> {
+        let data = primitive_data_from_collada(object, element, file, materials)?;
+
+        Some(Self::upload(gl, &data, skinning_mode))
     }
 
-    fn upload(gl: &glow::Context, data: &PrimitiveData) -> Self {
+    fn upload(gl: &glow::Context, data: &PrimitiveData, skinning_mode: SkinningMode) -> Self {
         unsafe {
             let vao = gl.create_vertex_array().expect("could not create vao");
             let vbo = gl.create_buffer().expect("could not create vbo");
@@ -70,6 +106,22 @@ impl Primitive {
             gl.bind_buffer(glow::ARRAY_BUFFER, None);
             gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, None);
 
+            // the gpu path deforms the uploaded vertices, it never reads them back
+            let mut bind_vertices: Option<Vec<Vertex>> = None;
+            let deformed = data.skinned || !data.morph_targets.is_empty();
+            if skinning_mode == SkinningMode::Cpu && deformed {
+                bind_vertices = Some(data.vertices.clone());
+            }
+
+            let mut morph_texture: Option<glow::NativeTexture> = None;
+            if !data.morph_targets.is_empty() && gl.version().major >= 3 {
+                morph_texture = Some(upload_morph_targets(
+                    gl,
+                    &data.morph_targets,
+                    data.vertices.len(),
+                ));
+            }
+
             Self {
                 bounds: data.bounds,
                 vao,
@@ -79,10 +131,77 @@ impl Primitive {
                 material: data.material,
                 index_count,
                 vertex_count,
+                bind_vertices,
+                skinned: data.skinned,
+                morph_targets: data.morph_targets.clone(),
+                morph_texture,
             }
         }
     }
 
+    /// skin the bind pose vertices on the cpu and stream them into the vertex buffer
+    /// morph targets are applied first, `weights` holds the weight of each target
+    /// does nothing for primitives without joints and weights
+    pub fn update_skinning(&self, gl: &glow::Context, palette: &[Mat4], weights: &[f32]) {
+        self.stream_vertices(gl, weights, |vertex| vertex.skinned(palette));
+    }
+
+    /// same as update_skinning with dual quaternion skinning
+    pub fn update_dual_quat_skinning(
+        &self,
+        gl: &glow::Context,
+        palette: &[DualQuat],
+        weights: &[f32],
+    ) {
+        self.stream_vertices(gl, weights, |vertex| vertex.skinned_dual_quat(palette));
+    }
+
+    /// apply the morph targets of a primitive that is not skinned on the cpu
+    pub fn update_morphing(&self, gl: &glow::Context, weights: &[f32]) {
+        self.stream_vertices(gl, weights, |vertex| *vertex);
+    }
+
+    /// the vertex buffer belongs to the mesh, every node drawing the mesh shares it
+    /// so the vertices have to be streamed right before each draw, like render_node does,
+    /// or every instance ends up drawn with the pose of the last node
+    fn stream_vertices(
+        &self,
+        gl: &glow::Context,
+        weights: &[f32],
+        skin: impl Fn(&Vertex) -> Vertex,
+    ) {
+        if let Some(bind_vertices) = &self.bind_vertices {
+            let vertices = bind_vertices
+                .iter()
+                .enumerate()
+                .map(|(i, vertex)| skin(&vertex.morphed(i, &self.morph_targets, weights)))
+                .collect::<Vec<Vertex>>();
+
+            unsafe {
+                gl.bind_buffer(glow::ARRAY_BUFFER, Some(self.vbo));
+                // respecifying the whole store lets the driver orphan the old one
+                gl.buffer_data_u8_slice(
+                    glow::ARRAY_BUFFER,
+                    bytemuck::cast_slice(&vertices),
+                    glow::STREAM_DRAW,
+                );
+                gl.bind_buffer(glow::ARRAY_BUFFER, None);
+            }
+        }
+    }
+
+    pub fn get_morph_targets(&self) -> &[MorphTarget] {
+        &self.morph_targets
+    }
+
+    pub fn get_morph_texture(&self) -> Option<glow::NativeTexture> {
+        self.morph_texture
+    }
+
+    pub fn is_skinned(&self) -> bool {
+        self.skinned
+    }
+
     pub fn draw(&self, gl: &glow::Context) {
         unsafe {
             gl.bind_vertex_array(Some(self.vao));
@@ -109,6 +228,10 @@ impl Primitive {
             if let Some(ebo) = self.ebo {
                 gl.delete_buffer(ebo);
             }
+
+            if let Some(texture) = self.morph_texture {
+                gl.delete_texture(texture);
+            }
         }
     }
 
@@ -139,6 +262,8 @@ struct PrimitiveData {
     material: Option<usize>,
     mode: u32,
     bounds: BoundingBox,
+    skinned: bool,
+    morph_targets: Vec<MorphTarget>,
 }
 
 fn primitive_data_from_gltf(primitive: &gltf::Primitive, file: &GltfFile) -> PrimitiveData {
@@ -151,6 +276,7 @@ fn primitive_data_from_gltf(primitive: &gltf::Primitive, file: &GltfFile) -> Pri
 
     let mut vertices = Vec::new();
     let mut indices: Option<Vec<u32>> = None;
+    let mut skinned = false;
 
     let reader = primitive.reader(|buffer| Some(&file.get_buffers()[buffer.index()]));
 
@@ -197,17 +323,221 @@ fn primitive_data_from_gltf(primitive: &gltf::Primitive, file: &GltfFile) -> Pri
         joints.into_u16().enumerate().for_each(|(i, joint_batch)| {
             vertices[i].joints = joint_batch.map(|joint| joint as i32)
         });
+        skinned = true;
     }
 
     if let Some(indices_reader) = reader.read_indices() {
         indices = Some(indices_reader.into_u32().collect::<Vec<u32>>());
     }
 
+    let morph_targets = reader
+        .read_morph_targets()
+        .map(|(positions, normals, tangents)| MorphTarget {
+            positions: positions.map_or(Vec::new(), |positions| positions.collect()),
+            normals: normals.map_or(Vec::new(), |normals| normals.collect()),
+            tangents: tangents.map_or(Vec::new(), |tangents| tangents.collect()),
+        })
+        .collect::<Vec<MorphTarget>>();
+    if morph_targets.len() > MAX_MORPH_TARGETS {
+        println!(
+            "a primitive in folder {} has {} morph targets, only the first {} are drawn on the gpu",
+            file.get_folder(),
+            morph_targets.len(),
+            MAX_MORPH_TARGETS
+        );
+    }
+
     PrimitiveData {
         vertices,
         indices,
         material,
         mode,
         bounds,
+        skinned,
+        morph_targets,
+    }
+}
+
+/// COLLADA indexes positions, normals and uvs separately,
+/// every distinct combination used by a corner becomes a vertex
+fn primitive_data_from_collada(
+    object: &collada::Object,
+    element: &collada::PrimitiveElement,
+    file: &ColladaFile,
+    materials: &HashMap<String, usize>,
+) -> Option<PrimitiveData> {
+    let (corners, material) = match element {
+        collada::PrimitiveElement::Triangles(triangles) => {
+            let corners = triangles
+                .vertices
+                .iter()
+                .enumerate()
+                .flat_map(|(i, vertices)| {
+                    let uvs = triangles.tex_vertices.as_ref().map(|uvs| uvs[i]);
+                    let normals = triangles.normals.as_ref().map(|normals| normals[i]);
+                    [
+                        (
+                            vertices.0,
+                            uvs.map(|uv| uv.0),
+                            normals.map(|normal| normal.0),
+                        ),
+                        (
+                            vertices.1,
+                            uvs.map(|uv| uv.1),
+                            normals.map(|normal| normal.1),
+                        ),
+                        (
+                            vertices.2,
+                            uvs.map(|uv| uv.2),
+                            normals.map(|normal| normal.2),
+                        ),
+                    ]
+                })
+                .collect::<Vec<collada::VTNIndex>>();
+            (corners, &triangles.material)
+        }
+        collada::PrimitiveElement::Polylist(polylist) => {
+            // the crate turns polygons with more than 3 corners into points, they are lost
+            let corners = polylist
+                .shapes
+                .iter()
+                .filter_map(|shape| match shape {
+                    collada::Shape::Triangle(a, b, c) => Some([*a, *b, *c]),
+                    _ => None,
+                })
+                .flatten()
+                .collect::<Vec<collada::VTNIndex>>();
+            if corners.len() / 3 < polylist.shapes.len() {
+                println!(
+                    "{} of the polygons of {} in folder {} are not triangles and were skipped",
+                    polylist.shapes.len() - corners.len() / 3,
+                    object.name,
+                    file.get_folder()
+                );
+            }
+            (corners, &polylist.material)
+        }
+    };
+
+    if corners.is_empty() {
+        return None;
+    }
+
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    let mut unique: HashMap<collada::VTNIndex, u32> = HashMap::new();
+    let skinned = !object.joint_weights.is_empty();
+
+    for corner in corners {
+        let index = *unique.entry(corner).or_insert_with(|| {
+            let (position, uv, normal) = corner;
+            let p = &object.vertices[position];
+            let mut vertex = Vertex {
+                position: [p.x as f32, p.y as f32, p.z as f32],
+                ..Default::default()
+            };
+
+            if let Some(normal) = normal.and_then(|normal| object.normals.get(normal)) {
+                vertex.normal = [normal.x as f32, normal.y as f32, normal.z as f32];
+            }
+
+            // COLLADA uvs start at the bottom of the image, gltf ones at the top
+            if let Some(uv) = uv.and_then(|uv| object.tex_vertices.get(uv)) {
+                vertex.uv = [uv.x as f32, 1.0 - uv.y as f32];
+            }
+
+            if let Some(joints) = object.joint_weights.get(position) {
+                // influences past the fourth are dropped by the crate, the rest still add up to 1
+                let sum = joints.weights.iter().sum::<f32>();
+                if sum > 0.0 {
+                    vertex.weights = joints.weights.map(|weight| weight / sum);
+                }
+                vertex.joints = joints.joints.map(|joint| joint as i32);
+            }
+
+            vertices.push(vertex);
+            vertices.len() as u32 - 1
+        });
+        indices.push(index);
+    }
+
+    let mut bounds = BoundingBox {
+        min: [f32::MAX; 3],
+        max: [f32::MIN; 3],
+    };
+    for vertex in &vertices {
+        for i in 0..3 {
+            bounds.min[i] = bounds.min[i].min(vertex.position[i]);
+            bounds.max[i] = bounds.max[i].max(vertex.position[i]);
+        }
+    }
+
+    Some(PrimitiveData {
+        vertices,
+        indices: Some(indices),
+        material: material
+            .as_ref()
+            .and_then(|material| materials.get(material).copied()),
+        mode: glow::TRIANGLES,
+        bounds,
+        skinned,
+        morph_targets: Vec::new(),
+    })
+}
+
+/// pack the position and normal offsets of every target into a float texture
+/// two texels per vertex and target, target after target, see shader.vert
+/// tangent offsets stay on the cpu since vertices have no tangents
+fn upload_morph_targets(
+    gl: &glow::Context,
+    targets: &[MorphTarget],
+    vertex_count: usize,
+) -> glow::NativeTexture {
+    let offset = |offsets: &[[f32; 3]], vertex: usize| {
+        let [x, y, z] = offsets.get(vertex).copied().unwrap_or([0.0; 3]);
+        [x, y, z, 0.0]
+    };
+
+    let mut texels = Vec::new();
+    for target in targets.iter().take(MAX_MORPH_TARGETS) {
+        for vertex in 0..vertex_count {
+            texels.push(offset(&target.positions, vertex));
+            texels.push(offset(&target.normals, vertex));
+        }
+    }
+
+    let width = texels.len().min(MORPH_TEXTURE_WIDTH);
+    let height = texels.len().div_ceil(MORPH_TEXTURE_WIDTH);
+    texels.resize(width * height, [0.0; 4]);
+
+    unsafe {
+        let texture = gl
+            .create_texture()
+            .expect("could not create morph target texture");
+        gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+        gl.tex_image_2d(
+            glow::TEXTURE_2D,
+            0,
+            glow::RGBA32F as i32,
+            width as i32,
+            height as i32,
+            0,
+            glow::RGBA,
+            glow::FLOAT,
+            glow::PixelUnpackData::Slice(Some(bytemuck::cast_slice(&texels))),
+        );
+        gl.tex_parameter_i32(
+            glow::TEXTURE_2D,
+            glow::TEXTURE_MIN_FILTER,
+            glow::NEAREST as i32,
+        );
+        gl.tex_parameter_i32(
+            glow::TEXTURE_2D,
+            glow::TEXTURE_MAG_FILTER,
+            glow::NEAREST as i32,
+        );
+        gl.bind_texture(glow::TEXTURE_2D, None);
+
+        texture
     }
 }
@@ -0,0 +1,75 @@
+use std::fs;
+use std::path::Path;
+
+use animation::{
+    clip::Clip,
+    retarget::{NameMap, Rig},
+};
+
+use super::clip::{clips_from_collada, clips_from_gltf};
+use super::loader::{ColladaFile, GltfFile, ModelFile};
+use super::node::{Node, nodes_from_collada};
+use super::skin::Skin;
+
+/// file in a model folder mapping its joint names to the shared humanoid names
+pub const NAME_MAP_FILE: &str = "humanoid.map";
+
+/// name map of the model in `folder`, models without one keep their joint names
+pub fn name_map_from_folder(folder: &str) -> NameMap {
+    let path = Path::new(folder).join(NAME_MAP_FILE);
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(_) => return NameMap::new(),
+    };
+
+    match NameMap::parse(&text) {
+        Ok(names) => names,
+        Err(err) => {
+            println!("unable to read {}: {}", path.display(), err);
+            NameMap::new()
+        }
+    }
+}
+
+/// rig of the first skin in the file and the clips made for it, to be retargeted onto other models
+pub fn clip_library_from_gltf(file: &GltfFile) -> Result<(Rig, Vec<Clip>), String> {
+    let doc = file.get_document();
+    let skin = match doc.skins().next() {
+        Some(skin) => Skin::from_gltf(&skin, file),
+        None => return Err(format!("{} has no skin", file.get_folder())),
+    };
+    let nodes = doc
+        .nodes()
+        .map(|node| Node::from_gltf(&node))
+        .collect::<Vec<Node>>();
+
+    let skeleton = skin.build_skeleton(&nodes);
+    let mut rig = Rig::new(skeleton, name_map_from_folder(file.get_folder()));
+    rig.space = skin.root_transform(&rig.skeleton, &nodes);
+
+    Ok((rig, clips_from_gltf(file, &skin.joints, None)))
+}
+
+/// same as clip_library_from_gltf for COLLADA files, like the animations downloaded from mixamo
+/// the collada crate only finds skeletons through a skin, so files without one cannot be used
+pub fn clip_library_from_collada(file: &ColladaFile) -> Result<(Rig, Vec<Clip>), String> {
+    let skin = match Skin::from_collada(file) {
+        Some(skin) => skin,
+        None => return Err(format!("{} has no skin", file.get_folder())),
+    };
+    let nodes = nodes_from_collada(file);
+
+    let skeleton = skin.build_skeleton(&nodes);
+    let mut rig = Rig::new(skeleton, name_map_from_folder(file.get_folder()));
+    rig.space = skin.root_transform(&rig.skeleton, &nodes);
+
+    let clips = clips_from_collada(file, &nodes, &skin.joints, None);
+    Ok((rig, clips))
+}
+
+pub fn clip_library_from_file(file: &ModelFile) -> Result<(Rig, Vec<Clip>), String> {
+    match file {
+        ModelFile::Gltf(file) => clip_library_from_gltf(file),
+        ModelFile::Collada(file) => clip_library_from_collada(file),
+    }
+}
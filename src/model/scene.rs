@@ -1,3 +1,5 @@
+use super::node::COLLADA_ROOT;
+
 #[derive(Clone)]
 pub struct Scene {
     pub nodes: Vec<usize>,
@@ -17,6 +19,13 @@ impl Scene {
         }
     }
 
+    /// COLLADA files hold a single scene below the root built by nodes_from_collada
+    pub fn from_collada() -> Self {
+        Self {
+            nodes: vec![COLLADA_ROOT],
+        }
+    }
+
     pub fn get_node(&self, index: usize) -> &usize {
         &self.nodes[index]
     }
@@ -1,9 +1,25 @@
+use animation::{
+    clip::Clip,
+    compression::{Tolerance, reduce_clip},
+    controller::AnimationController,
+    foot_planting::{FootPlanting, Ground, GroundMesh},
+    retarget::{NameMap, Retarget, Rig},
+    skeleton,
+    skinning::SkinningMethod,
+};
+use math::{dual_quat::DualQuat, transform::Transform};
+
+use super::node::Node;
+use super::skin::Skin;
 
 /// GPU-side animation skeleton
 /// Stores bone matrices ready for GPU skinning
+#[derive(Clone)]
 pub struct Skeleton {
     pub id: usize,
     pub bone_matrices: Vec<math::mat4::Mat4>,
+    /// filled instead of bone_matrices with dual quaternion skinning
+    pub bone_dual_quats: Vec<DualQuat>,
     pub bone_count: usize,
 }
 
@@ -12,6 +28,7 @@ impl Skeleton {
         Self {
             id,
             bone_matrices: vec![math::mat4::Mat4::IDENTITY; bone_count],
+            bone_dual_quats: vec![DualQuat::IDENTITY; bone_count],
             bone_count,
         }
     }
@@ -27,8 +44,251 @@ impl Skeleton {
     pub fn get_bone_matrices(&self) -> &[math::mat4::Mat4] {
         &self.bone_matrices
     }
+
+    /// Update a bone's dual quaternion
+    pub fn set_bone_dual_quat(&mut self, bone_index: usize, dual_quat: DualQuat) {
+        if bone_index < self.bone_dual_quats.len() {
+            self.bone_dual_quats[bone_index] = dual_quat;
+        }
+    }
+
+    /// Get all bone dual quaternions for GPU upload
+    pub fn get_bone_dual_quats(&self) -> &[DualQuat] {
+        &self.bone_dual_quats
+    }
 }
 
 /// Links a GPU model with its skeleton for animation
 /// This is what you render when the model is animated
-pub struct GPUAnimatedModel {}
\ No newline at end of file
+#[derive(Clone)]
+pub struct GPUAnimatedModel {
+    /// node holding the skinned mesh
+    pub node: usize,
+    pub skeleton: Skeleton,
+    pub controller: AnimationController,
+    /// transform of the nodes above the root joint
+    /// joint matrices are relative to it so it replaces the mesh node transform
+    root_transform: Transform,
+    /// keeps the feet on the ground when set
+    foot_planting: Option<FootPlanting>,
+    /// root joint moved by root motion, the motion is applied to root_transform
+    root_motion: Option<usize>,
+    /// clip last asked for with play_next_clip
+    clip: usize,
+    /// how the shader blends the joints of a vertex
+    skinning: SkinningMethod,
+}
+
+impl GPUAnimatedModel {
+    /// fails when the joints of the skin do not form a tree
+    pub fn new(
+        node: usize,
+        skin_index: usize,
+        skin: &Skin,
+        nodes: &[Node],
+        clips: Vec<Clip>,
+    ) -> Result<Self, String> {
+        let skeleton = skin.build_skeleton(nodes);
+        let root_transform = skin.root_transform(&skeleton, nodes);
+
+        let mut controller = AnimationController::try_new(skeleton.clone())?;
+        for clip in clips {
+            controller.add_clip(clip);
+        }
+        if controller.clip_count() > 0 {
+            controller.play(0)?;
+        }
+
+        let mut model = Self {
+            node,
+            skeleton: Skeleton::new(skin_index, skin.joints.len()),
+            controller,
+            root_transform,
+            foot_planting: None,
+            root_motion: None,
+            clip: 0,
+            skinning: SkinningMethod::LinearBlend,
+        };
+        model.update(0.0, &GroundMesh::new());
+
+        Ok(model)
+    }
+
+    /// advance the animation and refresh the bone matrices
+    /// ground is in the space of the scene root, like the root transform
+    pub fn update(&mut self, delta_time: f32, ground: &dyn Ground) {
+        self.controller.update(delta_time);
+        for err in self.controller.drain_errors() {
+            println!("unable to sample clip {}: {}", err.clip_index, err.error);
+        }
+
+        if self.root_motion.is_some() {
+            self.root_transform = self
+                .root_transform
+                .combine(self.controller.get_root_motion());
+        }
+
+        if let Some(foot_planting) = &mut self.foot_planting {
+            foot_planting.apply(self.controller.get_pose_mut(), ground, &self.root_transform);
+        }
+
+        match self.skinning {
+            SkinningMethod::LinearBlend => {
+                let matrices = self.controller.update_palette();
+                for (i, matrix) in matrices.iter().enumerate() {
+                    self.skeleton.set_bone_matrix(i, *matrix);
+                }
+            }
+            SkinningMethod::DualQuat => {
+                let dual_quats = self.controller.update_dual_quat_palette();
+                for (i, dual_quat) in dual_quats.iter().enumerate() {
+                    self.skeleton.set_bone_dual_quat(i, *dual_quat);
+                }
+            }
+        }
+    }
+
+    pub fn get_skinning(&self) -> SkinningMethod {
+        self.skinning
+    }
+
+    /// dual quaternion skinning keeps twisting joints like shoulders and wrists from collapsing
+    /// it ignores joint scaling, which linear blend skinning handles
+    pub fn set_skinning(&mut self, skinning: SkinningMethod) {
+        self.skinning = skinning;
+    }
+
+    pub fn get_root_transform(&self) -> &Transform {
+        &self.root_transform
+    }
+
+    /// move the model with the horizontal motion of its root joint instead of letting the
+    /// root joint wander away and snap back when the clip loops
+    pub fn set_root_motion(&mut self, enabled: bool) {
+        let parents = &self.controller.get_skeleton().rest_pose.parents;
+        self.root_motion = if enabled {
+            parents.iter().position(|p| *p < 0)
+        } else {
+            None
+        };
+
+        if let Err(err) = self.controller.set_root_motion(self.root_motion) {
+            println!("unable to set up root motion: {}", err);
+        }
+    }
+
+    /// the skeleton of the model for retargeting, names maps its joints to the shared names
+    pub fn get_rig(&self, names: &NameMap) -> Rig {
+        let mut rig = Rig::new(self.controller.get_skeleton().clone(), names.clone());
+        rig.space = self.root_transform;
+
+        rig
+    }
+
+    /// retarget clips made for another rig onto this model and add them to the controller
+    /// reduction: drop the baked keyframes that can be rebuilt within the tolerance
+    /// returns how many clips were added
+    pub fn add_clip_library(
+        &mut self,
+        names: &NameMap,
+        library: &Rig,
+        clips: &[Clip],
+        reduction: Option<&Tolerance>,
+    ) -> Result<usize, String> {
+        let mut retarget = Retarget::new(library, &self.get_rig(names))?;
+
+        for clip in clips {
+            // retargeted clips are baked with a key every frame, most of them can go when asked to
+            let mut clip = retarget.retarget_clip(clip, 30.0)?;
+            if let Some(tolerance) = reduction {
+                clip = reduce_clip(&clip, tolerance);
+            }
+            clip.build_lookups(60.0);
+            self.controller.add_clip(clip);
+        }
+
+        Ok(clips.len())
+    }
+
+    /// cross fade to the clip after the one last asked for, wrapping around
+    pub fn play_next_clip(&mut self) {
+        let count = self.controller.clip_count();
+        if count == 0 {
+            return;
+        }
+
+        self.clip = (self.clip + 1) % count;
+        if let Err(err) = self.controller.crossfade_to(self.clip, 0.3) {
+            println!("unable to play the next clip: {}", err);
+        }
+    }
+
+    /// turn foot planting on or off, legs are found by their humanoid joint names
+    /// returns false when the skeleton has no recognisable legs
+    pub fn set_foot_planting(&mut self, enabled: bool) -> bool {
+        if !enabled {
+            self.foot_planting = None;
+            return true;
+        }
+
+        let skeleton = self.controller.get_skeleton();
+        let find = |suffix: &str| {
+            skeleton
+                .joint_names
+                .iter()
+                .find(|name| name.ends_with(suffix))
+                .map(|name| name.as_str())
+        };
+
+        let names = ["Hips", "LeftUpLeg", "LeftLeg", "LeftFoot"]
+            .into_iter()
+            .chain(["RightUpLeg", "RightLeg", "RightFoot"])
+            .map(find)
+            .collect::<Option<Vec<&str>>>();
+        let names = match names {
+            Some(names) => names,
+            None => return false,
+        };
+
+        // ray lengths scale with the legs so they work for any model size
+        let leg_length = leg_length(skeleton, &self.root_transform, names[1], names[3]);
+
+        match FootPlanting::from_names(
+            skeleton,
+            &self.root_transform,
+            names[0],
+            &[
+                [names[1], names[2], names[3]],
+                [names[4], names[5], names[6]],
+            ],
+            leg_length * 0.5,
+            leg_length * 0.5,
+        ) {
+            Ok(foot_planting) => {
+                self.foot_planting = Some(foot_planting);
+                true
+            }
+            Err(err) => {
+                println!("unable to set up foot planting: {}", err);
+                false
+            }
+        }
+    }
+}
+
+/// distance between the hip and ankle in the rest pose, in the space of the scene root
+fn leg_length(
+    skeleton: &skeleton::Skeleton,
+    root_transform: &Transform,
+    hip: &str,
+    ankle: &str,
+) -> f32 {
+    let position = |name: &str| {
+        let joint = skeleton.get_joint_index(name).unwrap();
+        root_transform
+            .combine(&skeleton.rest_pose.get_global_tranform(joint))
+            .translation
+    };
+
+    (position(hip) - position(ankle)).len()
+}
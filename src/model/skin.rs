@@ -1,4 +1,9 @@
-use math::mat4::Mat4;
+use animation::{pose::Pose, skeleton::Skeleton};
+use math::mat4::{Mat4, transpose};
+use math::transform::Transform;
+
+use super::loader::{ColladaFile, GltfFile};
+use super::node::{Node, find_parent, global_transform};
 
 #[derive(Clone)]
 pub struct Skin {
@@ -17,19 +22,113 @@ impl Skin {
         }
     }
 
-    pub fn from_gltf(skin: &gltf::Skin) -> Self {
+    pub fn from_gltf(skin: &gltf::Skin, file: &GltfFile) -> Self {
         let mut skeleton: Option<usize> = None;
         if let Some(s) = skin.skeleton() {
             skeleton = Some(s.index());
         }
 
+        // gltf matrices are column major, ours are row major
+        let reader = skin.reader(|buffer| Some(&file.get_buffers()[buffer.index()]));
+        let mut inverse_bind_posses: Option<Vec<Mat4>> = None;
+        if let Some(matrices) = reader.read_inverse_bind_matrices() {
+            inverse_bind_posses = Some(
+                matrices
+                    .map(|matrix| transpose(&Mat4::from(&matrix)))
+                    .collect::<Vec<Mat4>>(),
+            );
+        }
+
         Self {
             joints: skin
                 .joints()
                 .map(|joint| joint.index())
                 .collect::<Vec<usize>>(),
-            inverse_bind_posses: None,
+            inverse_bind_posses,
             skeleton,
         }
     }
+
+    /// skin of the skeleton of a COLLADA file, its joints are the nodes after COLLADA_ROOT
+    /// see nodes_from_collada, None when the file has no skeleton
+    pub fn from_collada(file: &ColladaFile) -> Option<Self> {
+        let skeleton = file.get_skeleton()?;
+
+        Some(Self {
+            joints: (1..=skeleton.joints.len()).collect::<Vec<usize>>(),
+            inverse_bind_posses: Some(
+                skeleton
+                    .joints
+                    .iter()
+                    .map(|joint| Mat4::from(&joint.inverse_bind_pose))
+                    .collect::<Vec<Mat4>>(),
+            ),
+            skeleton: Some(1),
+        })
+    }
+
+    /// position of a node in the joint list
+    pub fn get_joint_index(&self, node: usize) -> Option<usize> {
+        self.joints.iter().position(|joint| *joint == node)
+    }
+
+    /// build an animation skeleton from the skin
+    /// nodes: every node in the file, used to find the joint hierarchy and rest pose
+    pub fn build_skeleton(&self, nodes: &[Node]) -> Skeleton {
+        let mut skeleton = Skeleton::new();
+
+        let len = self.joints.len();
+        let mut rest_pose = Pose::new();
+        rest_pose.resize(len);
+
+        for (i, joint) in self.joints.iter().enumerate() {
+            let node = &nodes[*joint];
+
+            rest_pose.joints[i] = node.transform;
+            rest_pose.parents[i] = self.find_parent_joint(*joint, nodes);
+
+            let name = match &node.name {
+                Some(name) => name.clone(),
+                None => format!("joint_{}", i),
+            };
+            skeleton.joint_names.push(name);
+
+            // the gltf spec treats missing inverse bind matrices as identity matrices
+            let inverse_bind_pose = match &self.inverse_bind_posses {
+                Some(matrices) => matrices[i],
+                None => Mat4::IDENTITY,
+            };
+            skeleton.inverse_bind_pose.push(Some(inverse_bind_pose));
+        }
+
+        skeleton.rest_pose = rest_pose;
+
+        skeleton
+    }
+
+    /// transform of the nodes above the root joint of a skeleton built from this skin
+    pub fn root_transform(&self, skeleton: &Skeleton, nodes: &[Node]) -> Transform {
+        match skeleton.rest_pose.parents.iter().position(|p| *p < 0) {
+            Some(root) => match find_parent(nodes, self.joints[root]) {
+                Some(parent) => global_transform(nodes, parent),
+                None => Transform::DEFAULT,
+            },
+            None => Transform::DEFAULT,
+        }
+    }
+
+    /// walk up the node hierarchy until a node that is also a joint is found
+    /// returns -1 for root joints
+    fn find_parent_joint(&self, node: usize, nodes: &[Node]) -> i32 {
+        let mut current = node;
+
+        while let Some(parent) = find_parent(nodes, current) {
+            if let Some(joint) = self.get_joint_index(parent) {
+                return joint as i32;
+            }
+            current = parent;
+        }
+
+        -1
+    }
 }
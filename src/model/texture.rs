@@ -1,9 +1,9 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use glow::HasContext;
 use image::GenericImageView;
 
-use super::loader::GltfFile;
+use super::loader::{ColladaFile, GltfFile};
 
 #[derive(Clone, Copy)]
 pub struct Texture {
@@ -20,6 +20,11 @@ impl Texture {
         Self::upload(gl, &data)
     }
 
+    /// path: an image found by images_from_collada
+    pub fn from_collada(gl: &glow::Context, path: &Path) -> Self {
+        Self::upload(gl, &TextureData::from_path(path, false))
+    }
+
     fn upload(gl: &glow::Context, cpu: &TextureData) -> Self {
         unsafe {
             let tex = gl.create_texture().expect("Failed to create texture");
@@ -114,6 +119,43 @@ fn texture_data_from_gltf(texture: &gltf::Texture, file: &GltfFile) -> TextureDa
     }
 }
 
+/// id and path of every image of a COLLADA file that exists on disk
+/// exporters often write absolute paths of the machine the file was made on,
+/// an image with the same name next to the dae file is used instead
+pub fn images_from_collada(file: &ColladaFile) -> Vec<(String, PathBuf)> {
+    let library = match file.get_library("library_images") {
+        Some(library) => library,
+        None => return Vec::new(),
+    };
+    let folder = Path::new(&file.get_folder()[..]);
+
+    let mut images = Vec::new();
+    for image in library.get_children("image", file.get_ns()) {
+        let id = image.get_attribute("id", None).unwrap_or_default();
+        // COLLADA 1.5 wraps the path in a ref element
+        let uri = match image.get_child("init_from", file.get_ns()) {
+            Some(init_from) => match init_from.get_child("ref", file.get_ns()) {
+                Some(reference) => reference.content_str(),
+                None => init_from.content_str(),
+            },
+            None => continue,
+        };
+        let uri = uri.trim().trim_start_matches("file://").replace("%20", " ");
+
+        let name = Path::new(&uri).file_name().map(|name| folder.join(name));
+        match [Some(folder.join(&uri)), name]
+            .into_iter()
+            .flatten()
+            .find(|path| path.is_file())
+        {
+            Some(path) => images.push((String::from(id), path)),
+            None => println!("image {} of folder {} not found", uri, file.get_folder()),
+        }
+    }
+
+    images
+}
+
 #[derive(Clone)]
 struct TextureData {
     width: u32,
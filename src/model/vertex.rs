@@ -1,5 +1,10 @@
+use animation::morph::{MorphTarget, morph_normal, morph_position};
+use animation::skinning::{
+    skin_normal, skin_normal_dual_quat, skin_position, skin_position_dual_quat,
+};
 use bytemuck::{Pod, Zeroable, offset_of};
 use glow::HasContext;
+use math::{dual_quat::DualQuat, mat4::Mat4};
 
 #[repr(C)]
 #[derive(Debug, Clone, Copy, Default, Pod, Zeroable)]
@@ -56,14 +61,51 @@ impl Vertex {
             );
 
             //bone ids
-            gl.enable_vertex_attrib_array(4);
-            gl.vertex_attrib_pointer_i32(
-                4,
-                4,
-                glow::FLOAT,
-                size_of::<Self>() as i32,
-                offset_of!(Self, joints) as i32,
-            );
+            //integer attribute, read as an ivec4 in the shader
+            //older contexts lack integer attributes, they skin on the cpu instead
+            if gl.version().major >= 3 {
+                gl.enable_vertex_attrib_array(4);
+                gl.vertex_attrib_pointer_i32(
+                    4,
+                    4,
+                    glow::INT,
+                    size_of::<Self>() as i32,
+                    offset_of!(Self, joints) as i32,
+                );
+            }
+        }
+    }
+
+    /// cpu reference of the morphing done in shader.vert
+    /// index: position of the vertex in its primitive, targets hold one offset per vertex
+    pub fn morphed(&self, index: usize, targets: &[MorphTarget], weights: &[f32]) -> Self {
+        if targets.is_empty() {
+            return *self;
+        }
+
+        Self {
+            position: morph_position(&self.position, index, targets, weights),
+            normal: morph_normal(&self.normal, index, targets, weights),
+            ..*self
+        }
+    }
+
+    /// cpu reference of the skinning done in shader.vert
+    /// palette: matrices returned by the animation controller
+    pub fn skinned(&self, palette: &[Mat4]) -> Self {
+        Self {
+            position: skin_position(palette, &self.position, &self.joints, &self.weights),
+            normal: skin_normal(palette, &self.normal, &self.joints, &self.weights),
+            ..*self
+        }
+    }
+
+    /// cpu reference of the dual quaternion skinning done in shader.vert
+    pub fn skinned_dual_quat(&self, palette: &[DualQuat]) -> Self {
+        Self {
+            position: skin_position_dual_quat(palette, &self.position, &self.joints, &self.weights),
+            normal: skin_normal_dual_quat(palette, &self.normal, &self.joints, &self.weights),
+            ..*self
         }
     }
 }
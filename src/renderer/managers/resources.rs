@@ -1,7 +1,21 @@
+use animation::{foot_planting::GroundMesh, retarget::NameMap, skinning::SkinningMethod};
+
 use crate::src::model::{
-    loader::GltfFile, material::Material, mesh::Mesh, node::Node, skin::Skin, texture::Texture,
+    clip::{clips_from_collada, clips_from_gltf},
+    ground::{ground_from_collada, ground_from_gltf},
+    loader::{ColladaFile, GltfFile, ModelFile},
+    material::{Material, materials_from_collada},
+    mesh::Mesh,
+    node::{Node, nodes_from_collada},
+    node_animation::NodeAnimationPlayer,
+    rig::{clip_library_from_file, name_map_from_folder},
+    skeleton::GPUAnimatedModel,
+    skin::Skin,
+    texture::{Texture, images_from_collada},
 };
 
+use super::shader::SkinningMode;
+
 #[derive(Clone)]
 pub struct ResourceManager {
     pub meshes: Vec<Mesh>,
@@ -9,6 +23,13 @@ pub struct ResourceManager {
     pub materials: Vec<Material>,
     pub nodes: Vec<Node>,
     pub skins: Vec<Skin>,
+    pub animated_models: Vec<GPUAnimatedModel>,
+    /// plays the animations of nodes that are not part of a skin, writing into `nodes`
+    pub node_player: Option<NodeAnimationPlayer>,
+    /// static geometry animated models can plant their feet on
+    pub ground: GroundMesh,
+    /// joint names of the animated models mapped to the shared names used for retargeting
+    pub names: NameMap,
 }
 impl ResourceManager {
     pub fn new() -> Self {
@@ -18,15 +39,27 @@ impl ResourceManager {
             materials: Vec::new(),
             nodes: Vec::new(),
             skins: Vec::new(),
+            animated_models: Vec::new(),
+            node_player: None,
+            ground: GroundMesh::new(),
+            names: NameMap::new(),
         }
     }
 
-    pub fn from_gltf(gl: &glow::Context, file: &GltfFile) -> Self {
+    /// skinning_mode: where skinned meshes are deformed, see ShaderManager
+    pub fn from_file(gl: &glow::Context, file: &ModelFile, skinning_mode: SkinningMode) -> Self {
+        match file {
+            ModelFile::Gltf(file) => Self::from_gltf(gl, file, skinning_mode),
+            ModelFile::Collada(file) => Self::from_collada(gl, file, skinning_mode),
+        }
+    }
+
+    pub fn from_gltf(gl: &glow::Context, file: &GltfFile, skinning_mode: SkinningMode) -> Self {
         let doc = file.get_document();
-        Self {
+        let mut resources = Self {
             meshes: (doc
                 .meshes()
-                .map(|mesh| Mesh::from_gltf(gl, &mesh, file))
+                .map(|mesh| Mesh::from_gltf(gl, &mesh, file, skinning_mode))
                 .collect::<Vec<Mesh>>()),
             textures: (doc
                 .textures()
@@ -42,8 +75,201 @@ impl ResourceManager {
                 .collect::<Vec<Node>>()),
             skins: (doc
                 .skins()
-                .map(|skin| Skin::from_gltf(&skin))
+                .map(|skin| Skin::from_gltf(&skin, file))
                 .collect::<Vec<Skin>>()),
+            animated_models: Vec::new(),
+            node_player: None,
+            ground: GroundMesh::new(),
+            names: name_map_from_folder(file.get_folder()),
+        };
+
+        // every skinned node gets its own controller
+        for (index, node) in resources.nodes.iter().enumerate() {
+            if let Some(skin_index) = node.skin {
+                let skin = &resources.skins[skin_index];
+                let mut model = match GPUAnimatedModel::new(
+                    index,
+                    skin_index,
+                    skin,
+                    &resources.nodes,
+                    clips_from_gltf(file, &skin.joints, None),
+                ) {
+                    Ok(model) => model,
+                    Err(err) => {
+                        println!("unable to animate node {}: {}", index, err);
+                        continue;
+                    }
+                };
+                // morph targets the clips do not animate keep the weights of the file
+                let weights = resources.get_rest_weights(index);
+                if !weights.is_empty() {
+                    model.controller.set_rest_weights(index as u32, weights);
+                }
+                resources.animated_models.push(model);
+            }
+        }
+
+        // the rest of the nodes share a single player, files without node animations skip it
+        match NodeAnimationPlayer::from_gltf(file, &resources.nodes, &resources.skins) {
+            Ok(mut player) if player.is_animated() => {
+                for (index, node) in resources.nodes.iter().enumerate() {
+                    let weights = resources.get_rest_weights(index);
+                    if node.skin.is_none() && !weights.is_empty() {
+                        player.set_rest_weights(index, weights);
+                    }
+                }
+                resources.node_player = Some(player);
+            }
+            Ok(_) => {}
+            Err(err) => println!("unable to animate the nodes: {}", err),
+        }
+
+        // the ground is built from the rest transforms, animated nodes do not move it
+        resources.ground = ground_from_gltf(file, &resources.nodes);
+
+        resources
+    }
+
+    /// COLLADA files hold at most one skeleton, every skinned mesh is bound to it
+    /// see nodes_from_collada for the layout of the nodes
+    pub fn from_collada(
+        gl: &glow::Context,
+        file: &ColladaFile,
+        skinning_mode: SkinningMode,
+    ) -> Self {
+        let images = images_from_collada(file);
+        let (materials, material_indices) = materials_from_collada(file, &images);
+
+        let mut resources = Self {
+            meshes: file
+                .get_objects()
+                .iter()
+                .map(|object| {
+                    Mesh::from_collada(gl, object, file, &material_indices, skinning_mode)
+                })
+                .collect::<Vec<Mesh>>(),
+            textures: images
+                .iter()
+                .map(|(_, path)| Texture::from_collada(gl, path))
+                .collect::<Vec<Texture>>(),
+            materials,
+            nodes: nodes_from_collada(file),
+            skins: Skin::from_collada(file).into_iter().collect::<Vec<Skin>>(),
+            animated_models: Vec::new(),
+            node_player: None,
+            ground: GroundMesh::new(),
+            names: name_map_from_folder(file.get_folder()),
+        };
+
+        for (index, node) in resources.nodes.iter().enumerate() {
+            if let Some(skin_index) = node.skin {
+                let skin = &resources.skins[skin_index];
+                match GPUAnimatedModel::new(
+                    index,
+                    skin_index,
+                    skin,
+                    &resources.nodes,
+                    clips_from_collada(file, &resources.nodes, &skin.joints, None),
+                ) {
+                    Ok(model) => resources.animated_models.push(model),
+                    Err(err) => println!("unable to animate node {}: {}", index, err),
+                }
+            }
+        }
+
+        resources.ground = ground_from_collada(file, &resources.nodes);
+
+        resources
+    }
+
+    pub fn update_animations(&mut self, delta_time: f32) {
+        for model in &mut self.animated_models {
+            model.update(delta_time, &self.ground);
+        }
+
+        if let Some(player) = &mut self.node_player {
+            player.update(delta_time, &mut self.nodes);
+        }
+    }
+
+    /// retarget the clips of another file onto every animated model
+    pub fn add_clip_library(&mut self, file: &ModelFile) {
+        let (library, clips) = match clip_library_from_file(file) {
+            Ok(library) => library,
+            Err(err) => {
+                println!("unable to load clip library: {}", err);
+                return;
+            }
+        };
+
+        for model in &mut self.animated_models {
+            if let Err(err) = model.add_clip_library(&self.names, &library, &clips, None) {
+                println!("unable to retarget clips onto node {}: {}", model.node, err);
+            }
+        }
+    }
+
+    pub fn play_next_clip(&mut self) {
+        for model in &mut self.animated_models {
+            model.play_next_clip();
+        }
+
+        if let Some(player) = &mut self.node_player {
+            player.play_next_clip();
+        }
+    }
+
+    pub fn set_root_motion(&mut self, enabled: bool) {
+        for model in &mut self.animated_models {
+            model.set_root_motion(enabled);
+        }
+    }
+
+    /// turn foot planting on or off for every animated model that has legs
+    pub fn set_foot_planting(&mut self, enabled: bool) {
+        for model in &mut self.animated_models {
+            if !model.set_foot_planting(enabled) {
+                println!("node {} has no legs to plant", model.node);
+            }
+        }
+    }
+
+    pub fn set_skinning(&mut self, skinning: SkinningMethod) {
+        for model in &mut self.animated_models {
+            model.set_skinning(skinning);
+        }
+    }
+
+    pub fn get_animated_model(&self, node: usize) -> Option<&GPUAnimatedModel> {
+        self.animated_models.iter().find(|model| model.node == node)
+    }
+
+    /// morph target weights of the mesh held by a node, animated ones when a controller drives them
+    pub fn get_weights(&self, node: usize) -> &[f32] {
+        let animated = match self.get_animated_model(node) {
+            Some(model) => model.controller.get_weights().get(node as u32),
+            None => self
+                .node_player
+                .as_ref()
+                .and_then(|player| player.get_weights().get(node as u32)),
+        };
+
+        match animated {
+            Some(weights) => weights,
+            None => self.get_rest_weights(node),
+        }
+    }
+
+    /// weights of the node itself, else the default weights of its mesh
+    fn get_rest_weights(&self, node: usize) -> &[f32] {
+        let node = self.get_node(node);
+        if let Some(weights) = &node.weights {
+            return weights;
+        }
+
+        match node.mesh {
+            Some(mesh) => self.get_mesh(mesh).get_weights(),
+            None => &[],
         }
     }
 
@@ -2,8 +2,11 @@ use crate::src::model::material::Material;
 use crate::src::model::texture::Texture;
 use crate::src::viewer::camera::Camera;
 use crate::src::viewer::light::PointLight;
-use math::{mat4::Mat4, vec3::Vec3, vec4::Vec4};
+use animation::morph::MAX_MORPH_TARGETS;
+use glow::HasContext;
+use math::{dual_quat::DualQuat, mat4::Mat4, vec3::Vec3, vec4::Vec4};
 use std::collections::HashMap;
+use std::fs;
 
 use crate::src::renderer::shader::Program;
 
@@ -19,10 +22,14 @@ pub enum UniformLevel {
 pub enum UniformValue {
     Int(i32),
     Float(f32),
+    FloatArray(Vec<f32>),
     Vector3f(Vec3),
     Vector4f(Vec4),
     Matrix4x4(Mat4),
+    Matrix4x4Array(Vec<Mat4>),
     Texture(Texture, u32),
+    /// float texture holding data rather than an image, like the morph target offsets
+    DataTexture(glow::NativeTexture, u32),
 }
 
 #[derive(Clone)]
@@ -42,10 +49,20 @@ impl Uniform {
     }
 }
 
+/// where skinned vertices get deformed
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SkinningMode {
+    /// bone matrices are uploaded and shader.vert does the skinning
+    Gpu,
+    /// vertices are skinned on the cpu and streamed into the vertex buffer every frame
+    Cpu,
+}
+
 #[derive(Clone)]
 pub struct ShaderManager {
     program: Program,
     uniforms: HashMap<String, Uniform>,
+    skinning_mode: SkinningMode,
 }
 
 impl ShaderManager {
@@ -53,23 +70,57 @@ impl ShaderManager {
         let vert_src = String::from("shaders/shader.vert");
         let frag_src = String::from("shaders/shader.frag");
 
-        let program = Program::from_files(gl, &vert_src[..], &frag_src[..]);
+        // older desktop and gles contexts can't compile the gpu skinning shader
+        let (program, skinning_mode) =
+            match Program::try_from_files(gl, &vert_src[..], &frag_src[..]) {
+                Ok(program) => (program, SkinningMode::Gpu),
+                Err(err) => {
+                    eprintln!("falling back to cpu skinning: {}", err);
+                    (Self::legacy_program(gl), SkinningMode::Cpu)
+                }
+            };
 
         program.bind(gl);
         program.set_uniform_i32(gl, "albedoMap", 0);
         program.set_uniform_i32(gl, "metallicMap", 1);
         program.set_uniform_i32(gl, "normalMap", 2);
+        program.set_uniform_i32(gl, "morphTargets", 3);
 
         Self {
             program,
             uniforms: HashMap::new(),
+            skinning_mode,
         }
     }
 
+    fn legacy_program(gl: &glow::Context) -> Program {
+        let vert_src =
+            fs::read_to_string("shaders/legacy.vert").expect("failed to read vertex shader");
+        let frag_src =
+            fs::read_to_string("shaders/legacy.frag").expect("failed to read fragment shader");
+
+        let vert_src = legacy_shader_header(gl, glow::VERTEX_SHADER) + &vert_src;
+        let frag_src = legacy_shader_header(gl, glow::FRAGMENT_SHADER) + &frag_src;
+
+        Program::try_from_src(
+            gl,
+            &[
+                (&vert_src[..], glow::VERTEX_SHADER),
+                (&frag_src[..], glow::FRAGMENT_SHADER),
+            ],
+            &[(0, "pos"), (1, "norm"), (2, "uv")],
+        )
+        .unwrap_or_else(|err| panic!("{}", err))
+    }
+
     pub fn bind_shader(&self, gl: &glow::Context) {
         self.program.bind(gl);
     }
 
+    pub fn get_skinning_mode(&self) -> SkinningMode {
+        self.skinning_mode
+    }
+
     pub fn update_camera_uniforms(&mut self, camera: &Camera, ratio: f32) -> &mut Self {
         self.set_camera_pos_uniform(camera.pos)
             .set_view_uniform(&camera.get_view())
@@ -176,7 +227,7 @@ impl ShaderManager {
     pub fn set_animated_uniform(&mut self, value: bool) -> &mut Self {
         self.set_uniform(
             "animated",
-            Uniform::new(UniformLevel::Model, UniformValue::Int(value as i32)),
+            Uniform::new(UniformLevel::Node, UniformValue::Int(value as i32)),
         );
         self
     }
@@ -220,7 +271,89 @@ impl ShaderManager {
         self
     }
 
-    pub fn set_skeleton_matrices() {}
+    pub fn set_skeleton_matrices(&mut self, matrices: &[Mat4]) -> &mut Self {
+        self.set_uniform(
+            "boneMats",
+            Uniform::new(
+                UniformLevel::Node,
+                UniformValue::Matrix4x4Array(matrices.to_vec()),
+            ),
+        );
+        self
+    }
+
+    /// dual quaternions go through the bone matrices, the real part in the first column
+    /// and the dual part in the second
+    pub fn set_skeleton_dual_quats(&mut self, dual_quats: &[DualQuat]) -> &mut Self {
+        let matrices = dual_quats
+            .iter()
+            .map(|dual_quat| {
+                let mut matrix = Mat4::IDENTITY;
+                // rows of the data end up as columns in the shader
+                matrix.data[0] = dual_quat.real.to_array();
+                matrix.data[1] = dual_quat.dual.to_array();
+                matrix
+            })
+            .collect::<Vec<Mat4>>();
+
+        self.set_uniform(
+            "boneMats",
+            Uniform::new(UniformLevel::Node, UniformValue::Matrix4x4Array(matrices)),
+        );
+        self
+    }
+
+    pub fn set_dual_quat_skinning_uniform(&mut self, value: bool) -> &mut Self {
+        self.set_uniform(
+            "dualQuatSkinning",
+            Uniform::new(UniformLevel::Node, UniformValue::Int(value as i32)),
+        );
+        self
+    }
+
+    /// morph targets of the primitive about to be drawn, weights past its targets are ignored
+    /// primitives without a morph texture draw without morphing
+    pub fn set_morph_targets_uniform(
+        &mut self,
+        texture: Option<glow::NativeTexture>,
+        target_count: usize,
+        vertex_count: usize,
+        weights: &[f32],
+    ) -> &mut Self {
+        let count = match texture {
+            Some(_) => target_count.min(weights.len()).min(MAX_MORPH_TARGETS),
+            None => 0,
+        };
+
+        self.set_uniform(
+            "morphTargetCount",
+            Uniform::new(UniformLevel::Primitive, UniformValue::Int(count as i32)),
+        );
+        if let Some(texture) = texture {
+            self.set_uniform(
+                "morphTargets",
+                Uniform::new(
+                    UniformLevel::Primitive,
+                    UniformValue::DataTexture(texture, 3),
+                ),
+            );
+            self.set_uniform(
+                "morphVertexCount",
+                Uniform::new(
+                    UniformLevel::Primitive,
+                    UniformValue::Int(vertex_count as i32),
+                ),
+            );
+            self.set_uniform(
+                "morphWeights",
+                Uniform::new(
+                    UniformLevel::Primitive,
+                    UniformValue::FloatArray(weights[..count].to_vec()),
+                ),
+            );
+        }
+        self
+    }
 
     pub fn update_per_frame_uniforms(&self, gl: &glow::Context) {
         self.uniforms.iter().for_each(|uniform| {
@@ -263,6 +396,12 @@ impl ShaderManager {
                 self.program.set_uniform_f32(gl, name, *value);
             }
 
+            UniformValue::FloatArray(values) => {
+                if !values.is_empty() {
+                    self.program.set_uniform_f32_slice(gl, name, values);
+                }
+            }
+
             UniformValue::Vector3f(value) => {
                 self.program.set_uniform_vec3(gl, name, &value.to_array());
             }
@@ -276,9 +415,23 @@ impl ShaderManager {
                     .set_uniform_mat4(gl, name, value.data.as_flattened());
             }
 
+            UniformValue::Matrix4x4Array(values) => {
+                let data = values
+                    .iter()
+                    .flat_map(|value| value.data.as_flattened())
+                    .copied()
+                    .collect::<Vec<f32>>();
+                self.program.set_uniform_mat4(gl, name, &data);
+            }
+
             UniformValue::Texture(texture, unit) => {
                 texture.bind_to_uint(gl, *unit);
             }
+
+            UniformValue::DataTexture(texture, unit) => unsafe {
+                gl.active_texture(glow::TEXTURE0 + unit);
+                gl.bind_texture(glow::TEXTURE_2D, Some(*texture));
+            },
         }
     }
 
@@ -289,3 +442,23 @@ impl ShaderManager {
             .or_insert(value);
     }
 }
+
+/// version line and compatibility macros for the legacy shaders
+/// they are written in gles 1.00 / glsl 1.20 style, core profiles need a few renames
+fn legacy_shader_header(gl: &glow::Context, stage: u32) -> String {
+    let version = gl.version();
+
+    let header = if version.is_embedded {
+        "#version 100\nprecision highp float;\n#define fragColor gl_FragColor\n"
+    } else if (version.major, version.minor) >= (3, 3) {
+        if stage == glow::VERTEX_SHADER {
+            "#version 330 core\n#define attribute in\n#define varying out\n"
+        } else {
+            "#version 330 core\n#define varying in\n#define texture2D texture\nout vec4 fragColor;\n"
+        }
+    } else {
+        "#version 120\n#define fragColor gl_FragColor\n"
+    };
+
+    String::from(header)
+}
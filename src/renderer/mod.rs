@@ -2,13 +2,17 @@ pub mod command;
 pub mod managers;
 pub mod shader;
 
+use animation::skinning::SkinningMethod;
 use glow::HasContext;
 use math::transform::Transform;
 
-use managers::{resources::ResourceManager, shader::ShaderManager};
+use managers::{
+    resources::ResourceManager,
+    shader::{ShaderManager, SkinningMode},
+};
 
 use crate::src::{
-    model::{loader::GltfFile, material::Material, mesh::Mesh, scene::Scene},
+    model::{loader::ModelFile, material::Material, mesh::Mesh, scene::Scene},
     viewer::{camera::Camera, light::PointLight},
 };
 
@@ -22,10 +26,14 @@ pub struct Renderer {
 }
 
 impl Renderer {
-    pub fn new(gl: &glow::Context, file: &GltfFile) -> Self {
+    pub fn new(gl: &glow::Context, file: &ModelFile) -> Self {
+        // the shader decides where skinning happens, the meshes only keep what that needs
+        let shader_manager = ShaderManager::new(gl);
+        let skinning_mode = shader_manager.get_skinning_mode();
+
         Self {
-            resource_manager: ResourceManager::from_gltf(gl, &file),
-            shader_manager: ShaderManager::new(gl),
+            resource_manager: ResourceManager::from_file(gl, file, skinning_mode),
+            shader_manager,
             background_color: color_rgb(0.4, 0.4, 0.8),
         }
     }
@@ -34,10 +42,32 @@ impl Renderer {
         self.background_color = color_rgb(r, g, b);
     }
 
+    pub fn update(&mut self, delta_time: f32) {
+        self.resource_manager.update_animations(delta_time);
+    }
+
+    pub fn add_clip_library(&mut self, file: &ModelFile) {
+        self.resource_manager.add_clip_library(file);
+    }
+
+    pub fn play_next_clip(&mut self) {
+        self.resource_manager.play_next_clip();
+    }
+
+    pub fn set_root_motion(&mut self, enabled: bool) {
+        self.resource_manager.set_root_motion(enabled);
+    }
+
+    pub fn set_foot_planting(&mut self, enabled: bool) {
+        self.resource_manager.set_foot_planting(enabled);
+    }
+
+    pub fn set_skinning(&mut self, skinning: SkinningMethod) {
+        self.resource_manager.set_skinning(skinning);
+    }
+
     fn render_scene(&mut self, gl: &glow::Context, scene: &Scene, scene_tranform: &Transform) {
-        self.shader_manager
-            .set_animated_uniform(false)
-            .update_per_model_uniforms(gl);
+        self.shader_manager.update_per_model_uniforms(gl);
 
         for node in &scene.nodes {
             render_node(
@@ -45,7 +75,8 @@ impl Renderer {
                 *node,
                 &self.resource_manager,
                 &mut self.shader_manager,
-                &scene_tranform,
+                scene_tranform,
+                scene_tranform,
             );
         }
     }
@@ -100,6 +131,7 @@ fn render_node(
     node_index: usize,
     resources: &ResourceManager,
     shader: &mut ShaderManager,
+    scene_transform: &Transform,
     parent_transform: &Transform,
 ) {
     let node = resources.get_node(node_index);
@@ -107,14 +139,76 @@ fn render_node(
     let world_tranform = parent_transform.combine(&node.transform);
 
     if let Some(mesh_index) = node.mesh {
-        shader
-            .set_transform_uniform(&world_tranform.to_mat())
-            .update_per_node_uniforms(gl);
+        let weights = resources.get_weights(node_index);
 
-        render_mesh(gl, resources, shader, resources.get_mesh(mesh_index));
+        // skinned meshes ignore their node transform,
+        // the joint matrices already place them relative to the skeleton root
+        if let Some(model) = resources.get_animated_model(node_index) {
+            let model_transform = scene_transform.combine(model.get_root_transform());
+            shader.set_transform_uniform(&model_transform.to_mat());
+
+            let skeleton = &model.skeleton;
+            let dual_quat = model.get_skinning() == SkinningMethod::DualQuat;
+            match shader.get_skinning_mode() {
+                SkinningMode::Gpu => {
+                    shader
+                        .set_animated_uniform(true)
+                        .set_dual_quat_skinning_uniform(dual_quat);
+                    if dual_quat {
+                        shader.set_skeleton_dual_quats(skeleton.get_bone_dual_quats());
+                    } else {
+                        shader.set_skeleton_matrices(skeleton.get_bone_matrices());
+                    }
+                }
+                SkinningMode::Cpu => {
+                    shader.set_animated_uniform(false);
+                    let mesh = resources.get_mesh(mesh_index);
+                    mesh.get_primitives().iter().for_each(|primitive| {
+                        if dual_quat {
+                            primitive.update_dual_quat_skinning(
+                                gl,
+                                skeleton.get_bone_dual_quats(),
+                                weights,
+                            );
+                        } else {
+                            primitive.update_skinning(gl, skeleton.get_bone_matrices(), weights);
+                        }
+                    });
+                }
+            }
+        } else {
+            shader
+                .set_transform_uniform(&world_tranform.to_mat())
+                .set_animated_uniform(false)
+                .set_dual_quat_skinning_uniform(false);
+
+            if shader.get_skinning_mode() == SkinningMode::Cpu {
+                let mesh = resources.get_mesh(mesh_index);
+                mesh.get_primitives()
+                    .iter()
+                    .filter(|primitive| !primitive.get_morph_targets().is_empty())
+                    .for_each(|primitive| primitive.update_morphing(gl, weights));
+            }
+        }
+        shader.update_per_node_uniforms(gl);
+
+        render_mesh(
+            gl,
+            resources,
+            shader,
+            resources.get_mesh(mesh_index),
+            weights,
+        );
     }
     node.children.iter().for_each(|child| {
-        render_node(gl, *child, resources, shader, &world_tranform);
+        render_node(
+            gl,
+            *child,
+            resources,
+            shader,
+            scene_transform,
+            &world_tranform,
+        );
     });
 }
 
@@ -123,6 +217,7 @@ fn render_mesh(
     resources: &ResourceManager,
     shader: &mut ShaderManager,
     mesh: &Mesh,
+    weights: &[f32],
 ) {
     shader.bind_shader(gl);
 
@@ -144,6 +239,18 @@ fn render_mesh(
             shader.set_metallic_map_uniform(texture);
         }
 
+        // the cpu path already streamed the morphed vertices
+        let morph_texture = match shader.get_skinning_mode() {
+            SkinningMode::Gpu => primitive.get_morph_texture(),
+            SkinningMode::Cpu => None,
+        };
+        shader.set_morph_targets_uniform(
+            morph_texture,
+            primitive.get_morph_targets().len(),
+            primitive.get_vert_count(),
+            weights,
+        );
+
         shader.update_per_primitive_uniforms(gl);
 
         primitive.draw(gl);
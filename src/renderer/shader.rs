@@ -9,39 +9,67 @@ pub struct Program {
 
 impl Program {
     pub fn from_files(gl: &glow::Context, vert_src: &str, frag_src: &str) -> Self {
+        Self::try_from_files(gl, vert_src, frag_src).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// same as `from_files` but compile and link errors are returned instead of panicking
+    pub fn try_from_files(
+        gl: &glow::Context,
+        vert_src: &str,
+        frag_src: &str,
+    ) -> Result<Self, String> {
         let vert_src = fs::read_to_string(vert_src).expect("failed to read vertex shader");
         let frag_src = fs::read_to_string(frag_src).expect("failed to read fragment shader");
 
-        Self::from_src(
+        Self::try_from_src(
             gl,
             [
                 (&vert_src[..], glow::VERTEX_SHADER),
                 (&frag_src[..], glow::FRAGMENT_SHADER),
             ]
             .as_ref(),
+            &[],
         )
     }
 
     pub fn from_src(gl: &glow::Context, shaders: &[(&str, u32)]) -> Self {
+        Self::try_from_src(gl, shaders, &[]).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// attributes: explicit (location, name) bindings for shaders without layout qualifiers
+    pub fn try_from_src(
+        gl: &glow::Context,
+        shaders: &[(&str, u32)],
+        attributes: &[(u32, &str)],
+    ) -> Result<Self, String> {
         unsafe {
             let program = gl.create_program().expect("cannot create program");
 
             for src in shaders {
-                let shader = Shader::from_shader(gl, src.0, src.1);
+                let shader = match Shader::try_from_shader(gl, src.0, src.1) {
+                    Ok(shader) => shader,
+                    Err(err) => {
+                        gl.delete_program(program);
+                        return Err(err);
+                    }
+                };
                 gl.attach_shader(program, shader.id);
                 shader.delete(gl);
             }
 
+            for (location, name) in attributes {
+                gl.bind_attrib_location(program, *location, name);
+            }
+
             gl.link_program(program);
 
             if !gl.get_program_link_status(program) {
-                panic!(
-                    "failed to link shader program: {}",
-                    gl.get_program_info_log(program)
-                );
+                let log = gl.get_program_info_log(program);
+                gl.delete_program(program);
+                return Err(format!("failed to link shader program: {}", log));
             }
 
-            Self { id: program }
+            Ok(Self { id: program })
         }
     }
 
@@ -77,6 +105,16 @@ impl Program {
         }
     }
 
+    pub fn set_uniform_f32_slice(&self, gl: &glow::Context, name: &str, values: &[f32]) {
+        unsafe {
+            let loc = gl.get_uniform_location(self.id, name);
+
+            if let Some(loc) = loc {
+                gl.uniform_1_f32_slice(Some(&loc), values);
+            }
+        }
+    }
+
     pub fn set_uniform_mat4(&self, gl: &glow::Context, name: &str, mat: &[f32]) {
         unsafe {
             let loc = gl.get_uniform_location(self.id, name);
@@ -114,16 +152,18 @@ struct Shader {
 }
 
 impl Shader {
-    pub fn from_shader(gl: &glow::Context, src: &str, stage: u32) -> Self {
+    pub fn try_from_shader(gl: &glow::Context, src: &str, stage: u32) -> Result<Self, String> {
         unsafe {
             let id = gl.create_shader(stage).expect("cannot create shader");
             gl.shader_source(id, src);
             gl.compile_shader(id);
             if !gl.get_shader_compile_status(id) {
-                panic!("failed to compile shader: {}", gl.get_shader_info_log(id));
+                let log = gl.get_shader_info_log(id);
+                gl.delete_shader(id);
+                return Err(format!("failed to compile shader: {}", log));
             }
 
-            Self { id }
+            Ok(Self { id })
         }
     }
 
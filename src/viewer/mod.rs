@@ -1,8 +1,10 @@
 pub mod camera;
 pub mod light;
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 
+use animation::skinning::SkinningMethod;
 use camera::Camera;
 use light::PointLightManager;
 
@@ -14,7 +16,7 @@ use math::{
 };
 
 use crate::src::{
-    model::{loader::GltfFile, scene::Scene},
+    model::{loader::ModelFile, scene::Scene},
     renderer::Renderer,
     viewer::{camera::Direction, light::PointLight},
 };
@@ -27,6 +29,12 @@ pub struct Viewer {
     pub scenes: Vec<Scene>,
     pub current_scene: usize,
     pub renderer: Renderer,
+    last_update: Instant,
+    foot_planting: bool,
+    root_motion: bool,
+    dual_quat_skinning: bool,
+    /// folder of a clip library not loaded yet, see set_clip_library
+    clip_library: Option<PathBuf>,
 }
 
 impl Viewer {
@@ -44,12 +52,15 @@ impl Viewer {
         transform.orientation = Quat::create(180.0, vec3(0.0, 1.0, 0.0));
         transform.translation = vec3(0.0, 2.0, 5.0);
 
-        let file = GltfFile::load_gltf(folder).unwrap();
-        let scenes = file
-            .get_document()
-            .scenes()
-            .map(|scene| Scene::from_gltf(&scene))
-            .collect::<Vec<Scene>>();
+        let file = ModelFile::load(folder).unwrap();
+        let scenes = match &file {
+            ModelFile::Gltf(file) => file
+                .get_document()
+                .scenes()
+                .map(|scene| Scene::from_gltf(&scene))
+                .collect::<Vec<Scene>>(),
+            ModelFile::Collada(_) => vec![Scene::from_collada()],
+        };
 
         Self {
             camera: Camera::default(),
@@ -58,11 +69,21 @@ impl Viewer {
             scenes,
             current_scene: 0,
             renderer: Renderer::new(gl, &file),
+            last_update: Instant::now(),
+            foot_planting: false,
+            root_motion: false,
+            dual_quat_skinning: false,
+            clip_library: None,
         }
     }
 
     pub fn update(&mut self) {
+        let now = Instant::now();
+        let delta_time = now.duration_since(self.last_update).as_secs_f32();
+        self.last_update = now;
+
         self.camera.update_motion();
+        self.renderer.update(delta_time);
     }
 
     pub fn run_renderer(&mut self, gl: &glow::Context, window_ratio: f32) {
@@ -79,6 +100,49 @@ impl Viewer {
         );
     }
 
+    pub fn toggle_foot_planting(&mut self) {
+        self.foot_planting = !self.foot_planting;
+        self.renderer.set_foot_planting(self.foot_planting);
+    }
+
+    pub fn toggle_root_motion(&mut self) {
+        self.root_motion = !self.root_motion;
+        self.renderer.set_root_motion(self.root_motion);
+    }
+
+    /// switch the animated models between linear blend and dual quaternion skinning
+    pub fn toggle_dual_quat_skinning(&mut self) {
+        self.dual_quat_skinning = !self.dual_quat_skinning;
+        self.renderer.set_skinning(if self.dual_quat_skinning {
+            SkinningMethod::DualQuat
+        } else {
+            SkinningMethod::LinearBlend
+        });
+    }
+
+    /// play the clips of the model in `folder` on the models of this viewer
+    pub fn add_clip_library(&mut self, folder: &Path) {
+        match ModelFile::load(folder) {
+            Ok(file) => self.renderer.add_clip_library(&file),
+            Err(err) => println!("unable to load {}: {}", folder.display(), err),
+        }
+    }
+
+    /// like add_clip_library but the model is only loaded the first time play_next_clip is called,
+    /// so starting the viewer does not pay for a second model that may never be used
+    pub fn set_clip_library(&mut self, folder: &Path) {
+        self.clip_library = Some(folder.to_path_buf());
+    }
+
+    pub fn play_next_clip(&mut self) {
+        // a library that fails to load is not tried again
+        if let Some(folder) = self.clip_library.take() {
+            self.add_clip_library(&folder);
+        }
+
+        self.renderer.play_next_clip();
+    }
+
     pub fn set_scene(&mut self, index: usize) {
         if !(index >= self.scenes.len()) {
             self.current_scene = index;